@@ -0,0 +1,40 @@
+//! Run `msbuild -version` using the newest installed instance.
+//!
+//! Use `cargo run --example msbuild_locator` to compile and run this.
+
+use std::ffi::OsStr;
+
+use vssetup::{HRESULT, SetupConfiguration, com, hresults};
+
+fn main() -> Result<(), HRESULT> {
+    com::initialize()?;
+
+    let setup = match SetupConfiguration::new() {
+        Ok(setup) => setup,
+        Err(e) if hresults::is_installer_missing(e) => {
+            println!("Visual Studio is not installed");
+            return Ok(());
+        }
+        Err(e) => return Err(e),
+    };
+    let Some(instance) = setup.latest()? else {
+        println!("No Visual Studio instances found");
+        return Ok(());
+    };
+
+    println!(
+        "Using {} {}",
+        instance.installation_path()?.display(),
+        instance.installation_version()?
+    );
+    match instance.msbuild_path()? {
+        Some(msbuild) => println!("MSBuild: {}", msbuild.display()),
+        None => {
+            println!("MSBuild is not installed in this instance");
+            return Ok(());
+        }
+    }
+    let status = instance.run_msbuild(&[OsStr::new("-version")])?;
+    println!("MSBuild exited with {status}");
+    Ok(())
+}
@@ -0,0 +1,23 @@
+//! Print the installer policies that apply to this machine.
+//!
+//! Use `cargo run --example policy` to compile and run this.
+
+use vssetup::strings::bstr_to_pathbuf;
+use vssetup::{HRESULT, SetupConfiguration, SetupPolicy, com};
+
+fn main() -> Result<(), HRESULT> {
+    com::initialize()?;
+
+    let policy = SetupConfiguration::new()?.to_policy()?;
+    println!(
+        "Shared installation path: {}",
+        bstr_to_pathbuf(&policy.get_shared_installation_path()?).display()
+    );
+    for name in SetupPolicy::KNOWN_NAMES {
+        match policy.get(name) {
+            Ok(value) => println!("{name}: {value:?}"),
+            Err(error) => println!("{name}: not set ({error})"),
+        }
+    }
+    Ok(())
+}
@@ -4,7 +4,8 @@
 //! If you also want to list all packages then use the `--packages` arguments.
 //! E.g. `cargo run --example vsall -- --packages`.
 
-use vssetup::{HRESULT, SetupConfiguration, com};
+use vssetup::prelude::*;
+use vssetup::strings::{bstr_to_pathbuf, bstr_to_string_lossy};
 
 fn main() -> Result<(), HRESULT> {
     com::initialize()?;
@@ -28,17 +29,14 @@ fn main() -> Result<(), HRESULT> {
         }
         println!(
             "displayName: {}",
-            instance.GetDisplayName(0x400)?.to_string()
+            bstr_to_string_lossy(&instance.GetDisplayName(Lcid::USER_DEFAULT)?)
         );
         println!(
             "description: {}",
-            instance.GetDescription(0x400)?.to_string()
+            bstr_to_string_lossy(&instance.GetDescription(Lcid::USER_DEFAULT)?)
         );
         println!("instanceId: {}", instance.GetInstanceId()?);
-        println!(
-            "installDate: FILETIME({})",
-            &instance.GetInstallDate()?.as_u64()
-        );
+        println!("installDate: {}", instance.GetInstallDate()?);
         println!("installationPath: {}", instance.GetInstallationPath()?);
         println!(
             "installationVersion: {}",
@@ -46,7 +44,10 @@ fn main() -> Result<(), HRESULT> {
         );
         println!("state: {}", instance.GetState()?);
         println!("enginePath: {}", instance.GetEnginePath()?);
-        println!("productPath: {}", instance.GetProductPath()?.to_string());
+        println!(
+            "productPath: {}",
+            bstr_to_pathbuf(&instance.GetProductPath()?).display()
+        );
         if let Ok(Some(product)) = instance.GetProduct() {
             println!("product: {{");
             println!("    id: {}", product.GetId()?);
@@ -64,6 +65,10 @@ fn main() -> Result<(), HRESULT> {
             );
             println!("}}");
         }
+        // Only set if the user gave the install a nickname.
+        if let Ok(Some(nickname)) = instance.nickname() {
+            println!("nickname: {nickname}");
+        }
         if let Ok(properties) = instance.to_property_store() {
             println!("propertyStore: {{");
             for property in properties.GetNames()?.iter() {
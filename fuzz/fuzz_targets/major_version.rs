@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use vssetup::fuzzing::major_version;
+
+fuzz_target!(|s: &str| {
+    let wide: Vec<u16> = s.encode_utf16().collect();
+    let _ = major_version(&wide);
+});
@@ -0,0 +1,14 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use vssetup::fuzzing::normalize_path;
+
+fuzz_target!(|data: &[u8]| {
+    let path: Vec<u16> = data
+        .chunks_exact(2)
+        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+        .collect();
+    let normalized = normalize_path(&path);
+    // Normalizing twice must not change anything.
+    assert_eq!(normalize_path(&normalized), normalized);
+});
@@ -0,0 +1,10 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use vssetup::fuzzing::is_toolset_component;
+
+// The input is a component id and a version prefix, separated by a newline.
+fuzz_target!(|s: &str| {
+    let (id, prefix) = s.split_once('\n').unwrap_or((s, "14.29"));
+    let _ = is_toolset_component(id, prefix);
+});
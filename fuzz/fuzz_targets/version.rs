@@ -0,0 +1,24 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use vssetup::PackedVersion;
+
+fuzz_target!(|s: &str| {
+    if let Ok(version) = s.parse::<PackedVersion>() {
+        // Display always gives all four components, which must parse back to the same version.
+        let dotted = format!("{version}");
+        assert_eq!(dotted.parse::<PackedVersion>(), Ok(version));
+        assert_eq!(
+            PackedVersion::new(
+                version.major(),
+                version.minor(),
+                version.build(),
+                version.revision()
+            ),
+            version
+        );
+    }
+    if let Ok(range) = PackedVersion::parse_range(s) {
+        assert!(range.start() <= range.end());
+    }
+});
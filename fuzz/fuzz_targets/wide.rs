@@ -0,0 +1,19 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use vssetup::fuzzing::encode_wide;
+
+fuzz_target!(|data: &[u8]| {
+    let units: Vec<u16> = data
+        .chunks_exact(2)
+        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+        .collect();
+    match encode_wide(&units) {
+        Ok(wide) => {
+            let (nul, rest) = wide.split_last().unwrap();
+            assert_eq!(*nul, 0);
+            assert_eq!(rest, &units[..]);
+        }
+        Err(_) => assert!(units.contains(&0)),
+    }
+});
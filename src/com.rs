@@ -6,13 +6,25 @@
 //! **WARNGING**: Using any API in this crate after COM is unitilized is Undefined Behaviour (UB).
 //! If in doubt it is safer to simply not call [`uninitialize`].
 
+use core::sync::atomic::{AtomicBool, Ordering};
+
 use windows_result::HRESULT;
 
+use crate::sys::{CoInitializeEx, CoUnInitialize};
+
+/// `HRESULT_FROM_WIN32(ERROR_POSSIBLE_DEADLOCK)`
+///
+/// Returned by [`try_initialize_nonblocking`] when the host has said it's inside `DllMain`.
+pub const E_POSSIBLE_DEADLOCK: HRESULT = HRESULT(0x8007046B_u32 as i32);
+
+static IN_DLL_MAIN: AtomicBool = AtomicBool::new(false);
+
 /// Runs the given function with COM initalized and uninitalizes COM afterward.
 ///
 /// # Safety
 ///
-/// See [`uninitialize`].
+/// See [`uninitialize`]. The result must not hold any COM objects,
+/// as they'd be released after COM is uninitialized.
 pub unsafe fn with_com<R, F: FnOnce() -> R>(f: F) -> Result<R, HRESULT> {
     initialize()?;
     let result = f();
@@ -29,6 +41,113 @@ pub fn initialize() -> Result<(), HRESULT> {
     if result.is_ok() { Ok(()) } else { Err(result) }
 }
 
+/// Tell this crate whether the host is currently running `DllMain` (i.e. holds the loader lock).
+///
+/// There is no reliable way to detect the loader lock so this is opt-in.
+/// Hosts that may call into this crate while loading or unloading should set this to `true`
+/// on entering `DllMain` and back to `false` before leaving it.
+/// The flag is process-wide so, while it's set, [`try_initialize_nonblocking`] refuses on every thread.
+///
+/// See [`for_plugins`](crate::for_plugins) for how to avoid doing COM work in these contexts at all.
+pub fn set_in_dll_main(in_dll_main: bool) {
+    IN_DLL_MAIN.store(in_dll_main, Ordering::SeqCst);
+}
+
+/// Initialize COM, unless that might deadlock.
+///
+/// Initializing COM while the loader lock is held can deadlock, so this returns
+/// [`E_POSSIBLE_DEADLOCK`] without doing anything if the host has called
+/// [`set_in_dll_main(true)`](set_in_dll_main). Otherwise it's the same as [`initialize`].
+pub fn try_initialize_nonblocking() -> Result<(), HRESULT> {
+    initialize_unless(&IN_DLL_MAIN)
+}
+
+/// [`try_initialize_nonblocking`] with the flag passed in, so tests needn't touch the global one.
+fn initialize_unless(in_dll_main: &AtomicBool) -> Result<(), HRESULT> {
+    if in_dll_main.load(Ordering::SeqCst) {
+        return Err(E_POSSIBLE_DEADLOCK);
+    }
+    initialize()
+}
+
+/// Work sent to the [`Worker`] thread, given the result of initializing COM there.
+#[cfg(feature = "std")]
+type Job = alloc::boxed::Box<dyn FnOnce(Result<(), HRESULT>) + Send>;
+
+#[cfg(feature = "std")]
+static WORKER: std::sync::OnceLock<Worker> = std::sync::OnceLock::new();
+
+/// A handle to a dedicated thread that owns COM and runs work sent to it.
+///
+/// Get one with [`ensure_started_from_plugin`].
+#[cfg(feature = "std")]
+#[derive(Clone, Debug)]
+pub struct Worker {
+    sender: std::sync::mpsc::Sender<Job>,
+}
+
+#[cfg(feature = "std")]
+impl Worker {
+    /// Spawn the thread, which initializes COM with `init` before running any work.
+    fn start(init: fn() -> Result<(), HRESULT>) -> Result<Self, HRESULT> {
+        let (sender, receiver) = std::sync::mpsc::channel::<Job>();
+        std::thread::Builder::new()
+            .name("vssetup-com".into())
+            .spawn(move || {
+                let initialized = init();
+                for job in receiver {
+                    job(initialized);
+                }
+            })
+            .map_err(|_| crate::hresults::E_OUTOFMEMORY)?;
+        Ok(Self { sender })
+    }
+
+    /// Run `f` on the worker thread and wait for its result.
+    ///
+    /// Returns the error from initializing COM on the worker, if that failed,
+    /// or `E_UNEXPECTED` if the worker has stopped because an earlier call panicked.
+    /// The result is sent back to the caller's thread so it must not hold any COM objects.
+    pub fn run<R, F>(&self, f: F) -> Result<R, HRESULT>
+    where
+        R: Send + 'static,
+        F: FnOnce() -> R + Send + 'static,
+    {
+        let (sender, receiver) = std::sync::mpsc::sync_channel(1);
+        let job: Job = alloc::boxed::Box::new(move |initialized: Result<(), HRESULT>| {
+            let _ = sender.send(initialized.map(|()| f()));
+        });
+        self.sender
+            .send(job)
+            .map_err(|_| crate::hresults::E_UNEXPECTED)?;
+        receiver
+            .recv()
+            .unwrap_or(Err(crate::hresults::E_UNEXPECTED))
+    }
+}
+
+/// Start the process-wide COM worker thread, if it isn't already running, and return a handle to it.
+///
+/// Nothing is done on the calling thread other than starting the worker:
+/// COM is initialized with [`try_initialize_nonblocking`] on the worker itself
+/// and all the work given to [`Worker::run`] happens there.
+/// Every call returns a handle to the same thread.
+///
+/// Returns [`E_POSSIBLE_DEADLOCK`] if the host has called [`set_in_dll_main(true)`](set_in_dll_main),
+/// as a thread mustn't be started from `DllMain`.
+#[cfg(feature = "std")]
+pub fn ensure_started_from_plugin() -> Result<Worker, HRESULT> {
+    if IN_DLL_MAIN.load(Ordering::SeqCst) {
+        return Err(E_POSSIBLE_DEADLOCK);
+    }
+    if let Some(worker) = WORKER.get() {
+        return Ok(worker.clone());
+    }
+    let worker = Worker::start(try_initialize_nonblocking)?;
+    // If another thread got here first, use its worker and let this one's thread exit.
+    Ok(WORKER.get_or_init(|| worker).clone())
+}
+
 /// Unitialize COM.
 ///
 /// # Safety
@@ -64,15 +183,56 @@ pub unsafe fn uninitialize() {
     }
 }
 
-mod api {
-    use super::HRESULT;
-    #[cfg(not(target_vendor = "win7"))]
-    windows_link::link!("combase.dll" "system" fn CoInitializeEx(pvReserved: *const (), dwCoInit: u32) -> HRESULT);
-    #[cfg(target_vendor = "win7")]
-    windows_link::link!("ole32.dll" "system" fn CoInitializeEx(pvReserved: *const (), dwCoInit: u32) -> HRESULT);
-    #[cfg(not(target_vendor = "win7"))]
-    windows_link::link!("combase.dll" "system" fn CoUnInitialize());
-    #[cfg(target_vendor = "win7")]
-    windows_link::link!("ole32.dll" "system" fn CoUnInitialize());
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refuses_in_dll_main() {
+        // Setting the process-wide flag would make other tests running in parallel refuse too.
+        let in_dll_main = AtomicBool::new(true);
+        // This must return before calling into COM.
+        assert_eq!(initialize_unless(&in_dll_main), Err(E_POSSIBLE_DEADLOCK));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn worker_runs_everything_on_one_thread() {
+        use std::sync::Mutex;
+        use std::thread::{self, ThreadId};
+        use std::vec::Vec;
+
+        // Records which threads COM was "initialized" on.
+        static INITIALIZED_ON: Mutex<Vec<ThreadId>> = Mutex::new(Vec::new());
+        fn init() -> Result<(), HRESULT> {
+            INITIALIZED_ON.lock().unwrap().push(thread::current().id());
+            Ok(())
+        }
+
+        let caller = thread::current().id();
+        let worker = Worker::start(init).unwrap();
+        let mut ran_on = Vec::new();
+        for _ in 0..3 {
+            ran_on.push(worker.run(|| thread::current().id()).unwrap());
+        }
+        // Calls from other threads land on the same worker.
+        let clone = worker.clone();
+        ran_on.push(
+            thread::spawn(move || clone.run(|| thread::current().id()).unwrap())
+                .join()
+                .unwrap(),
+        );
+
+        let initialized_on = INITIALIZED_ON.lock().unwrap().clone();
+        assert_eq!(initialized_on.len(), 1);
+        assert_ne!(initialized_on[0], caller);
+        assert!(ran_on.iter().all(|&id| id == initialized_on[0]));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn worker_reports_failed_initialization() {
+        let worker = Worker::start(|| Err(crate::hresults::CO_E_NOTINITIALIZED)).unwrap();
+        assert_eq!(worker.run(|| ()), Err(crate::hresults::CO_E_NOTINITIALIZED));
+    }
 }
-use api::*;
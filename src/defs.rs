@@ -1,3 +1,5 @@
+use alloc::string::String;
+use alloc::vec::Vec;
 use core::ffi::c_void;
 use core::fmt;
 use core::mem::ManuallyDrop;
@@ -5,6 +7,8 @@ use core::ptr::NonNull;
 use windows_result::HRESULT;
 use windows_strings::BSTR;
 
+use crate::hresults::{DISP_E_OVERFLOW, DISP_E_TYPEMISMATCH};
+
 // Windows.Win32.Foundation.FILETIME
 #[repr(C)]
 #[derive(Default, Debug, Clone, Copy)]
@@ -17,6 +21,155 @@ impl FILETIME {
     pub fn as_u64(&self) -> u64 {
         ((self.dwHighDateTime as u64) << 32) | (self.dwLowDateTime as u64)
     }
+
+    /// The time as a `SystemTime`.
+    ///
+    /// Returns `None` for the zero value, which some incomplete instances report as their
+    /// install date, or if the time can't be represented by `SystemTime`.
+    #[cfg(feature = "std")]
+    pub fn to_system_time(&self) -> Option<std::time::SystemTime> {
+        use std::time::{Duration, SystemTime};
+        const TICKS_PER_SECOND: u64 = 10_000_000;
+        let duration = |ticks: u64| {
+            let nanos = (ticks % TICKS_PER_SECOND) as u32 * 100;
+            Duration::new(ticks / TICKS_PER_SECOND, nanos)
+        };
+        match self.as_u64() {
+            0 => None,
+            ticks if ticks >= UNIX_EPOCH => {
+                SystemTime::UNIX_EPOCH.checked_add(duration(ticks - UNIX_EPOCH))
+            }
+            ticks => SystemTime::UNIX_EPOCH.checked_sub(duration(UNIX_EPOCH - ticks)),
+        }
+    }
+
+    /// The time as a `time::OffsetDateTime` in UTC.
+    ///
+    /// Returns `None` for the zero value, as for [`to_system_time`](Self::to_system_time),
+    /// or if the year is after 9999.
+    #[cfg(feature = "time")]
+    pub fn to_offset_date_time(&self) -> Option<time::OffsetDateTime> {
+        time::OffsetDateTime::from_unix_timestamp_nanos(self.unix_nanos()?).ok()
+    }
+
+    /// The time as a `chrono::DateTime<Utc>`.
+    ///
+    /// Returns `None` for the zero value, as for [`to_system_time`](Self::to_system_time).
+    #[cfg(feature = "chrono")]
+    pub fn to_chrono_date_time(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        const NANOS_PER_SECOND: i128 = 1_000_000_000;
+        let nanos = self.unix_nanos()?;
+        let seconds = i64::try_from(nanos.div_euclid(NANOS_PER_SECOND)).ok()?;
+        chrono::DateTime::from_timestamp(seconds, nanos.rem_euclid(NANOS_PER_SECOND) as u32)
+    }
+
+    /// The number of nanoseconds since 1970-01-01 (UTC), or `None` for the zero value.
+    #[cfg(any(feature = "time", feature = "chrono"))]
+    fn unix_nanos(&self) -> Option<i128> {
+        match self.as_u64() {
+            0 => None,
+            ticks => Some((i128::from(ticks) - i128::from(UNIX_EPOCH)) * 100),
+        }
+    }
+}
+
+/// The number of 100 nanosecond intervals from 1601-01-01 to 1970-01-01.
+#[cfg(any(feature = "std", feature = "time", feature = "chrono"))]
+const UNIX_EPOCH: u64 = 116_444_736_000_000_000;
+
+impl From<FILETIME> for u64 {
+    fn from(time: FILETIME) -> Self {
+        time.as_u64()
+    }
+}
+
+impl PartialEq for FILETIME {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_u64() == other.as_u64()
+    }
+}
+
+impl Eq for FILETIME {}
+
+/// Earlier times are less than later times.
+impl Ord for FILETIME {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+        self.as_u64().cmp(&other.as_u64())
+    }
+}
+
+impl PartialOrd for FILETIME {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Writes the time as ISO 8601 in UTC, to the second, e.g. `2024-03-14T09:26:53Z`.
+impl fmt::Display for FILETIME {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+        let seconds = self.as_u64() / 10_000_000;
+        let (days, time) = (seconds / SECONDS_PER_DAY, seconds % SECONDS_PER_DAY);
+        let (year, month, day) = civil_from_days(days);
+        core::write!(
+            f,
+            "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+            time / 3600,
+            time / 60 % 60,
+            time % 60
+        )
+    }
+}
+
+/// The `FILETIME` of an OLE automation date, or `None` if it's before 1601 or out of range.
+///
+/// The fraction is the time of day even for negative dates, so `-1.25` is 1899-12-29 06:00.
+fn date_to_filetime(date: f64) -> Option<FILETIME> {
+    // Days from 1601-01-01 to 1899-12-30.
+    const EPOCH_DAYS: f64 = 109_205.0;
+    const MILLIS_PER_DAY: f64 = 86_400_000.0;
+    if !date.is_finite() {
+        return None;
+    }
+    // `as` truncates towards zero.
+    let days = date as i64 as f64;
+    let time = if date < days {
+        days - date
+    } else {
+        date - days
+    };
+    let millis = (EPOCH_DAYS + days) * MILLIS_PER_DAY + time * MILLIS_PER_DAY;
+    if !(0.0..(u64::MAX / 10_000) as f64).contains(&millis) {
+        return None;
+    }
+    let ticks = (millis + 0.5) as u64 * 10_000;
+    Some(FILETIME {
+        dwLowDateTime: ticks as u32,
+        dwHighDateTime: (ticks >> 32) as u32,
+    })
+}
+
+/// The year, month and day of a number of days since 1601-01-01.
+///
+/// See <https://howardhinnant.github.io/date_algorithms.html#civil_from_days>,
+/// here counting from 1600-03-01 so everything stays unsigned.
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    // 1600-03-01 to 1601-01-01.
+    let days = days + 306;
+    let era = days / 146097;
+    let day_of_era = days % 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    };
+    let year = 1600 + year_of_era + era * 400 + u64::from(month <= 2);
+    (year, month, day)
 }
 
 // Windows.Win32.System.Com.SAFEARRAYBOUND
@@ -36,6 +189,13 @@ pub struct SAFEARRAY {
     pub rgsabound: [SAFEARRAYBOUND; 1],
 }
 
+// SAFEARRAY features that make `SafeArrayDestroy` free the elements.
+pub const FADF_RECORD: u16 = 0x20;
+pub const FADF_BSTR: u16 = 0x100;
+pub const FADF_UNKNOWN: u16 = 0x200;
+pub const FADF_DISPATCH: u16 = 0x400;
+pub const FADF_VARIANT: u16 = 0x800;
+
 pub type LCID = u32;
 pub type LPCOLESTR = *const u16;
 pub type VARIANT_BOOL = i16;
@@ -44,6 +204,8 @@ pub type VARIANT_BOOL = i16;
 // We only need to support a subset of all possible VARIANT types
 
 type VARTYPE = u16;
+pub const VT_EMPTY: VARTYPE = 0;
+pub const VT_NULL: VARTYPE = 1;
 pub const VT_BSTR: VARTYPE = 8;
 pub const VT_BOOL: VARTYPE = 11;
 pub const VT_I1: VARTYPE = 16;
@@ -54,15 +216,198 @@ pub const VT_UI1: VARTYPE = 17;
 pub const VT_UI2: VARTYPE = 18;
 pub const VT_UI4: VARTYPE = 19;
 pub const VT_UI8: VARTYPE = 21;
+pub const VT_DATE: VARTYPE = 7;
+pub const VT_FILETIME: VARTYPE = 64;
+pub const VT_ARRAY: VARTYPE = 0x2000;
+const VT_ARRAY_BSTR: VARTYPE = VT_ARRAY | VT_BSTR;
 
 pub enum Variant {
     Bstr(BSTR),
     Bool(bool),
     Signed(i64),
     Unsigned(u64),
+    /// E.g. some catalog and channel properties.
+    StringArray(Vec<BSTR>),
+    FileTime(FILETIME),
+    /// An OLE automation date, i.e. days since 1899-12-30 with the time as the fraction.
+    Date(f64),
+    /// No value, e.g. a policy that isn't set.
+    Empty,
+    /// An explicit SQL-style null.
+    Null,
     Unknown,
 }
 
+impl Variant {
+    pub fn as_bstr(&self) -> Option<&BSTR> {
+        match self {
+            Self::Bstr(bstr) => Some(bstr),
+            _ => None,
+        }
+    }
+
+    /// The string, converted lossily as by [`bstr_to_string_lossy`](crate::strings::bstr_to_string_lossy).
+    pub fn into_string(self) -> Option<String> {
+        self.as_bstr()
+            .map(|bstr| crate::strings::bstr_to_string_lossy(bstr))
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            Self::Bool(bool) => Some(bool),
+            _ => None,
+        }
+    }
+
+    /// The number, if it's signed or an unsigned number no greater than `i64::MAX`.
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            Self::Signed(i64) => Some(i64),
+            Self::Unsigned(u64) => i64::try_from(u64).ok(),
+            _ => None,
+        }
+    }
+
+    /// The number, if it's unsigned or a signed number that isn't negative.
+    pub fn as_u64(&self) -> Option<u64> {
+        match *self {
+            Self::Unsigned(u64) => Some(u64),
+            Self::Signed(i64) => u64::try_from(i64).ok(),
+            _ => None,
+        }
+    }
+
+    fn is_number(&self) -> bool {
+        matches!(self, Self::Signed(_) | Self::Unsigned(_))
+    }
+
+    /// Both kinds of number, so they can be compared with each other.
+    fn as_i128(&self) -> Option<i128> {
+        match *self {
+            Self::Signed(i64) => Some(i64.into()),
+            Self::Unsigned(u64) => Some(u64.into()),
+            _ => None,
+        }
+    }
+}
+
+/// Variants are equal if they hold the same value, so `Signed(1) == Unsigned(1)`.
+///
+/// `Unknown` holds a value this crate can't read, so it isn't equal to anything, not even
+/// another `Unknown`. `Empty` and `Null` are each only equal to themselves.
+impl PartialEq for Variant {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Bstr(a), Self::Bstr(b)) => a == b,
+            (Self::Bool(a), Self::Bool(b)) => a == b,
+            (Self::StringArray(a), Self::StringArray(b)) => a == b,
+            (Self::FileTime(a), Self::FileTime(b)) => a == b,
+            (Self::Date(a), Self::Date(b)) => a == b,
+            (Self::Empty, Self::Empty) | (Self::Null, Self::Null) => true,
+            _ => self.as_i128().is_some_and(|a| other.as_i128() == Some(a)),
+        }
+    }
+}
+
+/// Whether this is a string equal to `other`.
+///
+/// Strings that aren't valid UTF-16 are never equal to a `str`.
+impl PartialEq<str> for Variant {
+    fn eq(&self, other: &str) -> bool {
+        self.as_bstr()
+            .is_some_and(|bstr| bstr.iter().copied().eq(other.encode_utf16()))
+    }
+}
+
+impl PartialEq<&str> for Variant {
+    fn eq(&self, other: &&str) -> bool {
+        *self == **other
+    }
+}
+
+impl PartialEq<Variant> for str {
+    fn eq(&self, other: &Variant) -> bool {
+        *other == *self
+    }
+}
+
+impl PartialEq<Variant> for &str {
+    fn eq(&self, other: &Variant) -> bool {
+        *other == **self
+    }
+}
+
+impl PartialEq<bool> for Variant {
+    fn eq(&self, other: &bool) -> bool {
+        self.as_bool() == Some(*other)
+    }
+}
+
+/// Numbers are compared by value, whether the variant is `Signed` or `Unsigned`.
+macro_rules! eq_integers {
+    ($($int:ty),+) => {$(
+        impl PartialEq<$int> for Variant {
+            fn eq(&self, other: &$int) -> bool {
+                self.as_i128() == Some(i128::from(*other))
+            }
+        }
+    )+};
+}
+eq_integers!(i8, i16, i32, i64, u8, u16, u32, u64);
+
+/// Fails with `DISP_E_TYPEMISMATCH` if the variant isn't a string.
+impl TryFrom<Variant> for String {
+    type Error = HRESULT;
+    fn try_from(variant: Variant) -> Result<Self, HRESULT> {
+        variant.into_string().ok_or(DISP_E_TYPEMISMATCH)
+    }
+}
+
+/// Fails with `DISP_E_TYPEMISMATCH` if the variant isn't a `bool`.
+impl TryFrom<Variant> for bool {
+    type Error = HRESULT;
+    fn try_from(variant: Variant) -> Result<Self, HRESULT> {
+        variant.as_bool().ok_or(DISP_E_TYPEMISMATCH)
+    }
+}
+
+/// Fails with `DISP_E_TYPEMISMATCH` if the variant isn't a number,
+/// or `DISP_E_OVERFLOW` if it's too big.
+impl TryFrom<Variant> for i64 {
+    type Error = HRESULT;
+    fn try_from(variant: Variant) -> Result<Self, HRESULT> {
+        match variant.as_i64() {
+            Some(i64) => Ok(i64),
+            None if variant.is_number() => Err(DISP_E_OVERFLOW),
+            None => Err(DISP_E_TYPEMISMATCH),
+        }
+    }
+}
+
+/// Fails with `DISP_E_TYPEMISMATCH` if the variant isn't a number,
+/// or `DISP_E_OVERFLOW` if it's negative.
+impl TryFrom<Variant> for u64 {
+    type Error = HRESULT;
+    fn try_from(variant: Variant) -> Result<Self, HRESULT> {
+        match variant.as_u64() {
+            Some(u64) => Ok(u64),
+            None if variant.is_number() => Err(DISP_E_OVERFLOW),
+            None => Err(DISP_E_TYPEMISMATCH),
+        }
+    }
+}
+
+/// Fails with `DISP_E_TYPEMISMATCH` if the variant isn't a string.
+/// Unlike a `String`, the path can hold any string setup returns.
+#[cfg(feature = "std")]
+impl TryFrom<Variant> for std::path::PathBuf {
+    type Error = HRESULT;
+    fn try_from(variant: Variant) -> Result<Self, HRESULT> {
+        let bstr = variant.as_bstr().ok_or(DISP_E_TYPEMISMATCH)?;
+        Ok(crate::strings::bstr_to_pathbuf(bstr))
+    }
+}
+
 impl fmt::Debug for Variant {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -70,6 +415,11 @@ impl fmt::Debug for Variant {
             Self::Bool(bool) => core::write!(f, "{bool}"),
             Self::Signed(i64) => core::write!(f, "[int]{i64}"),
             Self::Unsigned(u64) => core::write!(f, "[uint]{u64}"),
+            Self::StringArray(strings) => f.debug_list().entries(strings).finish(),
+            Self::FileTime(filetime) => core::write!(f, "[filetime]{filetime}"),
+            Self::Date(f64) => core::write!(f, "[date]{f64}"),
+            Self::Empty => core::write!(f, "<empty>"),
+            Self::Null => core::write!(f, "<null>"),
             Self::Unknown => core::write!(f, "<unknown>"),
         }
     }
@@ -82,6 +432,21 @@ impl fmt::Display for Variant {
             Self::Bool(bool) => core::write!(f, "{bool}"),
             Self::Signed(i64) => core::write!(f, "{i64}"),
             Self::Unsigned(u64) => core::write!(f, "{u64}"),
+            Self::StringArray(strings) => {
+                for (i, bstr) in strings.iter().enumerate() {
+                    if i != 0 {
+                        f.write_str(", ")?;
+                    }
+                    core::write!(f, "{bstr}")?;
+                }
+                Ok(())
+            }
+            Self::FileTime(filetime) => core::write!(f, "{filetime}"),
+            Self::Date(f64) => match date_to_filetime(*f64) {
+                Some(filetime) => core::write!(f, "{filetime}"),
+                None => core::write!(f, "{f64}"),
+            },
+            Self::Empty | Self::Null => Ok(()),
             Self::Unknown => core::write!(f, "<unknown>"),
         }
     }
@@ -99,10 +464,37 @@ pub struct VARIANT {
 impl VARIANT {
     pub fn into_variant(mut self) -> Variant {
         match self.vt {
-            VT_BSTR => Variant::Bstr(unsafe { ManuallyDrop::take(&mut self.data.bstrVal) }),
+            VT_BSTR => {
+                // The string is now ours to free, not the `VARIANT`'s.
+                self.vt = VT_EMPTY;
+                Variant::Bstr(unsafe { ManuallyDrop::take(&mut self.data.bstrVal) })
+            }
             VT_BOOL => Variant::Bool(unsafe { self.data.boolVal != 0 }),
-            VT_I1 | VT_I2 | VT_I4 | VT_I8 => Variant::Signed(unsafe { self.data.llVal as i64 }),
-            VT_UI1 | VT_UI2 | VT_UI4 | VT_UI8 => Variant::Unsigned(unsafe { self.data.llVal }),
+            // Only the bytes of the value's own size are set, the rest of the union may be garbage.
+            VT_I1 => Variant::Signed(unsafe { self.data.cVal }.into()),
+            VT_I2 => Variant::Signed(unsafe { self.data.iVal }.into()),
+            VT_I4 => Variant::Signed(unsafe { self.data.lVal }.into()),
+            VT_I8 => Variant::Signed(unsafe { self.data.llVal }),
+            VT_UI1 => Variant::Unsigned(unsafe { self.data.bVal }.into()),
+            VT_UI2 => Variant::Unsigned(unsafe { self.data.uiVal }.into()),
+            VT_UI4 => Variant::Unsigned(unsafe { self.data.ulVal }.into()),
+            VT_UI8 => Variant::Unsigned(unsafe { self.data.ullVal }),
+            VT_DATE => Variant::Date(unsafe { self.data.date }),
+            VT_FILETIME => Variant::FileTime(unsafe { self.data.filetime }),
+            VT_ARRAY_BSTR => {
+                // The array is now ours to destroy, not the `VARIANT`'s.
+                let raw = unsafe { self.data.parray };
+                self.vt = VT_EMPTY;
+                match unsafe { crate::SafeArray::<BSTR>::from_raw(raw) } {
+                    Ok(array) => Variant::StringArray(array.into_vec()),
+                    Err(_) => {
+                        let _ = unsafe { crate::sys::SafeArrayDestroy(raw) };
+                        Variant::Unknown
+                    }
+                }
+            }
+            VT_EMPTY => Variant::Empty,
+            VT_NULL => Variant::Null,
             // This should not be reachable when using the API exposed by this crate.
             _ => {
                 if cfg!(debug_assertions) {
@@ -113,27 +505,56 @@ impl VARIANT {
         }
     }
 }
+#[cfg(test)]
+impl VARIANT {
+    /// A `VT_BSTR` variant, as setup would return.
+    pub(crate) fn from_bstr(bstr: BSTR) -> Self {
+        Self {
+            vt: VT_BSTR,
+            wReserved1: 0,
+            wReserved2: 0,
+            wReserved3: 0,
+            data: VARIANT_DATA {
+                bstrVal: ManuallyDrop::new(bstr),
+            },
+        }
+    }
+}
+
 impl Drop for VARIANT {
     fn drop(&mut self) {
-        if self.vt == VT_BSTR {
-            unsafe {
-                ManuallyDrop::drop(&mut self.data.bstrVal);
-            }
+        match self.vt {
+            VT_BSTR => unsafe { ManuallyDrop::drop(&mut self.data.bstrVal) },
+            // `FADF_BSTR` makes this free the strings too.
+            VT_ARRAY_BSTR => unsafe {
+                let _ = crate::sys::SafeArrayDestroy(self.data.parray);
+            },
+            _ => {}
         }
     }
 }
 
 #[repr(C)]
 pub union VARIANT_DATA {
-    llVal: u64,
+    cVal: i8,
+    iVal: i16,
+    lVal: i32,
+    llVal: i64,
+    bVal: u8,
+    uiVal: u16,
+    ulVal: u32,
+    ullVal: u64,
     boolVal: VARIANT_BOOL,
+    date: f64,
+    filetime: FILETIME,
     bstrVal: ManuallyDrop<BSTR>,
+    parray: *mut SAFEARRAY,
     // This is necessary to correctly size the union for types we don't support.
     __unknown__: [*mut (); 2],
 }
 
 #[repr(C)]
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
 pub struct GUID {
     pub data1: u32,
     pub data2: u16,
@@ -141,10 +562,31 @@ pub struct GUID {
     pub data4: [u8; 8],
 }
 
-#[derive(Clone, Copy)]
+/// An owned COM reference. Cloning calls `AddRef` and dropping calls `Release`.
 #[repr(transparent)]
 pub struct IUnknown(NonNull<c_void>);
 
+impl IUnknown {
+    #[inline(always)]
+    fn vtable(&self) -> &IUnknown_Vtbl {
+        // SAFETY: every COM object starts with a pointer to a vtable that begins with `IUnknown`.
+        unsafe { &**self.0.as_ptr().cast::<*const IUnknown_Vtbl>() }
+    }
+}
+
+impl Clone for IUnknown {
+    fn clone(&self) -> Self {
+        unsafe { (self.vtable().AddRef)(self.0.as_ptr()) };
+        Self(self.0)
+    }
+}
+
+impl Drop for IUnknown {
+    fn drop(&mut self) {
+        unsafe { (self.vtable().Release)(self.0.as_ptr()) };
+    }
+}
+
 #[repr(C)]
 pub struct IUnknown_Vtbl {
     pub QueryInterface: unsafe extern "system" fn(
@@ -167,12 +609,25 @@ impl GUID {
     }
 }
 
+/// The registry form, e.g. `{177F0C4A-1CD3-4DE7-A32C-71DBBB9FA36D}`.
+impl fmt::Display for GUID {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [a, b, c, d, e, g, h, i] = self.data4;
+        write!(
+            f,
+            "{{{:08X}-{:04X}-{:04X}-{a:02X}{b:02X}-{c:02X}{d:02X}{e:02X}{g:02X}{h:02X}{i:02X}}}",
+            self.data1, self.data2, self.data3
+        )
+    }
+}
+
+impl fmt::Debug for GUID {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
 pub const CLSCTX_ALL: u32 = 23;
-pub const S_OK: HRESULT = HRESULT(0);
-pub const S_FALSE: HRESULT = HRESULT(0x1);
-pub const E_POINTER: HRESULT = HRESULT(0x80004003_u32 as i32);
-pub const E_INVALIDARG: HRESULT = HRESULT(0x80070057_u32 as i32);
-pub const E_UNEXPECTED: HRESULT = HRESULT(0x8000FFFF_u32 as i32);
 
 #[cfg(test)]
 mod tests {
@@ -186,4 +641,512 @@ mod tests {
 
         assert_eq!(align_of::<VARIANT>(), 8);
     }
+
+    fn filetime(ticks: u64) -> FILETIME {
+        FILETIME {
+            dwLowDateTime: ticks as u32,
+            dwHighDateTime: (ticks >> 32) as u32,
+        }
+    }
+
+    /// A `VARIANT` of type `vt` where every byte of the value is set, as if left over.
+    fn garbage_variant(vt: VARTYPE) -> VARIANT {
+        VARIANT {
+            vt,
+            wReserved1: 0,
+            wReserved2: 0,
+            wReserved3: 0,
+            data: VARIANT_DATA {
+                __unknown__: [usize::MAX as *mut (); 2],
+            },
+        }
+    }
+
+    #[test]
+    fn variant_integers() {
+        let signed = |variant: VARIANT| match variant.into_variant() {
+            Variant::Signed(value) => value,
+            other => panic!("{other:?}"),
+        };
+        let unsigned = |variant: VARIANT| match variant.into_variant() {
+            Variant::Unsigned(value) => value,
+            other => panic!("{other:?}"),
+        };
+
+        for value in [-1, 0, 1, i8::MIN, i8::MAX] {
+            let mut variant = garbage_variant(VT_I1);
+            variant.data.cVal = value;
+            assert_eq!(signed(variant), i64::from(value));
+        }
+        for value in [-1, 0, 1, i16::MIN, i16::MAX] {
+            let mut variant = garbage_variant(VT_I2);
+            variant.data.iVal = value;
+            assert_eq!(signed(variant), i64::from(value));
+        }
+        for value in [-1, 0, 1, i32::MIN, i32::MAX] {
+            let mut variant = garbage_variant(VT_I4);
+            variant.data.lVal = value;
+            assert_eq!(signed(variant), i64::from(value));
+        }
+        for value in [-1, 0, 1, i64::MIN, i64::MAX] {
+            let mut variant = garbage_variant(VT_I8);
+            variant.data.llVal = value;
+            assert_eq!(signed(variant), value);
+        }
+
+        for value in [0, 1, u8::MAX] {
+            let mut variant = garbage_variant(VT_UI1);
+            variant.data.bVal = value;
+            assert_eq!(unsigned(variant), u64::from(value));
+        }
+        for value in [0, 1, u16::MAX] {
+            let mut variant = garbage_variant(VT_UI2);
+            variant.data.uiVal = value;
+            assert_eq!(unsigned(variant), u64::from(value));
+        }
+        for value in [0, 1, u32::MAX] {
+            let mut variant = garbage_variant(VT_UI4);
+            variant.data.ulVal = value;
+            assert_eq!(unsigned(variant), u64::from(value));
+        }
+        for value in [0, 1, u64::MAX] {
+            let mut variant = garbage_variant(VT_UI8);
+            variant.data.ullVal = value;
+            assert_eq!(unsigned(variant), value);
+        }
+    }
+
+    #[test]
+    fn variant_bstr() {
+        let bstr_variant = || {
+            let mut variant = garbage_variant(VT_BSTR);
+            variant.data.bstrVal = ManuallyDrop::new(BSTR::from("Über 🦀"));
+            variant
+        };
+        match bstr_variant().into_variant() {
+            Variant::Bstr(bstr) => assert_eq!(bstr, "Über 🦀"),
+            other => panic!("{other:?}"),
+        }
+        // Dropping without converting frees the string instead.
+        drop(bstr_variant());
+    }
+
+    #[test]
+    fn variant_empty_and_null() {
+        // A zeroed `VARIANT` is `VT_EMPTY`, which is what `VariantInit` produces.
+        let variant: VARIANT = unsafe { core::mem::zeroed() };
+        let variant = variant.into_variant();
+        assert!(matches!(variant, Variant::Empty), "{variant:?}");
+        assert_eq!(alloc::format!("{variant}"), "");
+        assert_eq!(alloc::format!("{variant:?}"), "<empty>");
+
+        let variant = garbage_variant(VT_NULL).into_variant();
+        assert!(matches!(variant, Variant::Null), "{variant:?}");
+        assert_eq!(alloc::format!("{variant}"), "");
+        assert_eq!(alloc::format!("{variant:?}"), "<null>");
+    }
+
+    #[test]
+    fn variant_conversions() {
+        use crate::hresults::{DISP_E_OVERFLOW as OVERFLOW, DISP_E_TYPEMISMATCH as MISMATCH};
+
+        type Expected = (
+            Result<&'static str, HRESULT>,
+            Result<bool, HRESULT>,
+            Result<i64, HRESULT>,
+            Result<u64, HRESULT>,
+        );
+        let table: [(fn() -> Variant, Expected); 12] = [
+            (
+                || Variant::Bstr(BSTR::from("17.9")),
+                (Ok("17.9"), Err(MISMATCH), Err(MISMATCH), Err(MISMATCH)),
+            ),
+            // Strings that look like something else stay strings.
+            (
+                || Variant::Bstr(BSTR::from("1")),
+                (Ok("1"), Err(MISMATCH), Err(MISMATCH), Err(MISMATCH)),
+            ),
+            (
+                || Variant::Bstr(BSTR::new()),
+                (Ok(""), Err(MISMATCH), Err(MISMATCH), Err(MISMATCH)),
+            ),
+            (
+                || Variant::Bool(true),
+                (Err(MISMATCH), Ok(true), Err(MISMATCH), Err(MISMATCH)),
+            ),
+            (
+                || Variant::Signed(-1),
+                (Err(MISMATCH), Err(MISMATCH), Ok(-1), Err(OVERFLOW)),
+            ),
+            (
+                || Variant::Signed(i64::MAX),
+                (
+                    Err(MISMATCH),
+                    Err(MISMATCH),
+                    Ok(i64::MAX),
+                    Ok(i64::MAX as u64),
+                ),
+            ),
+            (
+                || Variant::Unsigned(1),
+                (Err(MISMATCH), Err(MISMATCH), Ok(1), Ok(1)),
+            ),
+            (
+                || Variant::Unsigned(u64::MAX),
+                (Err(MISMATCH), Err(MISMATCH), Err(OVERFLOW), Ok(u64::MAX)),
+            ),
+            (
+                || Variant::StringArray(alloc::vec![BSTR::from("a")]),
+                (Err(MISMATCH), Err(MISMATCH), Err(MISMATCH), Err(MISMATCH)),
+            ),
+            (
+                || Variant::Empty,
+                (Err(MISMATCH), Err(MISMATCH), Err(MISMATCH), Err(MISMATCH)),
+            ),
+            (
+                || Variant::Null,
+                (Err(MISMATCH), Err(MISMATCH), Err(MISMATCH), Err(MISMATCH)),
+            ),
+            (
+                || Variant::Unknown,
+                (Err(MISMATCH), Err(MISMATCH), Err(MISMATCH), Err(MISMATCH)),
+            ),
+        ];
+        for (variant, (string, bool, i64, u64)) in table {
+            let name = alloc::format!("{:?}", variant());
+            assert_eq!(
+                String::try_from(variant()),
+                string.map(String::from),
+                "{name}"
+            );
+            assert_eq!(bool::try_from(variant()), bool, "{name}");
+            assert_eq!(i64::try_from(variant()), i64, "{name}");
+            assert_eq!(u64::try_from(variant()), u64, "{name}");
+
+            assert_eq!(variant().into_string().as_deref(), string.ok(), "{name}");
+            assert_eq!(
+                variant().as_bstr().map(|b| b.len()),
+                string.ok().map(str::len),
+                "{name}"
+            );
+            assert_eq!(variant().as_bool(), bool.ok(), "{name}");
+            assert_eq!(variant().as_i64(), i64.ok(), "{name}");
+            assert_eq!(variant().as_u64(), u64.ok(), "{name}");
+        }
+    }
+
+    #[test]
+    fn variant_eq() {
+        let bstr = |s: &str| Variant::Bstr(BSTR::from(s));
+
+        // The boundaries between signed and unsigned.
+        assert_eq!(Variant::Signed(0), Variant::Unsigned(0));
+        assert_eq!(Variant::Unsigned(1), Variant::Signed(1));
+        assert_eq!(
+            Variant::Signed(i64::MAX),
+            Variant::Unsigned(i64::MAX as u64)
+        );
+        assert_ne!(Variant::Signed(-1), Variant::Unsigned(u64::MAX));
+        assert_ne!(Variant::Signed(i64::MIN), Variant::Unsigned(1 << 63));
+        assert_ne!(
+            Variant::Unsigned(i64::MAX as u64 + 1),
+            Variant::Signed(i64::MIN)
+        );
+        assert_eq!(Variant::Signed(-1), -1);
+        assert_ne!(Variant::Signed(-1), u64::MAX);
+        assert_eq!(Variant::Unsigned(u64::MAX), u64::MAX);
+        assert_ne!(Variant::Unsigned(u64::MAX), -1i64);
+        assert_eq!(Variant::Unsigned(255), 255u8);
+        assert_ne!(Variant::Unsigned(256), 0u8);
+        assert_eq!(Variant::Signed(1), 1);
+        assert_ne!(Variant::Bool(true), 1);
+        assert_ne!(bstr("1"), 1);
+
+        // Strings, compared as UTF-16.
+        assert_eq!(bstr("VisualStudio.17.Release"), "VisualStudio.17.Release");
+        assert_eq!(bstr("Über 🦀"), "Über 🦀");
+        assert_eq!(*"Über 🦀", bstr("Über 🦀"));
+        assert_eq!("Über 🦀", bstr("Über 🦀"));
+        assert_ne!(bstr("Über 🦀"), "Uber 🦀");
+        assert_ne!(bstr("Über"), "Über 🦀");
+        assert_ne!(bstr("über"), "Über");
+        assert_eq!(bstr(""), "");
+        assert_ne!(Variant::Empty, "");
+        assert_eq!(bstr("Über 🦀"), bstr("Über 🦀"));
+        assert_ne!(bstr("1"), Variant::Unsigned(1));
+        // A lone surrogate, which `str` can't represent.
+        let lone = Variant::Bstr(crate::strings::bstr_from_units(&[0xD83E]));
+        assert_ne!(lone, "\u{FFFD}");
+        assert_ne!(lone, "");
+
+        assert_eq!(Variant::Bool(true), true);
+        assert_ne!(Variant::Bool(true), false);
+        assert_ne!(Variant::Unsigned(0), false);
+        assert_eq!(
+            Variant::StringArray(alloc::vec![BSTR::from("a")]),
+            Variant::StringArray(alloc::vec![BSTR::from("a")])
+        );
+        assert_ne!(Variant::StringArray(alloc::vec![BSTR::from("a")]), "a");
+
+        assert_eq!(Variant::Empty, Variant::Empty);
+        assert_eq!(Variant::Null, Variant::Null);
+        assert_ne!(Variant::Empty, Variant::Null);
+        assert_ne!(Variant::Unknown, Variant::Unknown);
+        assert_ne!(Variant::Unknown, Variant::Empty);
+    }
+
+    /// A `VT_ARRAY | VT_BSTR` variant holding a new array of `strings`.
+    fn string_array_variant(strings: &[&str]) -> VARIANT {
+        let mut variant = garbage_variant(VT_ARRAY_BSTR);
+        unsafe {
+            let raw = crate::sys::SafeArrayCreateVector(VT_BSTR, 0, strings.len() as u32);
+            assert!(!raw.is_null());
+            assert_ne!((*raw).fFeatures & FADF_BSTR, 0);
+            let data = (*raw).pvData.cast::<BSTR>();
+            for (i, string) in strings.iter().enumerate() {
+                core::ptr::write(data.add(i), BSTR::from(*string));
+            }
+            variant.data.parray = raw;
+        }
+        variant
+    }
+
+    #[test]
+    fn variant_string_array() {
+        let variant = string_array_variant(&[r"C:\VS", "Über 🦀"]);
+        // An extra lock keeps the array alive after `into_variant` tries to destroy it,
+        // so it can be checked that the strings were moved out rather than copied.
+        let raw = unsafe { variant.data.parray };
+        unsafe { crate::sys::SafeArrayLock(raw).unwrap() };
+        let value = variant.into_variant();
+        let Variant::StringArray(strings) = &value else {
+            panic!("{value:?}");
+        };
+        assert_eq!(strings.len(), 2);
+        assert_eq!(strings[0], r"C:\VS");
+        assert_eq!(strings[1], "Über 🦀");
+        assert_eq!(alloc::format!("{value}"), r"C:\VS, Über 🦀");
+        assert_eq!(alloc::format!("{value:?}"), r"[C:\VS, Über 🦀]");
+        unsafe {
+            assert_eq!((*raw).cLocks, 1);
+            let data = (*raw).pvData.cast::<*const u16>();
+            assert!((*data).is_null() && (*data.add(1)).is_null());
+            crate::sys::SafeArrayUnlock(raw).unwrap();
+            crate::sys::SafeArrayDestroy(raw).unwrap();
+        }
+
+        let value = string_array_variant(&[]).into_variant();
+        assert!(matches!(&value, Variant::StringArray(strings) if strings.is_empty()));
+        assert_eq!(alloc::format!("{value}"), "");
+
+        // A null array can't be read, but mustn't panic either.
+        let mut variant = garbage_variant(VT_ARRAY_BSTR);
+        variant.data.parray = core::ptr::null_mut();
+        assert!(matches!(variant.into_variant(), Variant::Unknown));
+
+        // Dropping without converting destroys the array and its strings, once.
+        drop(string_array_variant(&["one", "two"]));
+    }
+
+    #[test]
+    fn variant_times() {
+        let mut variant = garbage_variant(VT_FILETIME);
+        variant.data.filetime = filetime(133_500_000_000_000_000);
+        let value = variant.into_variant();
+        assert_eq!(value, Variant::FileTime(filetime(133_500_000_000_000_000)));
+        assert_eq!(alloc::format!("{value}"), "2024-01-17T21:20:00Z");
+        assert_eq!(
+            alloc::format!("{value:?}"),
+            "[filetime]2024-01-17T21:20:00Z"
+        );
+
+        let table = [
+            (0.0, "1899-12-30T00:00:00Z"),
+            (2.5, "1900-01-01T12:00:00Z"),
+            (45308.0 + 8.0 / 9.0, "2024-01-17T21:20:00Z"),
+            (-1.25, "1899-12-29T06:00:00Z"),
+            (-109_205.0, "1601-01-01T00:00:00Z"),
+            // Too early for a `FILETIME`, or not a date at all.
+            (-109_206.0, "-109206"),
+            (f64::NAN, "NaN"),
+            (f64::INFINITY, "inf"),
+            (1e20, "100000000000000000000"),
+        ];
+        for (date, expected) in table {
+            let mut variant = garbage_variant(VT_DATE);
+            variant.data.date = date;
+            let value = variant.into_variant();
+            assert!(matches!(value, Variant::Date(d) if d.to_bits() == date.to_bits()));
+            assert_eq!(alloc::format!("{value}"), expected, "{date}");
+        }
+    }
+
+    #[test]
+    #[cfg_attr(debug_assertions, should_panic = "unhandled variant type: 6")]
+    fn variant_unhandled() {
+        // `VT_CY`, which setup never returns.
+        let variant = garbage_variant(6).into_variant();
+        assert!(matches!(variant, Variant::Unknown), "{variant:?}");
+    }
+
+    #[test]
+    fn filetime_display() {
+        const TICKS_PER_SECOND: u64 = 10_000_000;
+        // Seconds from 1601-01-01 to 1970-01-01.
+        const UNIX_EPOCH: u64 = 11_644_473_600;
+        let unix = |seconds: u64| filetime((UNIX_EPOCH + seconds) * TICKS_PER_SECOND);
+        let table = [
+            (filetime(0), "1601-01-01T00:00:00Z"),
+            (filetime(TICKS_PER_SECOND - 1), "1601-01-01T00:00:00Z"),
+            (unix(0), "1970-01-01T00:00:00Z"),
+            // Leap years, including a century that is one and one that isn't.
+            (unix(951_782_400), "2000-02-29T00:00:00Z"),
+            (unix(1_709_164_800), "2024-02-29T00:00:00Z"),
+            (unix(1_709_251_199), "2024-02-29T23:59:59Z"),
+            (unix(4_107_542_399), "2100-02-28T23:59:59Z"),
+            (unix(4_107_542_400), "2100-03-01T00:00:00Z"),
+            (unix(1_735_689_599), "2024-12-31T23:59:59Z"),
+            (unix(1_710_408_413), "2024-03-14T09:26:53Z"),
+            (filetime(133_500_000_000_000_000), "2024-01-17T21:20:00Z"),
+            (filetime(u64::MAX), "60056-05-28T05:36:10Z"),
+        ];
+        for (time, expected) in table {
+            assert_eq!(alloc::format!("{time}"), expected);
+        }
+    }
+
+    #[test]
+    fn filetime_order() {
+        // The high part is more significant, unlike a derived comparison.
+        let earlier = FILETIME {
+            dwLowDateTime: u32::MAX,
+            dwHighDateTime: 1,
+        };
+        let later = FILETIME {
+            dwLowDateTime: 0,
+            dwHighDateTime: 2,
+        };
+        assert!(earlier < later);
+        assert_eq!(earlier.max(later), later);
+        assert_eq!(filetime(42), filetime(42));
+        assert_ne!(filetime(42), filetime(43));
+        let mut times = [
+            filetime(3),
+            filetime(u64::MAX),
+            filetime(0),
+            filetime(1 << 32),
+        ];
+        times.sort();
+        assert_eq!(times.map(u64::from), [0, 3, 1 << 32, u64::MAX]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn filetime_to_system_time() {
+        use std::time::{Duration, SystemTime};
+
+        assert_eq!(filetime(0).to_system_time(), None);
+        assert_eq!(
+            filetime(UNIX_EPOCH).to_system_time(),
+            Some(SystemTime::UNIX_EPOCH)
+        );
+        assert_eq!(
+            filetime(UNIX_EPOCH + 1).to_system_time(),
+            Some(SystemTime::UNIX_EPOCH + Duration::from_nanos(100))
+        );
+        assert_eq!(
+            filetime(UNIX_EPOCH - 1).to_system_time(),
+            Some(SystemTime::UNIX_EPOCH - Duration::from_nanos(100))
+        );
+        // 2024-03-14T09:26:53.1234567Z
+        let ticks = UNIX_EPOCH + 1_710_408_413 * 10_000_000 + 1_234_567;
+        assert_eq!(
+            filetime(ticks).to_system_time(),
+            Some(SystemTime::UNIX_EPOCH + Duration::new(1_710_408_413, 123_456_700))
+        );
+        assert_eq!(u64::from(filetime(ticks)), ticks);
+    }
+
+    /// Times either side of the Unix epoch and leap days, and their nanoseconds since the epoch.
+    #[cfg(any(feature = "time", feature = "chrono"))]
+    const DATE_TIMES: [u64; 7] = [
+        1,
+        UNIX_EPOCH - 1,
+        UNIX_EPOCH,
+        // 2000-02-29T12:34:56.7890123Z
+        UNIX_EPOCH + 951_827_696 * 10_000_000 + 7_890_123,
+        133_500_000_000_000_000,
+        // 9999-12-31T23:59:59.9999999Z
+        2_650_467_743_999_999_999,
+        u64::MAX,
+    ];
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn filetime_to_offset_date_time() {
+        assert_eq!(filetime(0).to_offset_date_time(), None);
+        assert_eq!(filetime(u64::MAX).to_offset_date_time(), None);
+        for ticks in &DATE_TIMES[..DATE_TIMES.len() - 1] {
+            let time = filetime(*ticks);
+            let date_time = time.to_offset_date_time().unwrap();
+            let nanos = (i128::from(*ticks) - i128::from(UNIX_EPOCH)) * 100;
+            assert_eq!(date_time.unix_timestamp_nanos(), nanos, "{time}");
+            assert_eq!(date_time.offset(), time::UtcOffset::UTC);
+            let formatted = alloc::format!(
+                "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+                date_time.year(),
+                date_time.month() as u8,
+                date_time.day(),
+                date_time.hour(),
+                date_time.minute(),
+                date_time.second()
+            );
+            assert_eq!(formatted, alloc::format!("{time}"));
+        }
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn filetime_to_chrono_date_time() {
+        use chrono::{Datelike, Timelike};
+
+        assert_eq!(filetime(0).to_chrono_date_time(), None);
+        for ticks in DATE_TIMES {
+            let time = filetime(ticks);
+            let date_time = time.to_chrono_date_time().unwrap();
+            let nanos = (i128::from(ticks) - i128::from(UNIX_EPOCH)) * 100;
+            let seconds = date_time.timestamp();
+            let subsec = date_time.timestamp_subsec_nanos();
+            assert_eq!(
+                i128::from(seconds) * 1_000_000_000 + i128::from(subsec),
+                nanos
+            );
+            let formatted = alloc::format!(
+                "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+                date_time.year(),
+                date_time.month(),
+                date_time.day(),
+                date_time.hour(),
+                date_time.minute(),
+                date_time.second()
+            );
+            assert_eq!(formatted, alloc::format!("{time}"));
+        }
+    }
+
+    #[cfg(all(feature = "time", feature = "chrono"))]
+    #[test]
+    fn filetime_conversions_agree() {
+        for ticks in DATE_TIMES {
+            let time = filetime(ticks);
+            let (Some(offset), Some(chrono)) =
+                (time.to_offset_date_time(), time.to_chrono_date_time())
+            else {
+                continue;
+            };
+            let chrono_nanos = i128::from(chrono.timestamp()) * 1_000_000_000
+                + i128::from(chrono.timestamp_subsec_nanos());
+            assert_eq!(offset.unix_timestamp_nanos(), chrono_nanos, "{time}");
+        }
+    }
 }
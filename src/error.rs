@@ -0,0 +1,453 @@
+//! An error type that remembers which operation failed.
+//!
+//! The methods in this crate return a bare [`HRESULT`], as COM does.
+//! Each wrapper also has a `checked` method giving the same COM methods, but returning an
+//! [`Error`] that says which one failed. This includes the `QueryInterface` that some of them
+//! need first, e.g. for `ISetupInstance2`:
+//!
+//! ```
+//! use vssetup::error::Operation;
+//! use vssetup::guids::IID_ISetupInstance2;
+//!
+//! fn packages(instance: &vssetup::SetupInstance) -> Result<usize, vssetup::Error> {
+//!     match instance.checked().GetPackages() {
+//!         Ok(packages) => Ok(packages.iter().count()),
+//!         // Older versions of setup don't know about packages.
+//!         Err(e) if e.operation() == Some(Operation::QueryInterface(IID_ISetupInstance2)) => {
+//!             Ok(0)
+//!         }
+//!         Err(e) => Err(e),
+//!     }
+//! }
+//! ```
+//!
+//! For anything else, use [`ResultExt::context`] to say what was being done when it failed:
+//!
+//! ```
+//! use vssetup::error::{Operation, ResultExt};
+//!
+//! fn packages(instance: &vssetup::SetupInstance) -> Result<usize, vssetup::Error> {
+//!     let packages = instance.GetPackages().context(Operation::GetPackages)?;
+//!     Ok(packages.iter().count())
+//! }
+//! ```
+//!
+//! An [`Error`] converts to and from an `HRESULT`, so `?` works either way.
+
+use core::fmt;
+
+use windows_result::HRESULT;
+
+use crate::raw::{self, Interface};
+use crate::{
+    BSTR, EnumSetupInstances, FILETIME, GUID, InstanceState, Lcid, PackedVersion, SafeArray,
+    SetupConfiguration, SetupErrorInfo, SetupErrorState, SetupFailedPackageReference, SetupHelper,
+    SetupInstance, SetupInstanceCatalog, SetupLocalizedProperties, SetupLocalizedPropertyStore,
+    SetupPackageReference, SetupPolicy, SetupProductReference, SetupPropertyStore, Variant,
+    WideStr,
+};
+use core::ops::RangeInclusive;
+
+macro_rules! operations {
+    ($($method:ident),+ $(,)?) => {
+        /// What was being done when an error happened.
+        ///
+        /// Most are named after the COM method that failed.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        #[non_exhaustive]
+        pub enum Operation {
+            /// Creating the setup configuration with `CoCreateInstance`.
+            CoCreateInstance,
+            /// Casting to another interface, identified by its IID (see [`guids`](crate::guids)).
+            QueryInterface(GUID),
+            $($method,)+
+        }
+
+        impl Operation {
+            fn name(&self) -> &'static str {
+                match self {
+                    Self::CoCreateInstance => "CoCreateInstance",
+                    Self::QueryInterface(_) => "QueryInterface",
+                    $(Self::$method => stringify!($method),)+
+                }
+            }
+        }
+    };
+}
+operations!(
+    // ISetupConfiguration and ISetupConfiguration2
+    EnumInstances,
+    EnumAllInstances,
+    GetInstanceForCurrentProcess,
+    GetInstanceForPath,
+    // IEnumSetupInstances
+    Next,
+    Skip,
+    Reset,
+    Clone,
+    // ISetupInstance and ISetupInstance2
+    GetInstanceId,
+    GetInstallDate,
+    GetInstallationName,
+    GetInstallationPath,
+    GetInstallationVersion,
+    GetDisplayName,
+    GetDescription,
+    ResolvePath,
+    GetState,
+    GetPackages,
+    GetProduct,
+    GetProductPath,
+    GetErrors,
+    IsLaunchable,
+    IsComplete,
+    GetProperties,
+    GetEnginePath,
+    // ISetupInstanceCatalog and ISetupLocalizedProperties
+    GetCatalogInfo,
+    IsPrerelease,
+    GetLocalizedProperties,
+    GetLocalizedChannelProperties,
+    // ISetupPackageReference and friends
+    GetId,
+    GetVersion,
+    GetChip,
+    GetLanguage,
+    GetBranch,
+    GetType,
+    GetUniqueId,
+    GetIsExtension,
+    GetIsInstalled,
+    GetSupportsExtensions,
+    // ISetupErrorState and friends
+    GetFailedPackages,
+    GetSkippedPackages,
+    GetErrorLogFilePath,
+    GetLogFilePath,
+    GetRuntimeError,
+    GetSignature,
+    GetDetails,
+    GetAffectedPackages,
+    GetAction,
+    GetReturnCode,
+    GetErrorHResult,
+    GetErrorClassName,
+    GetErrorMessage,
+    // Property stores and the policy
+    GetNames,
+    GetValue,
+    GetSharedInstallationPath,
+    // ISetupHelper
+    ParseVersion,
+    ParseVersionRange,
+);
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())?;
+        if let Self::QueryInterface(iid) = self {
+            write!(f, " for {iid}")?;
+        }
+        Ok(())
+    }
+}
+
+/// An [`HRESULT`] and, if known, the [`Operation`] that returned it.
+///
+/// ```
+/// use vssetup::error::Operation;
+/// use vssetup::{Error, hresults};
+///
+/// let error = Error::new(hresults::E_NOINTERFACE, Operation::GetPackages);
+/// assert_eq!(format!("{error}"), "GetPackages failed with 0x80004002");
+/// assert_eq!(vssetup::HRESULT::from(error), hresults::E_NOINTERFACE);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Error {
+    hresult: HRESULT,
+    operation: Option<Operation>,
+}
+
+impl Error {
+    pub fn new(hresult: HRESULT, operation: Operation) -> Self {
+        Self {
+            hresult,
+            operation: Some(operation),
+        }
+    }
+
+    pub fn hresult(&self) -> HRESULT {
+        self.hresult
+    }
+
+    /// What failed, or `None` if this was converted from a bare `HRESULT`.
+    pub fn operation(&self) -> Option<Operation> {
+        self.operation
+    }
+}
+
+impl From<HRESULT> for Error {
+    fn from(hresult: HRESULT) -> Self {
+        Self {
+            hresult,
+            operation: None,
+        }
+    }
+}
+
+impl From<Error> for HRESULT {
+    fn from(error: Error) -> Self {
+        error.hresult
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.operation {
+            Some(operation) => write!(f, "{operation} failed with {}", self.hresult),
+            None => write!(f, "failed with {}", self.hresult),
+        }
+    }
+}
+
+impl core::error::Error for Error {}
+
+/// Adds an [`Operation`] to the error of a `Result<T, HRESULT>`.
+pub trait ResultExt<T>: sealed::Sealed {
+    fn context(self, operation: Operation) -> Result<T, Error>;
+}
+
+impl<T> ResultExt<T> for Result<T, HRESULT> {
+    fn context(self, operation: Operation) -> Result<T, Error> {
+        self.map_err(|hresult| Error::new(hresult, operation))
+    }
+}
+
+/// The COM methods of a wrapper, returning an [`Error`] that says which one failed.
+///
+/// Get one from the wrapper's `checked` method, e.g. [`SetupInstance::checked`].
+/// Methods that first cast to a newer interface report a failed cast as
+/// [`Operation::QueryInterface`] for that interface, rather than as the method itself.
+#[derive(Debug)]
+pub struct Checked<'a, T>(&'a T);
+
+impl<T> Clone for Checked<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Checked<'_, T> {}
+
+/// Check `com_ptr` implements `I`, as the wrapped method is about to find out.
+fn query<I: Interface>(com_ptr: &impl Interface) -> Result<(), Error> {
+    com_ptr
+        .cast::<I>()
+        .map(drop)
+        .map_err(|hresult| Error::new(hresult, Operation::QueryInterface(I::IID)))
+}
+
+macro_rules! checked {
+    ($($wrapper:ident {
+        $(
+            fn $method:ident $(<$lifetime:lifetime>)? ($($arg:ident: $arg_ty:ty),*) -> $ret:ty
+            $(, $how:ident $interface:ident)?;
+        )+
+    })+) => {$(
+        impl $wrapper {
+            #[doc = concat!(
+                "The COM methods of `", stringify!($wrapper),
+                "`, returning an [`Error`] that says which one failed.",
+            )]
+            pub fn checked(&self) -> Checked<'_, Self> {
+                Checked(self)
+            }
+        }
+
+        #[allow(non_snake_case)]
+        impl Checked<'_, $wrapper> {$(
+            #[doc = concat!(
+                "[`", stringify!($wrapper), "::", stringify!($method),
+                "`], with the operation that failed.",
+            )]
+            pub fn $method$(<$lifetime>)?(&self, $($arg: $arg_ty),*) -> Result<$ret, Error> {
+                checked!(@call self.0, $method($($arg),*) $(, $how $interface)?)
+            }
+        )+}
+    )+};
+    (@call $this:expr, $method:ident($($arg:ident),*)) => {
+        $this.$method($($arg),*).context(Operation::$method)
+    };
+    (@call $this:expr, $method:ident($($arg:ident),*), via $interface:ident) => {{
+        query::<raw::$interface>($this.com_ptr())?;
+        $this.$method($($arg),*).context(Operation::$method)
+    }};
+    (@call $this:expr, $method:ident($($arg:ident),*), cast $interface:ident) => {
+        $this
+            .$method($($arg),*)
+            .context(Operation::QueryInterface(<raw::$interface as Interface>::IID))
+    };
+}
+checked! {
+    SetupConfiguration {
+        fn EnumInstances() -> EnumSetupInstances;
+        fn EnumAllInstances() -> EnumSetupInstances, via ISetupConfiguration2;
+        fn GetInstanceForCurrentProcess() -> SetupInstance;
+        fn GetInstanceForPath<'w>(path: impl TryInto<WideStr<'w>>) -> SetupInstance;
+        fn to_policy() -> SetupPolicy, cast ISetupPolicy;
+        fn to_helper() -> SetupHelper, cast ISetupHelper;
+    }
+    EnumSetupInstances {
+        fn Skip(count: u32) -> bool;
+        fn Clone() -> EnumSetupInstances;
+    }
+    SetupInstance {
+        fn GetInstanceId() -> BSTR;
+        fn GetInstallDate() -> FILETIME;
+        fn GetInstallationName() -> BSTR;
+        fn GetInstallationPath() -> BSTR;
+        fn GetInstallationVersion() -> BSTR;
+        fn GetDisplayName(lcid: impl Into<Lcid>) -> BSTR;
+        fn GetDescription(lcid: impl Into<Lcid>) -> BSTR;
+        fn ResolvePath<'w>(relative_path: impl TryInto<WideStr<'w>>) -> BSTR;
+        fn GetProductPath() -> BSTR, via ISetupInstance2;
+        fn GetEnginePath() -> BSTR, via ISetupInstance2;
+        fn IsLaunchable() -> bool, via ISetupInstance2;
+        fn IsComplete() -> bool, via ISetupInstance2;
+        fn GetProduct() -> Option<SetupProductReference>, via ISetupInstance2;
+        fn GetState() -> InstanceState, via ISetupInstance2;
+        fn GetPackages() -> SafeArray<SetupPackageReference>, via ISetupInstance2;
+        fn GetProperties() -> Option<SetupPropertyStore>, via ISetupInstance2;
+        fn GetErrors() -> Option<SetupErrorState>, via ISetupInstance2;
+        fn to_catalog() -> SetupInstanceCatalog, cast ISetupInstanceCatalog;
+        fn to_property_store() -> SetupPropertyStore, cast ISetupPropertyStore;
+        fn to_localized_properties() -> SetupLocalizedProperties, cast ISetupLocalizedProperties;
+    }
+    SetupProductReference {
+        fn GetIsInstalled() -> bool, via ISetupProductReference;
+        fn GetSupportsExtensions() -> bool, via ISetupProductReference2;
+    }
+    SetupErrorState {
+        fn GetFailedPackages() -> Option<SafeArray<SetupFailedPackageReference>>;
+        fn GetSkippedPackages() -> Option<SafeArray<SetupPackageReference>>;
+        fn GetErrorLogFilePath() -> BSTR, via ISetupErrorState2;
+        fn GetLogFilePath() -> BSTR, via ISetupErrorState2;
+        fn GetRuntimeError() -> Option<SetupErrorInfo>, via ISetupErrorState3;
+    }
+    SetupErrorInfo {
+        fn GetErrorHResult() -> HRESULT;
+        fn GetErrorClassName() -> BSTR;
+        fn GetErrorMessage() -> BSTR;
+    }
+    SetupFailedPackageReference {
+        fn GetLogFilePath() -> BSTR, via ISetupFailedPackageReference2;
+        fn GetDescription() -> BSTR, via ISetupFailedPackageReference2;
+        fn GetSignature() -> BSTR, via ISetupFailedPackageReference2;
+        fn GetDetails() -> SafeArray<BSTR>, via ISetupFailedPackageReference2;
+        fn GetAffectedPackages() -> Option<SafeArray<SetupPackageReference>>,
+            via ISetupFailedPackageReference2;
+        fn GetAction() -> BSTR, via ISetupFailedPackageReference3;
+        fn GetReturnCode() -> BSTR, via ISetupFailedPackageReference3;
+    }
+    SetupPropertyStore {
+        fn GetNames() -> SafeArray<BSTR>;
+        fn GetValue<'w>(name: impl TryInto<WideStr<'w>>) -> Variant;
+    }
+    SetupLocalizedProperties {
+        fn GetLocalizedProperties() -> Option<SetupLocalizedPropertyStore>;
+        fn GetLocalizedChannelProperties() -> Option<SetupLocalizedPropertyStore>;
+    }
+    SetupLocalizedPropertyStore {
+        fn GetNames(lcid: impl Into<Lcid>) -> SafeArray<BSTR>;
+        fn GetValue<'w>(name: impl TryInto<WideStr<'w>>, lcid: impl Into<Lcid>) -> Variant;
+    }
+    SetupPackageReference {
+        fn GetId() -> BSTR;
+        fn GetVersion() -> BSTR;
+        fn GetChip() -> BSTR;
+        fn GetLanguage() -> BSTR;
+        fn GetBranch() -> BSTR;
+        fn GetType() -> BSTR;
+        fn GetUniqueId() -> BSTR;
+        fn GetIsExtension() -> bool;
+    }
+    SetupInstanceCatalog {
+        fn GetCatalogInfo() -> Option<SetupPropertyStore>;
+        fn IsPrerelease() -> bool;
+    }
+    SetupHelper {
+        fn ParseVersion<'w>(version: impl TryInto<WideStr<'w>>) -> PackedVersion;
+        fn ParseVersionRange<'w>(range: impl TryInto<WideStr<'w>>) -> RangeInclusive<PackedVersion>;
+    }
+    SetupPolicy {
+        fn GetSharedInstallationPath() -> BSTR;
+        fn GetValue<'w>(name: impl TryInto<WideStr<'w>>) -> Variant;
+    }
+}
+
+#[allow(non_snake_case)]
+impl Checked<'_, EnumSetupInstances> {
+    /// [`EnumSetupInstances::Next`], with the operation that failed.
+    pub fn Next<'a>(
+        &self,
+        instances: &'a mut [Option<SetupInstance>],
+    ) -> Result<Option<&'a [SetupInstance]>, Error> {
+        self.0.Next(instances).context(Operation::Next)
+    }
+}
+
+mod sealed {
+    pub trait Sealed {}
+    impl<T> Sealed for Result<T, windows_result::HRESULT> {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hresults::{E_INVALIDARG, E_NOINTERFACE, E_NOTFOUND};
+    use alloc::format;
+
+    #[test]
+    fn conversions() {
+        let error = Error::from(E_NOTFOUND);
+        assert_eq!(error.hresult(), E_NOTFOUND);
+        assert_eq!(error.operation(), None);
+        assert_eq!(HRESULT::from(error), E_NOTFOUND);
+
+        let error = Error::new(E_INVALIDARG, Operation::ResolvePath);
+        assert_eq!(error.operation(), Some(Operation::ResolvePath));
+        assert_eq!(HRESULT::from(error), E_INVALIDARG);
+
+        fn question_mark(result: Result<(), Error>) -> Result<(), HRESULT> {
+            result?;
+            Ok(())
+        }
+        assert_eq!(question_mark(Err(error)), Err(E_INVALIDARG));
+
+        let result: Result<(), HRESULT> = Err(E_NOINTERFACE);
+        assert_eq!(
+            result.context(Operation::GetPackages),
+            Err(Error::new(E_NOINTERFACE, Operation::GetPackages))
+        );
+        assert_eq!(Ok::<_, HRESULT>(1).context(Operation::Next), Ok(1));
+    }
+
+    #[test]
+    fn display() {
+        let iid = crate::guids::IID_ISetupInstance2;
+        let table = [
+            (Error::from(E_NOTFOUND), "failed with 0x80070490"),
+            (
+                Error::new(E_NOTFOUND, Operation::GetInstanceForPath),
+                "GetInstanceForPath failed with 0x80070490",
+            ),
+            (
+                Error::new(E_NOINTERFACE, Operation::QueryInterface(iid)),
+                "QueryInterface for {89143C9A-05AF-49B0-B717-72E218A2185C} failed with 0x80004002",
+            ),
+        ];
+        for (error, expected) in table {
+            assert_eq!(format!("{error}"), expected);
+        }
+    }
+}
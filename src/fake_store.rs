@@ -0,0 +1,19 @@
+//! A table-backed stand-in for the stores the crate reads settings from, for tests.
+
+use crate::HRESULT;
+
+/// A table of `(key, value)` entries.
+///
+/// An entry whose value is an error makes looking it up fail, as a broken store would.
+pub(crate) struct FakeStore<'a, K, V>(pub(crate) &'a [(K, Result<V, HRESULT>)]);
+
+impl<K, V> FakeStore<'_, K, V> {
+    /// The value of the first entry whose key matches, or `None` if there isn't one.
+    pub(crate) fn find(&self, matches: impl Fn(&K) -> bool) -> Result<Option<&V>, HRESULT> {
+        match self.0.iter().find(|(key, _)| matches(key)) {
+            Some((_, Ok(value))) => Ok(Some(value)),
+            Some((_, Err(error))) => Err(*error),
+            None => Ok(None),
+        }
+    }
+}
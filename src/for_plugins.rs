@@ -0,0 +1,32 @@
+//! Using this crate from a plugin, such as a shell extension or an IDE add-in.
+//!
+//! Plugins don't own the process they run in. The host may already have initialized COM
+//! on the calling thread (possibly with a different apartment model), and some calls arrive
+//! while the loader lock is held, e.g. from `DllMain`. Initializing COM there can deadlock.
+//!
+//! The safe pattern is to never touch COM on the caller's thread at all.
+//! [`com::ensure_started_from_plugin`](crate::com::ensure_started_from_plugin) starts a
+//! dedicated worker thread that initializes COM and does all the work sent to it,
+//! sending back plain Rust values (not COM objects) to the caller.
+//!
+//! ```rust
+//! use vssetup::{HRESULT, SetupConfiguration, com, strings};
+//!
+//! # #[cfg(feature = "std")]
+//! fn installation_paths() -> Result<Vec<std::path::PathBuf>, HRESULT> {
+//!     let worker = com::ensure_started_from_plugin()?;
+//!     worker.run(|| {
+//!         let setup = SetupConfiguration::new()?;
+//!         let mut paths = Vec::new();
+//!         for instance in setup.enum_all_instances()? {
+//!             paths.push(strings::bstr_to_pathbuf(&instance.get_installation_path()?));
+//!         }
+//!         Ok(paths)
+//!     })?
+//! }
+//! ```
+//!
+//! Don't start the worker or wait on it from inside `DllMain` itself.
+//! If the host may call you from there, call [`com::set_in_dll_main`](crate::com::set_in_dll_main)
+//! so that [`com::ensure_started_from_plugin`](crate::com::ensure_started_from_plugin) and
+//! [`com::try_initialize_nonblocking`](crate::com::try_initialize_nonblocking) will refuse rather than risk a deadlock, and defer the work until after loading has finished.
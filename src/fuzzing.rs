@@ -0,0 +1,28 @@
+//! Entry points for the fuzz targets in `fuzz/`.
+//!
+//! Only built with `--cfg fuzzing`, which `cargo fuzz` sets. Not part of the public API.
+
+use alloc::vec::Vec;
+
+use crate::HRESULT;
+
+pub fn major_version(version: &[u16]) -> Option<u16> {
+    crate::integrate::major_version(version)
+}
+
+/// Encode with `with_wide`'s buffers, returning the null-terminated result.
+pub fn encode_wide(units: &[u16]) -> Result<Vec<u16>, HRESULT> {
+    let mut stack = [0; crate::strings::STACK_LEN];
+    let mut heap = Vec::new();
+    crate::strings::encode(units.iter().copied(), &mut stack, &mut heap).map(<[u16]>::to_vec)
+}
+
+#[cfg(feature = "std")]
+pub fn is_toolset_component(id: &str, prefix: &str) -> bool {
+    crate::toolset::is_toolset_component(id, prefix)
+}
+
+#[cfg(feature = "std")]
+pub fn normalize_path(path: &[u16]) -> Vec<u16> {
+    crate::owner::normalize(path)
+}
@@ -0,0 +1,66 @@
+//! The class id of the setup configuration and the ids of its interfaces.
+//!
+//! These are useful for creating the class yourself, e.g. with a different `CLSCTX`,
+//! or for logging which interface a failed `QueryInterface` was for.
+//! The names match those in `Setup.Configuration.h`.
+
+pub use crate::defs::GUID;
+use crate::raw::{self, Interface};
+
+/// The class id to pass to `CoCreateInstance`, as used by [`SetupConfiguration::new`](crate::SetupConfiguration::new).
+pub const CLSID_SetupConfiguration: GUID = raw::SETUP_CONFIGURATION_CLSID;
+
+macro_rules! iids {
+    ($($iid:ident: $interface:ident),+ $(,)?) => {$(
+        #[doc = concat!("The id of `", stringify!($interface), "`.")]
+        pub const $iid: GUID = raw::$interface::IID;
+    )+};
+}
+iids!(
+    IID_ISetupInstance: ISetupInstance,
+    IID_ISetupInstance2: ISetupInstance2,
+    IID_ISetupInstanceCatalog: ISetupInstanceCatalog,
+    IID_ISetupLocalizedProperties: ISetupLocalizedProperties,
+    IID_IEnumSetupInstances: IEnumSetupInstances,
+    IID_ISetupConfiguration: ISetupConfiguration,
+    IID_ISetupConfiguration2: ISetupConfiguration2,
+    IID_ISetupPackageReference: ISetupPackageReference,
+    IID_ISetupHelper: ISetupHelper,
+    IID_ISetupErrorState: ISetupErrorState,
+    IID_ISetupErrorState2: ISetupErrorState2,
+    IID_ISetupErrorState3: ISetupErrorState3,
+    IID_ISetupFailedPackageReference: ISetupFailedPackageReference,
+    IID_ISetupFailedPackageReference2: ISetupFailedPackageReference2,
+    IID_ISetupFailedPackageReference3: ISetupFailedPackageReference3,
+    IID_ISetupPropertyStore: ISetupPropertyStore,
+    IID_ISetupLocalizedPropertyStore: ISetupLocalizedPropertyStore,
+    IID_ISetupPolicy: ISetupPolicy,
+    IID_ISetupErrorInfo: ISetupErrorInfo,
+    IID_ISetupProductReference: ISetupProductReference,
+    IID_ISetupProductReference2: ISetupProductReference2,
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::format;
+
+    #[test]
+    fn display() {
+        assert_eq!(
+            format!("{CLSID_SetupConfiguration}"),
+            "{177F0C4A-1CD3-4DE7-A32C-71DBBB9FA36D}"
+        );
+        assert_eq!(
+            format!("{IID_ISetupInstance:?}"),
+            "{B41463C3-8866-43B5-BC33-2B0676F7F42E}"
+        );
+        assert_eq!(
+            format!(
+                "{}",
+                GUID::from_u128(0x0000000a_000b_000c_0d0e_00000000000f)
+            ),
+            "{0000000A-000B-000C-0D0E-00000000000F}"
+        );
+    }
+}
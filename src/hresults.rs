@@ -0,0 +1,119 @@
+//! Named `HRESULT`s returned by setup and COM, so errors can be matched without magic numbers.
+//!
+//! ```
+//! use vssetup::hresults;
+//!
+//! # let err = hresults::E_NOTFOUND;
+//! match err {
+//!     hresults::E_NOTFOUND => println!("not installed"),
+//!     hresults::REGDB_E_CLASSNOTREG => println!("the installer isn't registered"),
+//!     err => println!("{err:?}"),
+//! }
+//! ```
+
+use windows_result::HRESULT;
+
+pub use crate::com::E_POSSIBLE_DEADLOCK;
+
+pub const S_OK: HRESULT = HRESULT(0);
+pub const S_FALSE: HRESULT = HRESULT(0x1);
+pub const E_POINTER: HRESULT = HRESULT(0x80004003_u32 as i32);
+pub const E_INVALIDARG: HRESULT = HRESULT(0x80070057_u32 as i32);
+pub const E_UNEXPECTED: HRESULT = HRESULT(0x8000FFFF_u32 as i32);
+pub const E_NOINTERFACE: HRESULT = HRESULT(0x80004002_u32 as i32);
+pub const E_FAIL: HRESULT = HRESULT(0x80004005_u32 as i32);
+pub const E_OUTOFMEMORY: HRESULT = HRESULT(0x8007000E_u32 as i32);
+/// `HRESULT_FROM_WIN32(ERROR_NOT_FOUND)`
+///
+/// Setup returns this when there's nothing to find, e.g. a path that isn't part of any instance.
+pub const E_NOTFOUND: HRESULT = HRESULT(0x80070490_u32 as i32);
+/// `HRESULT_FROM_WIN32(ERROR_NOT_SUPPORTED)`
+///
+/// Setup returns this for things an older version of the installer doesn't support.
+pub const E_NOTSUPPORTED: HRESULT = HRESULT(0x80070032_u32 as i32);
+/// A [`Variant`](crate::Variant) was converted to a type it doesn't hold, e.g. a number to a `String`.
+pub const DISP_E_TYPEMISMATCH: HRESULT = HRESULT(0x80020005_u32 as i32);
+/// A [`Variant`](crate::Variant) holds a number that doesn't fit the type it was converted to.
+pub const DISP_E_OVERFLOW: HRESULT = HRESULT(0x8002000A_u32 as i32);
+/// Returned by [`SetupConfiguration::new`](crate::SetupConfiguration::new) when the installer isn't installed.
+/// See [`is_installer_missing`].
+pub const REGDB_E_CLASSNOTREG: HRESULT = HRESULT(0x80040154_u32 as i32);
+/// Returned by [`SetupConfiguration::new`](crate::SetupConfiguration::new) when COM isn't initialized.
+/// See [`is_com_not_initialized`].
+pub const CO_E_NOTINITIALIZED: HRESULT = HRESULT(0x800401F0_u32 as i32);
+
+/// Whether setup couldn't find what was asked for, i.e. [`E_NOTFOUND`].
+pub fn is_not_found(hresult: HRESULT) -> bool {
+    hresult == E_NOTFOUND
+}
+
+/// Whether the Visual Studio Installer has never been installed on this machine,
+/// i.e. [`REGDB_E_CLASSNOTREG`] from [`SetupConfiguration::new`](crate::SetupConfiguration::new).
+///
+/// Machines without the installer have no instances,
+/// so this usually means "Visual Studio is not installed".
+pub fn is_installer_missing(hresult: HRESULT) -> bool {
+    hresult == REGDB_E_CLASSNOTREG
+}
+
+/// Whether COM hasn't been initialized on this thread, i.e. [`CO_E_NOTINITIALIZED`].
+///
+/// Call [`com::initialize`](crate::com::initialize) first,
+/// or use [`SetupConfiguration::new_initializing`](crate::SetupConfiguration::new_initializing).
+pub fn is_com_not_initialized(hresult: HRESULT) -> bool {
+    hresult == CO_E_NOTINITIALIZED
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values() {
+        // These come from `winerror.h` and must never change.
+        let table = [
+            (S_OK, 0x00000000),
+            (S_FALSE, 0x00000001),
+            (E_POINTER, 0x80004003),
+            (E_INVALIDARG, 0x80070057),
+            (E_UNEXPECTED, 0x8000FFFF),
+            (E_NOINTERFACE, 0x80004002),
+            (E_FAIL, 0x80004005),
+            (E_OUTOFMEMORY, 0x8007000E),
+            (E_NOTFOUND, 0x80070490),
+            (E_NOTSUPPORTED, 0x80070032),
+            (DISP_E_TYPEMISMATCH, 0x80020005),
+            (DISP_E_OVERFLOW, 0x8002000A),
+            (REGDB_E_CLASSNOTREG, 0x80040154),
+            (CO_E_NOTINITIALIZED, 0x800401F0),
+            (E_POSSIBLE_DEADLOCK, 0x8007046B),
+        ];
+        for (hresult, value) in table {
+            assert_eq!(hresult.0 as u32, value, "{hresult:?}");
+        }
+    }
+
+    #[test]
+    fn not_found() {
+        assert!(is_not_found(E_NOTFOUND));
+        for other in [S_OK, S_FALSE, E_FAIL, E_NOTSUPPORTED, REGDB_E_CLASSNOTREG] {
+            assert!(!is_not_found(other), "{other:?}");
+        }
+    }
+
+    #[test]
+    fn installer_missing() {
+        assert!(is_installer_missing(REGDB_E_CLASSNOTREG));
+        for other in [S_OK, E_NOTFOUND, CO_E_NOTINITIALIZED, E_NOINTERFACE] {
+            assert!(!is_installer_missing(other), "{other:?}");
+        }
+    }
+
+    #[test]
+    fn com_not_initialized() {
+        assert!(is_com_not_initialized(CO_E_NOTINITIALIZED));
+        for other in [S_OK, E_NOTFOUND, REGDB_E_CLASSNOTREG, E_POSSIBLE_DEADLOCK] {
+            assert!(!is_com_not_initialized(other), "{other:?}");
+        }
+    }
+}
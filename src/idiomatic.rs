@@ -0,0 +1,265 @@
+//! The same functionality with idiomatic names and types.
+//!
+//! The rest of the crate mirrors the COM API, so it's easy to cross-reference with Microsoft's
+//! documentation. This module is a thin layer over it that returns `String`s and `PathBuf`s
+//! rather than `BSTR`s, and an [`Error`] that says which operation failed.
+//!
+//! ```
+//! use vssetup::idiomatic::Config;
+//!
+//! fn main() -> Result<(), vssetup::Error> {
+//!     vssetup::com::initialize()?;
+//!     let config = Config::new()?;
+//!     for instance in config.all_instances()? {
+//!         println!("{}: {}", instance.display_name()?, instance.path()?.display());
+//!     }
+//!     Ok(())
+//! }
+//! ```
+//!
+//! The underlying types are always available, e.g. from [`Instance::as_setup_instance`],
+//! for anything not covered here.
+
+use std::path::{Path, PathBuf};
+use std::string::String;
+use std::vec::Vec;
+
+use crate::error::{Operation, ResultExt};
+use crate::{
+    Error, FILETIME, InstanceId, Lcid, ProductId, SetupConfiguration, SetupInstance,
+    SetupPackageReference, Version, strings,
+};
+
+/// Finds installed instances. Wraps a [`SetupConfiguration`].
+#[derive(Debug, Clone)]
+pub struct Config {
+    setup: SetupConfiguration,
+}
+
+impl Config {
+    /// Connect to setup. COM must be initialized, see [`com::initialize`](crate::com::initialize).
+    pub fn new() -> Result<Self, Error> {
+        let setup = SetupConfiguration::new().context(Operation::CoCreateInstance)?;
+        Ok(Self { setup })
+    }
+
+    /// The complete, launchable instances.
+    pub fn instances(&self) -> Result<Vec<Instance>, Error> {
+        let instances = self
+            .setup
+            .EnumInstances()
+            .context(Operation::EnumInstances)?;
+        collect(instances.collect_all())
+    }
+
+    /// Every instance, including those that are incomplete or can't be launched.
+    pub fn all_instances(&self) -> Result<Vec<Instance>, Error> {
+        let instances = self
+            .setup
+            .EnumAllInstances()
+            .context(Operation::EnumAllInstances)?;
+        collect(instances.collect_all())
+    }
+
+    /// The newest complete, launchable instance, excluding prereleases.
+    ///
+    /// Instances that can't be read are skipped, so an error can only come from enumerating them.
+    /// It's reported as [`Operation::EnumAllInstances`] even if it was the `QueryInterface` for
+    /// `ISetupConfiguration2` that failed; use [`Checked`](crate::error::Checked) to tell them apart.
+    pub fn latest(&self) -> Result<Option<Instance>, Error> {
+        let latest = self.setup.latest().context(Operation::EnumAllInstances)?;
+        Ok(latest.map(Instance::from))
+    }
+
+    /// The instance that `path` belongs to, if any.
+    pub fn instance_for_path(&self, path: impl AsRef<Path>) -> Result<Option<Instance>, Error> {
+        let instance = self
+            .setup
+            .instance_for_path(path)
+            .context(Operation::GetInstanceForPath)?;
+        Ok(instance.map(Instance::from))
+    }
+
+    /// The instance the current process belongs to, if any.
+    pub fn instance_for_current_process(&self) -> Result<Option<Instance>, Error> {
+        let instance = self
+            .setup
+            .instance_for_current_process()
+            .context(Operation::GetInstanceForCurrentProcess)?;
+        Ok(instance.map(Instance::from))
+    }
+
+    pub fn as_setup_configuration(&self) -> &SetupConfiguration {
+        &self.setup
+    }
+}
+
+impl From<SetupConfiguration> for Config {
+    fn from(setup: SetupConfiguration) -> Self {
+        Self { setup }
+    }
+}
+
+fn collect(instances: Result<Vec<SetupInstance>, crate::HRESULT>) -> Result<Vec<Instance>, Error> {
+    let instances = instances.context(Operation::Next)?;
+    Ok(instances.into_iter().map(Instance::from).collect())
+}
+
+/// An installed instance of Visual Studio (or the Build Tools, etc). Wraps a [`SetupInstance`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instance {
+    instance: SetupInstance,
+}
+
+impl Instance {
+    /// The id, e.g. `c0ffee42`.
+    pub fn id(&self) -> Result<InstanceId, Error> {
+        self.instance
+            .instance_id()
+            .context(Operation::GetInstanceId)
+    }
+
+    /// The display name in the user's language, e.g. `Visual Studio Community 2022`.
+    pub fn display_name(&self) -> Result<String, Error> {
+        let name = self
+            .instance
+            .GetDisplayName(Lcid::USER_DEFAULT)
+            .context(Operation::GetDisplayName)?;
+        Ok(strings::bstr_to_string_lossy(&name))
+    }
+
+    /// The description in the user's language.
+    pub fn description(&self) -> Result<String, Error> {
+        let description = self
+            .instance
+            .GetDescription(Lcid::USER_DEFAULT)
+            .context(Operation::GetDescription)?;
+        Ok(strings::bstr_to_string_lossy(&description))
+    }
+
+    /// The installation name, e.g. `VisualStudio/17.9.5+34714.143`.
+    pub fn name(&self) -> Result<String, Error> {
+        let name = self
+            .instance
+            .GetInstallationName()
+            .context(Operation::GetInstallationName)?;
+        Ok(strings::bstr_to_string_lossy(&name))
+    }
+
+    /// The installation path, e.g. `C:\Program Files\Microsoft Visual Studio\2022\Community`.
+    pub fn path(&self) -> Result<PathBuf, Error> {
+        self.instance
+            .installation_path()
+            .context(Operation::GetInstallationPath)
+    }
+
+    /// The installation version, e.g. `17.9.34723.18`.
+    pub fn version(&self) -> Result<Version, Error> {
+        self.instance
+            .installation_version()
+            .context(Operation::GetInstallationVersion)
+    }
+
+    /// When this instance was first installed.
+    pub fn install_date(&self) -> Result<FILETIME, Error> {
+        self.instance
+            .GetInstallDate()
+            .context(Operation::GetInstallDate)
+    }
+
+    /// The product, e.g. Community or Build Tools, or `None` if the instance has no product reference.
+    pub fn product(&self) -> Result<Option<ProductId>, Error> {
+        self.instance.product_id().context(Operation::GetProduct)
+    }
+
+    /// The full path to the product's main executable, e.g. `...\Common7\IDE\devenv.exe`.
+    pub fn product_path(&self) -> Result<PathBuf, Error> {
+        self.instance
+            .product_path()
+            .context(Operation::GetProductPath)
+    }
+
+    /// The path to the installer engine used to install this instance.
+    pub fn engine_path(&self) -> Result<PathBuf, Error> {
+        self.instance
+            .engine_path()
+            .context(Operation::GetEnginePath)
+    }
+
+    /// The full path of `relative_path` within this instance, e.g. of `VC\Tools\MSVC`.
+    pub fn resolve(&self, relative_path: impl AsRef<Path>) -> Result<PathBuf, Error> {
+        self.instance
+            .resolve(relative_path)
+            .context(Operation::ResolvePath)
+    }
+
+    pub fn is_complete(&self) -> Result<bool, Error> {
+        self.instance.IsComplete().context(Operation::IsComplete)
+    }
+
+    pub fn is_launchable(&self) -> Result<bool, Error> {
+        self.instance
+            .IsLaunchable()
+            .context(Operation::IsLaunchable)
+    }
+
+    /// Whether this is a prerelease, e.g. Preview.
+    pub fn is_prerelease(&self) -> Result<bool, Error> {
+        self.instance
+            .is_prerelease()
+            .context(Operation::IsPrerelease)
+    }
+
+    /// The installed packages: workloads, components and so on.
+    pub fn packages(&self) -> Result<Vec<Package>, Error> {
+        let packages = self
+            .instance
+            .GetPackages()
+            .context(Operation::GetPackages)?;
+        Ok(packages
+            .iter()
+            .map(|package| Package {
+                package: package.clone(),
+            })
+            .collect())
+    }
+
+    pub fn as_setup_instance(&self) -> &SetupInstance {
+        &self.instance
+    }
+}
+
+impl From<SetupInstance> for Instance {
+    fn from(instance: SetupInstance) -> Self {
+        Self { instance }
+    }
+}
+
+/// A package installed in an instance. Wraps a [`SetupPackageReference`].
+#[derive(Debug, Clone)]
+pub struct Package {
+    package: SetupPackageReference,
+}
+
+impl Package {
+    /// The id, e.g. `Microsoft.VisualStudio.Component.VC.Tools.x86.x64`.
+    pub fn id(&self) -> Result<String, Error> {
+        let id = self.package.GetId().context(Operation::GetId)?;
+        Ok(strings::bstr_to_string_lossy(&id))
+    }
+
+    pub fn version(&self) -> Result<String, Error> {
+        let version = self.package.GetVersion().context(Operation::GetVersion)?;
+        Ok(strings::bstr_to_string_lossy(&version))
+    }
+
+    /// The kind of package, e.g. `Workload`, `Component` or `Vsix`.
+    pub fn kind(&self) -> Result<String, Error> {
+        let kind = self.package.GetType().context(Operation::GetType)?;
+        Ok(strings::bstr_to_string_lossy(&kind))
+    }
+
+    pub fn as_package_reference(&self) -> &SetupPackageReference {
+        &self.package
+    }
+}
@@ -0,0 +1,502 @@
+//! Owned copies of the details of setup objects.
+//!
+//! These don't hold on to any COM objects so they can be kept, compared and sent between
+//! threads freely.
+
+use alloc::string::String;
+#[cfg(feature = "std")]
+use alloc::vec::Vec;
+
+use crate::{
+    BSTR, E_NOTFOUND, HRESULT, Lcid, SetupPackageReference, SetupPolicy, Variant, strings,
+};
+#[cfg(feature = "std")]
+use crate::{InstanceId, InstanceState, ProductId, SetupInstance, SetupInstanceCatalog, Version};
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+/// The details of a [`SetupPackageReference`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PackageInfo {
+    pub id: String,
+    pub version: String,
+    pub chip: String,
+    pub language: String,
+    pub branch: String,
+    /// The package type, e.g. `Component`, `Workload` or `Vsix`.
+    pub kind: String,
+    pub unique_id: String,
+    pub is_extension: bool,
+}
+
+impl PackageInfo {
+    pub fn from_package(package: &SetupPackageReference) -> Result<Self, HRESULT> {
+        let string = |bstr: Result<BSTR, HRESULT>| bstr.map(|b| strings::bstr_to_string_lossy(&b));
+        Ok(Self {
+            id: string(package.get_id())?,
+            version: string(package.get_version())?,
+            chip: string(package.get_chip())?,
+            language: string(package.get_language())?,
+            branch: string(package.get_branch())?,
+            kind: string(package.get_type())?,
+            unique_id: string(package.get_unique_id())?,
+            is_extension: package.get_is_extension()?,
+        })
+    }
+
+    /// Whether this package is an extension, either because setup says so or because it's a VSIX.
+    pub fn is_extension_or_vsix(&self) -> bool {
+        self.is_extension || self.kind.eq_ignore_ascii_case("Vsix")
+    }
+}
+
+/// What [`InstanceSnapshot::from_instance`] reads.
+///
+/// The default reads everything, with names in the user's language.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SnapshotOptions {
+    /// Read the installed packages. There can be thousands, so this is the slowest part.
+    pub packages: bool,
+    /// The language of the display name and description.
+    pub lcid: Lcid,
+}
+
+impl Default for SnapshotOptions {
+    fn default() -> Self {
+        Self {
+            packages: true,
+            lcid: Lcid::USER_DEFAULT,
+        }
+    }
+}
+
+impl SnapshotOptions {
+    pub fn packages(mut self, packages: bool) -> Self {
+        self.packages = packages;
+        self
+    }
+
+    pub fn lcid(mut self, lcid: impl Into<Lcid>) -> Self {
+        self.lcid = lcid.into();
+        self
+    }
+}
+
+/// The details of a [`SetupInstance`], read all at once.
+///
+/// Unlike the instance itself, this can be sent to other threads and kept after COM is uninitialized.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InstanceSnapshot {
+    pub instance_id: InstanceId,
+    pub display_name: String,
+    pub description: String,
+    /// E.g. `VisualStudio/17.9.5+34714.143`.
+    pub installation_name: String,
+    pub installation_path: std::path::PathBuf,
+    /// E.g. `17.9.34714.143`.
+    pub installation_version: Version,
+    /// A `FILETIME`, i.e. the number of 100 nanosecond intervals since 1601 (UTC).
+    pub install_date: u64,
+    pub state: InstanceState,
+    pub is_launchable: bool,
+    pub is_complete: bool,
+    /// `None` if the instance has no product reference, see [`SetupInstance::GetProduct`].
+    pub product_id: Option<ProductId>,
+    /// `None` if [`SnapshotOptions::packages`] was `false`.
+    pub packages: Option<Vec<PackageInfo>>,
+}
+
+#[cfg(feature = "std")]
+impl InstanceSnapshot {
+    pub fn from_instance(
+        instance: &SetupInstance,
+        options: SnapshotOptions,
+    ) -> Result<Self, HRESULT> {
+        let string = |bstr: Result<BSTR, HRESULT>| bstr.map(|b| strings::bstr_to_string_lossy(&b));
+        let packages = if options.packages {
+            let packages = instance.get_packages()?;
+            let packages = packages.iter().map(PackageInfo::from_package);
+            Some(packages.collect::<Result<_, _>>()?)
+        } else {
+            None
+        };
+        Ok(Self {
+            instance_id: instance.instance_id()?,
+            display_name: string(instance.get_display_name(options.lcid))?,
+            description: string(instance.get_description(options.lcid))?,
+            installation_name: string(instance.get_installation_name())?,
+            installation_path: instance.installation_path()?,
+            installation_version: instance.installation_version()?,
+            install_date: instance.get_install_date()?.as_u64(),
+            state: instance.get_state()?,
+            is_launchable: instance.is_launchable()?,
+            is_complete: instance.is_complete()?,
+            product_id: instance.product_id()?,
+            packages,
+        })
+    }
+}
+
+/// The details of a [`SetupInstanceCatalog`], i.e. of the release an instance was installed from.
+///
+/// Each field is `None` if the catalog doesn't have it.
+#[cfg(feature = "std")]
+#[derive(Debug, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CatalogInfo {
+    /// E.g. `VisualStudio/17.9.5+34714.143`.
+    pub id: Option<String>,
+    /// E.g. `d17.9`.
+    pub build_branch: Option<String>,
+    /// E.g. `17.9.34714.143`.
+    pub build_version: Option<String>,
+    /// E.g. `17.9.5`, or `17.10.0 Preview 2.0` for a prerelease.
+    pub product_display_version: Option<String>,
+    /// E.g. `17.9.5+34714.143`.
+    pub product_semantic_version: Option<String>,
+    /// The year in the product name, e.g. `2022`.
+    pub product_line_version: Option<String>,
+    /// E.g. `Dev17`.
+    pub product_line: Option<String>,
+    /// E.g. `RTW` or `Preview`.
+    pub product_milestone: Option<String>,
+    /// E.g. `Visual Studio`.
+    pub product_name: Option<String>,
+    /// E.g. `5`.
+    pub product_patch_version: Option<String>,
+    pub is_prerelease: bool,
+    /// Every other property, and any of the above that wasn't a string.
+    pub other: HashMap<String, crate::Variant>,
+}
+
+#[cfg(feature = "std")]
+impl CatalogInfo {
+    pub fn from_catalog(catalog: &SetupInstanceCatalog) -> Result<Self, HRESULT> {
+        let properties = match catalog.GetCatalogInfo()? {
+            Some(store) => store.to_map()?,
+            None => HashMap::new(),
+        };
+        Ok(Self::from_properties(properties, catalog.IsPrerelease()?))
+    }
+
+    pub(crate) fn from_properties(
+        mut properties: HashMap<String, crate::Variant>,
+        is_prerelease: bool,
+    ) -> Self {
+        // Take a string property out of `properties`, whatever the case of its name.
+        let mut string = |name: &str| {
+            let key = properties
+                .keys()
+                .find(|key| key.eq_ignore_ascii_case(name))?;
+            let key = key.clone();
+            match properties.remove(&key)? {
+                Variant::Bstr(bstr) => Some(strings::bstr_to_string_lossy(&bstr)),
+                other => {
+                    properties.insert(key, other);
+                    None
+                }
+            }
+        };
+        Self {
+            id: string("id"),
+            build_branch: string("buildBranch"),
+            build_version: string("buildVersion"),
+            product_display_version: string("productDisplayVersion"),
+            product_semantic_version: string("productSemanticVersion"),
+            product_line_version: string("productLineVersion"),
+            product_line: string("productLine"),
+            product_milestone: string("productMilestone"),
+            product_name: string("productName"),
+            product_patch_version: string("productPatchVersion"),
+            is_prerelease,
+            other: properties,
+        }
+    }
+}
+
+/// The channel an instance was installed from, e.g. the release or preview channel.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChannelInfo {
+    /// E.g. `VisualStudio.17.Release`.
+    pub id: String,
+    /// E.g. `https://aka.ms/vs/17/release/channel`.
+    pub uri: Option<String>,
+    /// The local copy of the channel manifest the instance was installed from, if setup kept one.
+    pub installed_manifest: Option<std::path::PathBuf>,
+}
+
+#[cfg(feature = "std")]
+impl ChannelInfo {
+    /// Reads the `channelId`, `channelUri` and `channelPath` properties from the instance's
+    /// own property store. Returns `Ok(None)` if there's no `channelId`.
+    pub fn from_instance(instance: &SetupInstance) -> Result<Option<Self>, HRESULT> {
+        let Some(id) = instance.channel_id()? else {
+            return Ok(None);
+        };
+        let store = || instance.to_property_store().map(Some);
+        Ok(Some(Self {
+            id,
+            uri: crate::property_from(store(), crate::wide_str!("channelUri"))?,
+            installed_manifest: crate::property_from(store(), crate::wide_str!("channelPath"))?,
+        }))
+    }
+
+    /// Whether this is a preview channel, i.e. the last part of the id ends with `Preview`.
+    ///
+    /// E.g. `VisualStudio.17.Preview` and the internal `VisualStudio.17.IntPreview` are, but
+    /// `VisualStudio.17.Release` and `VisualStudio.17.Release.LTSC.17.8` aren't.
+    pub fn is_preview(&self) -> bool {
+        let last = self.id.rsplit('.').next().unwrap_or_default();
+        let suffix = "preview";
+        last.len() >= suffix.len()
+            && last.as_bytes()[last.len() - suffix.len()..].eq_ignore_ascii_case(suffix.as_bytes())
+    }
+}
+
+/// The installer policies from a [`SetupPolicy`].
+///
+/// Each field is `None` if the policy isn't set. See [`SetupPolicy::KNOWN_NAMES`] for what they mean.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Policies {
+    pub cache_path: Option<String>,
+    pub keep_downloaded_payloads: Option<bool>,
+    pub shared_installation_path: Option<String>,
+    pub disable_rollback: Option<bool>,
+    pub background_download_disabled: Option<bool>,
+}
+
+/// Setup policies by name, as [`Policies::from_values`] reads them.
+pub(crate) trait PolicyValues {
+    /// The value of a policy, or `None` if it isn't set.
+    fn value(&self, name: &str) -> Result<Option<Variant>, HRESULT>;
+}
+
+impl PolicyValues for SetupPolicy {
+    fn value(&self, name: &str) -> Result<Option<Variant>, HRESULT> {
+        match self.get(name) {
+            Ok(Variant::Empty | Variant::Null) | Err(E_NOTFOUND) => Ok(None),
+            Ok(value) => Ok(Some(value)),
+            Err(error) => Err(error),
+        }
+    }
+}
+
+impl Policies {
+    pub fn from_policy(policy: &SetupPolicy) -> Result<Self, HRESULT> {
+        Self::from_values(policy)
+    }
+
+    pub(crate) fn from_values(values: &impl PolicyValues) -> Result<Self, HRESULT> {
+        // Registry values may be a string or a number, and setup may pass them on as either.
+        let string = |name| {
+            values.value(name).map(|value| match value? {
+                Variant::Bstr(bstr) if !bstr.is_empty() => {
+                    Some(strings::bstr_to_string_lossy(&bstr))
+                }
+                _ => None,
+            })
+        };
+        let flag = |name| {
+            values.value(name).map(|value| match value? {
+                Variant::Bool(flag) => Some(flag),
+                Variant::Signed(n) => Some(n != 0),
+                Variant::Unsigned(n) => Some(n != 0),
+                Variant::Bstr(bstr) => match strings::bstr_to_string_lossy(&bstr).trim() {
+                    "1" => Some(true),
+                    "0" => Some(false),
+                    s if s.eq_ignore_ascii_case("true") => Some(true),
+                    s if s.eq_ignore_ascii_case("false") => Some(false),
+                    _ => None,
+                },
+                _ => None,
+            })
+        };
+        Ok(Self {
+            cache_path: string("CachePath")?,
+            keep_downloaded_payloads: flag("KeepDownloadedPayloads")?,
+            shared_installation_path: string("SharedInstallationPath")?,
+            disable_rollback: flag("DisableRollback")?,
+            background_download_disabled: flag("BackgroundDownloadDisabled")?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake_store::FakeStore;
+
+    fn package(kind: &str, is_extension: bool) -> PackageInfo {
+        PackageInfo {
+            id: "Some.Package".into(),
+            version: "1.0".into(),
+            chip: String::new(),
+            language: String::new(),
+            branch: String::new(),
+            kind: kind.into(),
+            unique_id: "Some.Package,version=1.0".into(),
+            is_extension,
+        }
+    }
+
+    #[test]
+    fn extensions() {
+        let table = [
+            ("Vsix", false, true),
+            ("vsix", false, true),
+            ("Vsix", true, true),
+            ("Component", true, true),
+            ("Component", false, false),
+            ("Workload", false, false),
+            ("Msi", false, false),
+        ];
+        for (kind, is_extension, expected) in table {
+            assert_eq!(
+                package(kind, is_extension).is_extension_or_vsix(),
+                expected,
+                "{kind} {is_extension}"
+            );
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn preview_channels() {
+        let table = [
+            ("VisualStudio.17.Release", false),
+            ("VisualStudio.17.Preview", true),
+            ("VisualStudio.16.Release", false),
+            ("VisualStudio.16.Preview", true),
+            ("VisualStudio.17.IntPreview", true),
+            ("visualstudio.17.preview", true),
+            ("VisualStudio.17.Release.LTSC.17.8", false),
+            ("VisualStudio.Preview.Release", false),
+            ("Preview", true),
+            ("", false),
+        ];
+        for (id, expected) in table {
+            let channel = ChannelInfo {
+                id: id.into(),
+                uri: None,
+                installed_manifest: None,
+            };
+            assert_eq!(channel.is_preview(), expected, "{id}");
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn catalog_info() {
+        let bstr = |s: &str| Variant::Bstr(BSTR::from(s));
+        // Some fields are missing, and names may be in either case.
+        let properties = HashMap::from([
+            ("id".into(), bstr("VisualStudio/17.9.5+34714.143")),
+            ("buildBranch".into(), bstr("d17.9")),
+            ("BuildVersion".into(), bstr("17.9.34714.143")),
+            ("productDisplayVersion".into(), bstr("17.9.5")),
+            ("productLineVersion".into(), bstr("2022")),
+            ("productMilestone".into(), bstr("RTW")),
+            ("productPatchVersion".into(), Variant::Unsigned(5)),
+            ("requiredEngineVersion".into(), bstr("3.9.2164.13471")),
+        ]);
+        let info = CatalogInfo::from_properties(properties, false);
+        assert_eq!(info.id.as_deref(), Some("VisualStudio/17.9.5+34714.143"));
+        assert_eq!(info.build_branch.as_deref(), Some("d17.9"));
+        assert_eq!(info.build_version.as_deref(), Some("17.9.34714.143"));
+        assert_eq!(info.product_display_version.as_deref(), Some("17.9.5"));
+        assert_eq!(info.product_semantic_version, None);
+        assert_eq!(info.product_line_version.as_deref(), Some("2022"));
+        assert_eq!(info.product_line, None);
+        assert_eq!(info.product_milestone.as_deref(), Some("RTW"));
+        assert_eq!(info.product_name, None);
+        // Not a string, so it's kept as it was.
+        assert_eq!(info.product_patch_version, None);
+        assert!(!info.is_prerelease);
+        assert_eq!(info.other.len(), 2);
+        assert_eq!(info.other["productPatchVersion"], 5);
+        assert_eq!(info.other["requiredEngineVersion"], "3.9.2164.13471");
+
+        let info = CatalogInfo::from_properties(HashMap::new(), true);
+        assert_eq!(
+            info,
+            CatalogInfo {
+                is_prerelease: true,
+                ..CatalogInfo::default()
+            }
+        );
+    }
+
+    /// A policy value, without the `BSTR` that would stop it being a constant.
+    #[derive(Clone, Copy)]
+    enum FakeValue {
+        Str(&'static str),
+        Bool(bool),
+        Signed(i64),
+        Unsigned(u64),
+        Empty,
+    }
+
+    impl PolicyValues for FakeStore<'_, &str, FakeValue> {
+        fn value(&self, name: &str) -> Result<Option<Variant>, HRESULT> {
+            Ok(self.find(|&n| n == name)?.map(|&value| match value {
+                FakeValue::Str(s) => Variant::Bstr(BSTR::from(s)),
+                FakeValue::Bool(b) => Variant::Bool(b),
+                FakeValue::Signed(n) => Variant::Signed(n),
+                FakeValue::Unsigned(n) => Variant::Unsigned(n),
+                FakeValue::Empty => Variant::Empty,
+            }))
+        }
+    }
+
+    #[test]
+    fn policies() {
+        use FakeValue::*;
+
+        assert_eq!(
+            Policies::from_values(&FakeStore(&[])),
+            Ok(Policies::default())
+        );
+        let table: &[(FakeValue, Option<bool>)] = &[
+            (Unsigned(1), Some(true)),
+            (Unsigned(0), Some(false)),
+            (Signed(1), Some(true)),
+            (Bool(true), Some(true)),
+            (Bool(false), Some(false)),
+            (Str("1"), Some(true)),
+            (Str("0"), Some(false)),
+            (Str("True"), Some(true)),
+            (Str("yes"), None),
+            (Empty, None),
+        ];
+        for &(value, expected) in table {
+            let policy = FakeStore(&[("KeepDownloadedPayloads", Ok(value))]);
+            let policies = Policies::from_values(&policy).unwrap();
+            assert_eq!(policies.keep_downloaded_payloads, expected);
+        }
+
+        let policy = FakeStore(&[
+            ("CachePath", Ok(Str(r"D:\VSCache"))),
+            ("SharedInstallationPath", Ok(Str(""))),
+            ("DisableRollback", Ok(Unsigned(1))),
+            ("BackgroundDownloadDisabled", Ok(Unsigned(0))),
+        ]);
+        assert_eq!(
+            Policies::from_values(&policy),
+            Ok(Policies {
+                cache_path: Some(r"D:\VSCache".into()),
+                keep_downloaded_payloads: None,
+                shared_installation_path: None,
+                disable_rollback: Some(true),
+                background_download_disabled: Some(false),
+            })
+        );
+
+        let policy = FakeStore(&[("DisableRollback", Err(crate::E_UNEXPECTED))]);
+        assert_eq!(Policies::from_values(&policy), Err(crate::E_UNEXPECTED));
+    }
+}
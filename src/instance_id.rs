@@ -0,0 +1,78 @@
+//! Instance ids, such as `2e5ad5a6`.
+
+use alloc::string::String;
+use core::fmt;
+use core::str::FromStr;
+
+use crate::{E_INVALIDARG, HRESULT};
+
+/// The id of an instance, as returned by [`SetupInstance::instance_id`](crate::SetupInstance::instance_id).
+///
+/// Ids are compared ignoring ASCII case, so this can be used as a map key.
+/// They're written in lowercase, which is how the installer names the instance's
+/// directory under `%ProgramData%\Microsoft\VisualStudio\Packages\_Instances`.
+///
+/// Despite the name, ids aren't GUIDs. The installer uses eight hex digits,
+/// but this doesn't assume any particular format beyond printable ASCII.
+///
+/// ```
+/// use vssetup::InstanceId;
+///
+/// let id: InstanceId = "2E5AD5A6".parse().unwrap();
+/// assert_eq!(id, "2e5ad5a6".parse().unwrap());
+/// assert_eq!(format!("{id}"), "2e5ad5a6");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct InstanceId(String);
+
+impl InstanceId {
+    /// The id in lowercase, e.g. `2e5ad5a6`.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Returns `E_INVALIDARG` if the id is empty or has anything other than printable ASCII.
+impl FromStr for InstanceId {
+    type Err = HRESULT;
+
+    fn from_str(s: &str) -> Result<Self, HRESULT> {
+        if s.is_empty() || !s.bytes().all(|b| b.is_ascii_graphic()) {
+            return Err(E_INVALIDARG);
+        }
+        Ok(Self(s.to_ascii_lowercase()))
+    }
+}
+
+impl fmt::Display for InstanceId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let table = [
+            ("2e5ad5a6", Ok("2e5ad5a6")),
+            ("2E5AD5A6", Ok("2e5ad5a6")),
+            ("2e5AD5a6", Ok("2e5ad5a6")),
+            ("VisualStudio.17.Release", Ok("visualstudio.17.release")),
+            ("", Err(E_INVALIDARG)),
+            (" 2e5ad5a6", Err(E_INVALIDARG)),
+            ("2e5a d5a6", Err(E_INVALIDARG)),
+            ("2e5ad5a6\0", Err(E_INVALIDARG)),
+            ("2e5ad5é6", Err(E_INVALIDARG)),
+        ];
+        for (s, expected) in table {
+            assert_eq!(
+                s.parse::<InstanceId>().as_ref().map(InstanceId::as_str),
+                expected.as_ref().copied(),
+                "{s:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,234 @@
+//! Helpers for handing a Visual Studio instance over to other build tools.
+//!
+//! ## Example
+//!
+//! ```rust
+//! # fn main() -> Result<(), vssetup::HRESULT> {
+//! # vssetup::com::initialize()?;
+//! use vssetup::integrate::{Arch, cmake_generator, toolset_arg};
+//!
+//! let setup = vssetup::SetupConfiguration::new()?;
+//! for instance in setup.enum_instances()? {
+//!     let generator = cmake_generator(&instance)?;
+//!     let toolset = toolset_arg(None, Arch::X64);
+//!     // e.g. `-G "Visual Studio 17 2022" -A x64 -T host=x64`
+//!     let args = generator.args(Some(Arch::X64), Some(&toolset));
+//!     println!("cmake {}", args.join(" "));
+//! }
+//! # Ok(()) }
+//! ```
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::{E_UNEXPECTED, HRESULT, SetupInstance};
+
+/// The prefix shared by the MSVC toolset components, both the latest (`VC.Tools`)
+/// and the side-by-side ones (e.g. `VC.14.29.16.11`).
+pub(crate) const COMPONENT_PREFIX: &str = "Microsoft.VisualStudio.Component.VC.";
+
+/// Host architectures of the toolset components.
+/// Other components with a version (e.g. `ATL` or `MFC`) are not the toolset itself.
+pub(crate) const COMPONENT_HOSTS: [&str; 4] = [".x86.x64", ".ARM", ".ARM64", ".ARM64EC"];
+
+/// A CPU architecture, as used for the target platform or the host toolset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Arch {
+    X86,
+    X64,
+    Arm,
+    Arm64,
+}
+
+impl Arch {
+    /// The name used for the `host=` toolset option (e.g. `x64`).
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::X86 => "x86",
+            Self::X64 => "x64",
+            Self::Arm => "arm",
+            Self::Arm64 => "arm64",
+        }
+    }
+
+    /// The name CMake uses for the `-A` platform argument (e.g. `Win32`).
+    pub fn cmake_platform(self) -> &'static str {
+        match self {
+            Self::X86 => "Win32",
+            Self::X64 => "x64",
+            Self::Arm => "ARM",
+            Self::Arm64 => "ARM64",
+        }
+    }
+}
+
+/// The CMake "Visual Studio" generator matching an instance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CMakeGenerator {
+    Vs2017,
+    Vs2019,
+    Vs2022,
+    Vs2026,
+    /// A major version this crate doesn't know the generator name for.
+    Unknown(u16),
+}
+
+impl CMakeGenerator {
+    pub fn from_major_version(major: u16) -> Self {
+        match major {
+            15 => Self::Vs2017,
+            16 => Self::Vs2019,
+            17 => Self::Vs2022,
+            18 => Self::Vs2026,
+            _ => Self::Unknown(major),
+        }
+    }
+
+    pub fn major_version(self) -> u16 {
+        match self {
+            Self::Vs2017 => 15,
+            Self::Vs2019 => 16,
+            Self::Vs2022 => 17,
+            Self::Vs2026 => 18,
+            Self::Unknown(major) => major,
+        }
+    }
+
+    /// The generator name passed to `-G`, if known.
+    pub fn name(self) -> Option<&'static str> {
+        match self {
+            Self::Vs2017 => Some("Visual Studio 15 2017"),
+            Self::Vs2019 => Some("Visual Studio 16 2019"),
+            Self::Vs2022 => Some("Visual Studio 17 2022"),
+            Self::Vs2026 => Some("Visual Studio 18 2026"),
+            Self::Unknown(_) => None,
+        }
+    }
+
+    /// The arguments to pass to CMake when configuring a build tree.
+    ///
+    /// For an [`Unknown`](Self::Unknown) version no `-G` argument is emitted,
+    /// leaving CMake to pick its default generator.
+    pub fn args(self, platform: Option<Arch>, toolset: Option<&str>) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(name) = self.name() {
+            args.push(String::from("-G"));
+            args.push(String::from(name));
+        }
+        if let Some(platform) = platform {
+            args.push(String::from("-A"));
+            args.push(String::from(platform.cmake_platform()));
+        }
+        if let Some(toolset) = toolset {
+            args.push(String::from("-T"));
+            args.push(String::from(toolset));
+        }
+        args
+    }
+}
+
+/// Get the CMake generator for the instance's major version.
+pub fn cmake_generator(instance: &SetupInstance) -> Result<CMakeGenerator, HRESULT> {
+    let version = instance.get_installation_version()?;
+    let major = major_version(&version).ok_or(E_UNEXPECTED)?;
+    Ok(CMakeGenerator::from_major_version(major))
+}
+
+/// Builds the value for CMake's `-T` argument, e.g. `version=14.29,host=x64`.
+///
+/// `toolset_version` pins the MSVC toolset and `host` selects the host compiler.
+pub fn toolset_arg(toolset_version: Option<&str>, host: Arch) -> String {
+    match toolset_version {
+        Some(version) => format!("version={version},host={}", host.as_str()),
+        None => format!("host={}", host.as_str()),
+    }
+}
+
+/// Parse the leading number of a dotted version.
+pub(crate) fn major_version(version: &[u16]) -> Option<u16> {
+    let digits = version.split(|&c| c == u16::from(b'.')).next()?;
+    if digits.is_empty() {
+        return None;
+    }
+    digits.iter().try_fold(0u16, |major, &c| {
+        let digit = char::from_u32(c.into())?.to_digit(10)?;
+        major.checked_mul(10)?.checked_add(digit as u16)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    extern crate std;
+    use std::vec;
+
+    #[test]
+    fn generator_names() {
+        let table = [
+            (15, Some("Visual Studio 15 2017")),
+            (16, Some("Visual Studio 16 2019")),
+            (17, Some("Visual Studio 17 2022")),
+            (18, Some("Visual Studio 18 2026")),
+            (14, None),
+            (99, None),
+        ];
+        for (major, name) in table {
+            let generator = CMakeGenerator::from_major_version(major);
+            assert_eq!(generator.name(), name);
+            assert_eq!(generator.major_version(), major);
+        }
+    }
+
+    #[test]
+    fn toolset_args() {
+        let table = [
+            (None, Arch::X64, "host=x64"),
+            (None, Arch::X86, "host=x86"),
+            (Some("14.29"), Arch::X64, "version=14.29,host=x64"),
+            (
+                Some("14.38.33130"),
+                Arch::Arm64,
+                "version=14.38.33130,host=arm64",
+            ),
+        ];
+        for (version, host, expected) in table {
+            assert_eq!(toolset_arg(version, host), expected);
+        }
+    }
+
+    #[test]
+    fn args() {
+        let generator = CMakeGenerator::Vs2022;
+        assert_eq!(
+            generator.args(Some(Arch::X86), Some("host=x64")),
+            vec![
+                "-G",
+                "Visual Studio 17 2022",
+                "-A",
+                "Win32",
+                "-T",
+                "host=x64"
+            ]
+        );
+        assert_eq!(
+            generator.args(None, None),
+            vec!["-G", "Visual Studio 17 2022"]
+        );
+        assert_eq!(
+            CMakeGenerator::Unknown(19).args(Some(Arch::Arm64), None),
+            vec!["-A", "ARM64"]
+        );
+    }
+
+    #[test]
+    fn major_versions() {
+        let wide = |s: &str| s.encode_utf16().collect::<std::vec::Vec<u16>>();
+        assert_eq!(major_version(&wide("17.9.34723.18")), Some(17));
+        assert_eq!(major_version(&wide("15")), Some(15));
+        assert_eq!(major_version(&wide("")), None);
+        assert_eq!(major_version(&wide(".1")), None);
+        assert_eq!(major_version(&wide("x.1")), None);
+        assert_eq!(major_version(&wide("99999999.1")), None);
+    }
+}
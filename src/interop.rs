@@ -0,0 +1,108 @@
+//! Conversions to and from the `windows` crate's [`IUnknown`](windows_core::IUnknown).
+//!
+//! Converting a wrapper to `IUnknown` always succeeds. Converting back calls `QueryInterface`,
+//! so an object that doesn't implement the wrapper's interface returns `E_NOINTERFACE`.
+
+use crate::raw::{ISetupConfiguration, ISetupInstance, Interface};
+use crate::{HRESULT, IUnknown, SetupConfiguration, SetupInstance};
+
+/// Take over the reference held by a `windows` crate `IUnknown`.
+fn from_windows(unknown: windows_core::IUnknown) -> IUnknown {
+    // SAFETY: the pointer is a valid COM pointer and its reference is handed over.
+    unsafe { IUnknown::from_raw(windows_core::Interface::into_raw(unknown)) }
+}
+
+macro_rules! interop {
+    ($($wrapper:ident: $interface:ident),+) => {$(
+        impl From<$wrapper> for windows_core::IUnknown {
+            fn from(wrapper: $wrapper) -> Self {
+                // SAFETY: every COM interface is also an `IUnknown`.
+                unsafe { windows_core::Interface::from_raw(wrapper.into_raw()) }
+            }
+        }
+
+        impl TryFrom<windows_core::IUnknown> for $wrapper {
+            type Error = HRESULT;
+
+            fn try_from(unknown: windows_core::IUnknown) -> Result<Self, HRESULT> {
+                let raw = from_windows(unknown).cast::<$interface>()?;
+                Ok(Self { raw })
+            }
+        }
+    )+};
+}
+interop!(
+    SetupInstance: ISetupInstance,
+    SetupConfiguration: ISetupConfiguration
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{E_NOINTERFACE, GUID, IUnknown_Vtbl, S_OK};
+    use core::ffi::c_void;
+    use core::sync::atomic::{AtomicU32, Ordering::SeqCst};
+
+    /// A COM object that only implements `IUnknown` and `ISetupInstance`.
+    #[repr(C)]
+    struct FakeInstance {
+        vtable: &'static IUnknown_Vtbl,
+        refs: AtomicU32,
+    }
+
+    static FAKE_VTABLE: IUnknown_Vtbl = IUnknown_Vtbl {
+        QueryInterface: fake_query_interface,
+        AddRef: fake_add_ref,
+        Release: fake_release,
+    };
+
+    unsafe extern "system" fn fake_query_interface(
+        this: *mut c_void,
+        iid: *const GUID,
+        interface: *mut *mut c_void,
+    ) -> HRESULT {
+        let iid = unsafe { *iid };
+        if iid != IUnknown::IID && iid != ISetupInstance::IID {
+            return E_NOINTERFACE;
+        }
+        unsafe {
+            fake_add_ref(this);
+            interface.write(this);
+        }
+        S_OK
+    }
+
+    unsafe extern "system" fn fake_add_ref(this: *mut c_void) -> u32 {
+        unsafe { (*this.cast::<FakeInstance>()).refs.fetch_add(1, SeqCst) + 1 }
+    }
+
+    unsafe extern "system" fn fake_release(this: *mut c_void) -> u32 {
+        unsafe { (*this.cast::<FakeInstance>()).refs.fetch_sub(1, SeqCst) - 1 }
+    }
+
+    #[test]
+    fn round_trip() {
+        let fake = FakeInstance {
+            vtable: &FAKE_VTABLE,
+            refs: AtomicU32::new(1),
+        };
+        let ptr = core::ptr::from_ref(&fake).cast_mut().cast();
+        let instance = unsafe { SetupInstance::from_raw(ptr) };
+
+        let unknown = windows_core::IUnknown::from(instance);
+        assert_eq!(windows_core::Interface::as_raw(&unknown), ptr);
+        assert_eq!(fake.refs.load(SeqCst), 1);
+
+        let instance = SetupInstance::try_from(unknown).unwrap();
+        assert_eq!(instance.as_raw(), ptr);
+        assert_eq!(fake.refs.load(SeqCst), 1);
+
+        // It's not a configuration.
+        let unknown = windows_core::IUnknown::from(instance);
+        assert_eq!(
+            SetupConfiguration::try_from(unknown).err(),
+            Some(E_NOINTERFACE)
+        );
+        assert_eq!(fake.refs.load(SeqCst), 0);
+    }
+}
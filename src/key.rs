@@ -0,0 +1,142 @@
+//! Keys that identify an instance across reports, reinstalls and machines.
+//!
+//! An instance id alone changes when Visual Studio is reinstalled and an installation path
+//! alone is the same on many machines. [`unique_key`] combines the two, optionally scoped to
+//! a machine, into a string that is safe to store long-term and stable across versions of
+//! this crate.
+//!
+//! # Privacy
+//!
+//! Scoping keys to a machine uses its `MachineGuid` (see [`system::machine_guid`](crate::system::machine_guid)),
+//! which is semi-identifying. It is hashed rather than included directly, but the hash is still
+//! the same everywhere it's used, so this is opt-in. To share keys without them being joinable
+//! with other data, [`anonymize`] them with a secret salt.
+
+use alloc::string::String;
+use core::fmt::Write;
+
+use crate::sha256::{Sha256, hmac};
+
+/// Derive a key for an instance.
+///
+/// The key is `<instance id>-<path hash>`, prefixed with `<machine hash>-` if a machine guid is given.
+/// The hashes are 16 lowercase hex digits. The installation path is compared case-insensitively
+/// and ignoring the kind of slash or any trailing slashes.
+///
+/// ```
+/// let key = vssetup::key::unique_key(None, "2ba3c2a1", r"C:\Program Files\Microsoft Visual Studio\2022\Community");
+/// assert_eq!(key, vssetup::key::unique_key(None, "2ba3c2a1", "c:/program files/microsoft visual studio/2022/community/"));
+/// ```
+pub fn unique_key(
+    machine_guid: Option<&str>,
+    instance_id: &str,
+    installation_path: &str,
+) -> String {
+    let mut key = String::new();
+    if let Some(guid) = machine_guid {
+        let mut sha = Sha256::new();
+        sha.update(b"vssetup machine\0");
+        for c in guid.trim_matches(['{', '}']).chars() {
+            sha.update(c.to_ascii_lowercase().encode_utf8(&mut [0; 4]).as_bytes());
+        }
+        push_hex(&mut key, &sha.finish()[..8]);
+        key.push('-');
+    }
+    key.push_str(instance_id);
+    key.push('-');
+    let mut sha = Sha256::new();
+    sha.update(b"vssetup path\0");
+    for c in installation_path.trim_end_matches(['\\', '/']).chars() {
+        let c = if c == '/' { '\\' } else { c };
+        // Not `strings::to_uppercase`, which works on UTF-16 and keeps `ß` as it is.
+        // Changing how this folds would change every existing key.
+        for c in c.to_uppercase() {
+            sha.update(c.encode_utf8(&mut [0; 4]).as_bytes());
+        }
+    }
+    push_hex(&mut key, &sha.finish()[..8]);
+    key
+}
+
+/// Replace a key with its HMAC-SHA-256 under a caller-supplied salt, as 64 lowercase hex digits.
+///
+/// The same key and salt always give the same result, so anonymized keys can still be joined
+/// with each other. Without the salt they can't be linked back to the instance or machine.
+pub fn anonymize(key: &str, salt: &[u8]) -> String {
+    let mut anonymized = String::with_capacity(64);
+    push_hex(&mut anonymized, &hmac(salt, key.as_bytes()));
+    anonymized
+}
+
+fn push_hex(s: &mut String, bytes: &[u8]) {
+    for b in bytes {
+        let _ = write!(s, "{b:02x}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GUID: &str = "6f3b1a2e-8c4d-4e5f-9a0b-1c2d3e4f5a6b";
+    const ID: &str = "2ba3c2a1";
+    const PATH: &str = r"C:\Program Files\Microsoft Visual Studio\2022\Community";
+
+    #[test]
+    fn stable() {
+        // These must never change: keys are stored long-term.
+        assert_eq!(unique_key(None, ID, PATH), "2ba3c2a1-7d2946feae0d509b");
+        assert_eq!(
+            unique_key(Some(GUID), ID, PATH),
+            "93c0b8d5e0204fd9-2ba3c2a1-7d2946feae0d509b"
+        );
+        assert_eq!(
+            anonymize(&unique_key(None, ID, PATH), b"salt"),
+            "37e56212e564398b07dbfdf488018830839e5fe5700ce9d9fac8238584f65c8b"
+        );
+    }
+
+    #[test]
+    fn equivalent_inputs() {
+        let key = unique_key(Some(GUID), ID, PATH);
+        for path in [
+            r"c:\program files\microsoft visual studio\2022\community",
+            "C:/Program Files/Microsoft Visual Studio/2022/Community",
+            r"C:\Program Files\Microsoft Visual Studio\2022\Community\\",
+        ] {
+            assert_eq!(unique_key(Some(GUID), ID, path), key, "{path}");
+        }
+        let braced = alloc::format!("{{{}}}", GUID.to_ascii_uppercase());
+        assert_eq!(unique_key(Some(&braced), ID, PATH), key);
+    }
+
+    #[test]
+    fn distinct_inputs() {
+        let keys = [
+            unique_key(None, ID, PATH),
+            unique_key(Some(GUID), ID, PATH),
+            unique_key(Some("00000000-0000-0000-0000-000000000000"), ID, PATH),
+            unique_key(Some(GUID), "9e1f0c7d", PATH),
+            unique_key(Some(GUID), ID, r"C:\VS\2022"),
+            // The same values in different fields.
+            unique_key(Some(ID), GUID, PATH),
+            unique_key(Some(GUID), PATH, ID),
+        ];
+        for (i, a) in keys.iter().enumerate() {
+            for b in &keys[i + 1..] {
+                assert_ne!(a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn anonymized() {
+        let key = unique_key(Some(GUID), ID, PATH);
+        let first = anonymize(&key, b"first salt");
+        assert_eq!(first.len(), 64);
+        assert!(!first.contains(ID));
+        assert_eq!(anonymize(&key, b"first salt"), first);
+        assert_ne!(anonymize(&key, b"second salt"), first);
+        assert_ne!(anonymize(&unique_key(None, ID, PATH), b"first salt"), first);
+    }
+}
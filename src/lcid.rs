@@ -0,0 +1,187 @@
+//! Locale ids, used to choose the language of display names and other localized text.
+
+use windows_result::HRESULT;
+
+use crate::hresults::E_INVALIDARG;
+use crate::{LCID, strings, sys};
+
+/// A Windows locale id, e.g. [`Lcid::EN_US`].
+///
+/// Methods that take a locale accept anything that converts to an `Lcid`, including a plain
+/// integer, so `instance.GetDisplayName(0x409)` and `instance.GetDisplayName(Lcid::EN_US)` are the
+/// same.
+///
+/// ```
+/// use vssetup::Lcid;
+///
+/// assert_eq!(Lcid::EN_US.to_raw(), 0x409);
+/// assert_eq!(Lcid::DE_DE.language(), Lcid::from_raw(0x07));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Lcid(LCID);
+
+impl Lcid {
+    /// `LOCALE_USER_DEFAULT`: the user's default locale, which setup also uses for its UI.
+    pub const USER_DEFAULT: Self = Self(0x0400);
+    /// `LOCALE_SYSTEM_DEFAULT`: the machine's default locale.
+    pub const SYSTEM_DEFAULT: Self = Self(0x0800);
+    /// `LOCALE_INVARIANT`: no particular language.
+    pub const INVARIANT: Self = Self(0x007F);
+
+    // The languages Visual Studio is localized into.
+    pub const CS_CZ: Self = Self(0x0405);
+    pub const DE_DE: Self = Self(0x0407);
+    pub const EN_GB: Self = Self(0x0809);
+    pub const EN_US: Self = Self(0x0409);
+    pub const ES_ES: Self = Self(0x0C0A);
+    pub const FR_FR: Self = Self(0x040C);
+    pub const IT_IT: Self = Self(0x0410);
+    pub const JA_JP: Self = Self(0x0411);
+    pub const KO_KR: Self = Self(0x0412);
+    pub const PL_PL: Self = Self(0x0415);
+    pub const PT_BR: Self = Self(0x0416);
+    pub const RU_RU: Self = Self(0x0419);
+    pub const TR_TR: Self = Self(0x041F);
+    pub const ZH_CN: Self = Self(0x0804);
+    pub const ZH_TW: Self = Self(0x0404);
+
+    pub const fn from_raw(lcid: LCID) -> Self {
+        Self(lcid)
+    }
+
+    pub const fn to_raw(self) -> LCID {
+        self.0
+    }
+
+    /// The language without a region, e.g. `en` for `en-US`.
+    pub const fn language(self) -> Self {
+        // The primary language id is the low 10 bits.
+        Self(self.0 & 0x3FF)
+    }
+
+    /// The locale of a language tag such as `en-US`, or of a language alone such as `en`.
+    ///
+    /// Returns `E_INVALIDARG` if Windows doesn't know the tag, or for an empty tag.
+    /// Also returns `E_INVALIDARG` for locales Windows knows but which have no id of their own,
+    /// such as `en-DE`, as setup can't look anything up for them.
+    pub fn from_windows_language_tag(tag: &str) -> Result<Self, HRESULT> {
+        // Also accept languages without a region, e.g. `en`.
+        const LOCALE_ALLOW_NEUTRAL_NAMES: u32 = 0x0800_0000;
+        // What Windows gives for locales without an id of their own.
+        const LOCALE_CUSTOM_UNSPECIFIED: LCID = 0x1000;
+        if tag.is_empty() {
+            return Err(E_INVALIDARG);
+        }
+        let lcid = strings::with_wide(tag.encode_utf16(), |tag| unsafe {
+            Ok(sys::LocaleNameToLCID(
+                tag.as_ptr(),
+                LOCALE_ALLOW_NEUTRAL_NAMES,
+            ))
+        })?;
+        match lcid {
+            0 | LOCALE_CUSTOM_UNSPECIFIED => Err(E_INVALIDARG),
+            lcid => Ok(Self(lcid)),
+        }
+    }
+
+    /// The language of the user's Windows UI.
+    pub fn user_ui_language() -> Self {
+        // A LANGID is an LCID with the default sort order.
+        Self(LCID::from(unsafe { sys::GetUserDefaultUILanguage() }))
+    }
+}
+
+/// The language of the user's Windows UI, see [`Lcid::user_ui_language`].
+impl Default for Lcid {
+    fn default() -> Self {
+        Self::user_ui_language()
+    }
+}
+
+impl From<u32> for Lcid {
+    fn from(lcid: u32) -> Self {
+        Self(lcid)
+    }
+}
+
+/// So integer literals, which are `i32` unless inferred otherwise, can be used as an `Lcid`.
+impl From<i32> for Lcid {
+    fn from(lcid: i32) -> Self {
+        Self(lcid as u32)
+    }
+}
+
+impl From<Lcid> for u32 {
+    fn from(lcid: Lcid) -> Self {
+        lcid.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn constants() {
+        // These come from `winnls.h` and the language reference and must never change.
+        let table = [
+            (Lcid::USER_DEFAULT, 0x0400),
+            (Lcid::SYSTEM_DEFAULT, 0x0800),
+            (Lcid::INVARIANT, 0x007F),
+            (Lcid::CS_CZ, 1029),
+            (Lcid::DE_DE, 1031),
+            (Lcid::EN_GB, 2057),
+            (Lcid::EN_US, 1033),
+            (Lcid::ES_ES, 3082),
+            (Lcid::FR_FR, 1036),
+            (Lcid::IT_IT, 1040),
+            (Lcid::JA_JP, 1041),
+            (Lcid::KO_KR, 1042),
+            (Lcid::PL_PL, 1045),
+            (Lcid::PT_BR, 1046),
+            (Lcid::RU_RU, 1049),
+            (Lcid::TR_TR, 1055),
+            (Lcid::ZH_CN, 2052),
+            (Lcid::ZH_TW, 1028),
+        ];
+        for (lcid, raw) in table {
+            assert_eq!(lcid.to_raw(), raw, "{lcid:?}");
+            assert_eq!(Lcid::from(raw), lcid);
+            assert_eq!(u32::from(lcid), raw);
+        }
+        assert_eq!(Lcid::from(0x409), Lcid::EN_US);
+        assert_eq!(Lcid::EN_GB.language(), Lcid::EN_US.language());
+        assert_eq!(Lcid::EN_US.language().to_raw(), 0x09);
+    }
+
+    #[test]
+    fn language_tags() {
+        let table = [
+            ("en-US", Lcid::EN_US),
+            ("EN-us", Lcid::EN_US),
+            ("de-DE", Lcid::DE_DE),
+            ("ja-JP", Lcid::JA_JP),
+            ("en", Lcid::EN_US.language()),
+        ];
+        for (tag, lcid) in table {
+            assert_eq!(Lcid::from_windows_language_tag(tag), Ok(lcid), "{tag}");
+        }
+        // `en-DE` is known, but has no locale id.
+        for bad in ["", "xx-INVALID", "en-US\0", "en_US-", "en-DE"] {
+            assert_eq!(
+                Lcid::from_windows_language_tag(bad),
+                Err(E_INVALIDARG),
+                "{bad:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn user_ui_language() {
+        let lcid = Lcid::default();
+        assert_eq!(lcid, Lcid::user_ui_language());
+        // A specific language, unlike `USER_DEFAULT`.
+        assert_ne!(lcid.language().to_raw(), 0);
+        assert_ne!(lcid, Lcid::USER_DEFAULT);
+    }
+}
@@ -6,17 +6,43 @@
 //! To use this library you must first initialize COM.
 //! The helper, [`com::initialize`] will do this for you.
 //!
+//! ## `no_std`
+//!
+//! This crate only requires `core` and `alloc`.
+//! Conveniences that need the standard library are behind the `std` feature, which is enabled by default.
+//! Use `default-features = false` to opt out of them.
+//!
+//! ## `serde`
+//!
+//! The optional `serde` feature implements `Serialize` and `Deserialize` for the plain data types,
+//! such as `info::InstanceSnapshot`, [`Variant`], [`FILETIME`], [`InstanceState`] and [`ProductId`].
+//! `InstanceState` is written as its flags, e.g. `"Local | Registered"`, and `FILETIME` as a `u64`.
+//!
+//! ## `time` and `chrono`
+//!
+//! These optional features convert [`FILETIME`]s, such as install dates, to the date types of
+//! those crates, e.g. with `FILETIME::to_offset_date_time` or `FILETIME::to_chrono_date_time`.
+//!
+//! ## Naming
+//!
+//! Every method named after its COM counterpart (e.g. `GetDisplayName`) has a snake_case alias
+//! (e.g. `get_display_name`) which simply forwards to it.
+//! The snake_case names are the ones used in this documentation but both will continue to work.
+//!
+//! The [`idiomatic`] module goes further, with Rust names and types (`String`, `PathBuf` and
+//! [`Error`]) throughout. It needs the `std` feature.
+//!
 //! ## Example
 //!
 //! ```rust
-//! use vssetup::{com, HRESULT, SetupConfiguration};
+//! use vssetup::prelude::*;
 //!
 //! fn main() -> Result<(), HRESULT> {
-//!     com::initialize();
+//!     com::initialize()?;
 //!     let setup = SetupConfiguration::new()?;
-//!     let instances = setup.EnumAllInstances()?;
+//!     let instances = setup.enum_all_instances()?;
 //!     for instance in instances {
-//!         let name = instance.GetDisplayName(0x400)?.to_string();
+//!         let name = strings::bstr_to_string_lossy(&instance.get_display_name(Lcid::USER_DEFAULT)?);
 //!         println!("{name}");
 //!     }
 //!     Ok(())
@@ -31,19 +57,85 @@
 #![allow(clippy::upper_case_acronyms)]
 #![no_std]
 
+// `windows-strings` always requires `alloc` so there's no point in making it optional.
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
 mod defs;
 use defs::*;
 
+// Check the README's examples compile. They use `std`, e.g. through the `idiomatic` module.
+#[cfg(all(doctest, feature = "std"))]
+#[doc = include_str!("../README.md")]
+struct ReadmeDoctests;
+
+#[cfg(feature = "unstable-raw")]
+pub mod raw;
+#[cfg(not(feature = "unstable-raw"))]
 mod raw;
 use raw::*;
 
 pub mod com;
+pub mod error;
+#[cfg(test)]
+mod fake_store;
+pub mod for_plugins;
+#[cfg(fuzzing)]
+#[doc(hidden)]
+pub mod fuzzing;
+pub mod guids;
+pub mod hresults;
+#[cfg(feature = "std")]
+pub mod idiomatic;
+use hresults::*;
+pub mod info;
+mod instance_id;
+pub mod integrate;
+#[cfg(feature = "windows-interop")]
+mod interop;
+pub mod key;
+mod lcid;
+mod localized;
+#[cfg(feature = "std")]
+mod modified;
+#[cfg(feature = "std")]
+mod msbuild;
+#[cfg(feature = "std")]
+pub mod owner;
+pub mod prelude;
+mod probe;
+mod product;
+mod query;
+#[cfg(feature = "serde")]
+mod serialize;
+mod sha256;
+pub mod strings;
+mod sys;
+pub mod system;
+#[cfg(all(test, feature = "std"))]
+mod temp_dir;
+#[cfg(feature = "std")]
+mod toolset;
+mod version;
+pub mod vswhere;
 
+pub use defs::{FILETIME, Variant};
+pub use error::Error;
+pub use instance_id::InstanceId;
+pub use lcid::Lcid;
+pub use probe::{Requirements, installed, is_installer_available};
+pub use product::ProductId;
+pub use query::{InstanceQuery, QueryResult, Rejection, Requirement};
+pub use raw::InstanceState;
+pub use version::{PackedVersion, Version};
 pub use windows_result::HRESULT;
 pub use windows_strings::{BSTR, PCWSTR};
 
+use alloc::string::String;
+use alloc::vec::Vec;
 use core::marker::PhantomData;
-use core::ops::Deref;
+use core::ops::{Deref, RangeInclusive};
 use core::ptr::NonNull;
 use core::ptr::null_mut as null;
 use raw::Interface;
@@ -156,6 +248,9 @@ impl PartialEq<BSTR> for WideStr<'_> {
     }
 }
 
+// The wrappers are all `repr(transparent)` so that they can be used in place of COM pointers,
+// e.g. in the buffer passed to `EnumSetupInstances::Next`.
+
 /// The entry point for these APIs.
 ///
 /// # Example
@@ -166,6 +261,8 @@ impl PartialEq<BSTR> for WideStr<'_> {
 /// let setup = vssetup::SetupConfiguration::new()?;
 /// # Ok(()) }
 /// ```
+#[repr(transparent)]
+#[derive(Clone)]
 pub struct SetupConfiguration {
     raw: ISetupConfiguration,
 }
@@ -174,6 +271,22 @@ impl SetupConfiguration {
     /// Create a new instance of `SetupConfiguration`.
     ///
     /// This will fail if COM is not already initalized.
+    /// See [`hresults`] for telling that apart from the installer not being present:
+    ///
+    /// ```
+    /// use vssetup::{SetupConfiguration, hresults};
+    ///
+    /// match SetupConfiguration::new() {
+    ///     Ok(setup) => println!("found {} instances", setup.EnumInstances()?.count()),
+    ///     Err(e) if hresults::is_com_not_initialized(e) => println!("call com::initialize first"),
+    ///     Err(e) if hresults::is_installer_missing(e) => println!("Visual Studio is not installed"),
+    ///     Err(e) => return Err(e),
+    /// }
+    /// # Ok::<(), vssetup::HRESULT>(())
+    /// ```
+    ///
+    /// To use an `ISetupConfiguration` that's already been created some other way,
+    /// e.g. with a different `CLSCTX`, see [`from_raw`](Self::from_raw).
     pub fn new() -> Result<Self, HRESULT> {
         unsafe {
             let mut interface = null();
@@ -186,16 +299,24 @@ impl SetupConfiguration {
             )
             .ok_hresult()?;
             let interface = NonNull::new(interface).assert_ok()?;
-            Ok(Self::from_raw(interface))
+            Ok(Self::from_raw(interface.as_ptr()))
         }
     }
 
+    /// Like [`new`](Self::new) but initializes COM if it isn't already.
+    ///
+    /// COM is only initialized if `new` fails with [`CO_E_NOTINITIALIZED`],
+    /// and is then left initialized on this thread.
+    pub fn new_initializing() -> Result<Self, HRESULT> {
+        retry_initialized(Self::new, com::initialize)
+    }
+
     pub fn EnumInstances(&self) -> Result<EnumSetupInstances, HRESULT> {
         unsafe {
             let mut instances = None;
             self.com_ptr().EnumInstances(&mut instances).ok_hresult()?;
             let instances = instances.assert_ok()?;
-            Ok(EnumSetupInstances::from_raw(instances))
+            Ok(EnumSetupInstances::from_interface(instances))
         }
     }
 
@@ -205,7 +326,7 @@ impl SetupConfiguration {
             let setup = self.com_ptr().cast::<ISetupConfiguration2>()?;
             setup.EnumAllInstances(&mut instances).ok_hresult()?;
             let instances = instances.assert_ok()?;
-            Ok(EnumSetupInstances::from_raw(instances))
+            Ok(EnumSetupInstances::from_interface(instances))
         }
     }
 
@@ -216,7 +337,7 @@ impl SetupConfiguration {
                 .GetInstanceForCurrentProcess(&mut instance)
                 .ok_hresult()?;
             let instance = instance.assert_ok()?;
-            Ok(SetupInstance::from_raw(instance))
+            Ok(SetupInstance::from_interface(instance))
         }
     }
 
@@ -233,25 +354,158 @@ impl SetupConfiguration {
                 .GetInstanceForPath(path.as_ptr(), &mut instance)
                 .ok_hresult()?;
             let instance = instance.assert_ok()?;
-            Ok(SetupInstance::from_raw(instance))
+            Ok(SetupInstance::from_interface(instance))
+        }
+    }
+
+    /// The instance that `path` belongs to, e.g. from [`current_exe`](std::env::current_exe).
+    ///
+    /// Unlike [`get_instance_for_path`](Self::get_instance_for_path), this takes any path
+    /// and returns `Ok(None)` rather than [`E_NOTFOUND`] if the path isn't part of any instance.
+    /// The path is passed to setup as it is, so long paths and verbatim (`\\?\`) paths work too.
+    /// Returns `E_INVALIDARG` if the path contains a null.
+    #[cfg(feature = "std")]
+    pub fn instance_for_path(
+        &self,
+        path: impl AsRef<std::path::Path>,
+    ) -> Result<Option<SetupInstance>, HRESULT> {
+        let path = strings::os_str_to_wide(path.as_ref().as_os_str());
+        match strings::with_wide(path, |path| self.GetInstanceForPath(path)) {
+            Ok(instance) => Ok(Some(instance)),
+            Err(E_NOTFOUND) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The instance the current process belongs to, or `None` if it isn't part of one.
+    ///
+    /// Unlike [`get_instance_for_current_process`](Self::get_instance_for_current_process),
+    /// this returns `Ok(None)` rather than [`E_NOTFOUND`].
+    pub fn instance_for_current_process(&self) -> Result<Option<SetupInstance>, HRESULT> {
+        match self.GetInstanceForCurrentProcess() {
+            Ok(instance) => Ok(Some(instance)),
+            Err(E_NOTFOUND) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The newest instance that is complete and launchable, excluding prereleases.
+    ///
+    /// Versions are compared numerically, so `17.10` is newer than `17.9`.
+    /// If two instances have the same version, the first one enumerated is returned.
+    /// Instances that can't be queried are skipped.
+    pub fn latest(&self) -> Result<Option<SetupInstance>, HRESULT> {
+        self.latest_matching(false)
+    }
+
+    /// The same as [`latest`](Self::latest) but prereleases (e.g. Preview) are included.
+    pub fn latest_including_prerelease(&self) -> Result<Option<SetupInstance>, HRESULT> {
+        self.latest_matching(true)
+    }
+
+    fn latest_matching(&self, include_prerelease: bool) -> Result<Option<SetupInstance>, HRESULT> {
+        self.query()
+            .include_prerelease(include_prerelease)
+            .latest()
+            .find_first()
+    }
+
+    /// Find instances by product, installed components, version and so on.
+    pub fn query(&self) -> InstanceQuery<'_> {
+        InstanceQuery::new(self)
+    }
+
+    /// Get the installer policies, e.g. set by an administrator.
+    pub fn to_policy(&self) -> Result<SetupPolicy, HRESULT> {
+        unsafe {
+            self.com_ptr()
+                .cast()
+                .map(|raw| SetupPolicy::from_interface(raw))
+                .map_err(Into::into)
+        }
+    }
+
+    /// Get the helper for parsing version strings.
+    pub fn to_helper(&self) -> Result<SetupHelper, HRESULT> {
+        unsafe {
+            self.com_ptr()
+                .cast()
+                .map(|raw| SetupHelper::from_interface(raw))
+                .map_err(Into::into)
         }
     }
 
+    /// Wrap an `ISetupConfiguration` pointer, taking over one reference to it.
+    ///
     /// # Safety
     ///
-    /// The pointer must be a valid ISetupConfiguration COM pointer.
-    unsafe fn from_raw(raw: NonNull<core::ffi::c_void>) -> Self {
+    /// The pointer must be a non-null, valid `ISetupConfiguration` COM pointer.
+    /// The reference is released when the returned value is dropped,
+    /// so the caller must not release it too.
+    pub unsafe fn from_raw(raw: *mut core::ffi::c_void) -> Self {
+        debug_assert!(!raw.is_null());
         Self {
             // SAFETY: the caller must make sure this is safe.
-            raw: unsafe { ISetupConfiguration::from_raw(raw.as_ptr()) },
+            raw: unsafe { ISetupConfiguration::from_raw(raw) },
         }
     }
 
+    /// The `ISetupConfiguration` pointer, without adding a reference.
+    ///
+    /// It's only valid for as long as `self` is, unless the caller calls `AddRef`.
+    pub fn as_raw(&self) -> *mut core::ffi::c_void {
+        self.com_ptr().as_raw()
+    }
+
+    /// The `ISetupConfiguration` pointer, handing this reference over to the caller.
+    ///
+    /// The caller must release it, e.g. by passing it back to [`from_raw`](Self::from_raw).
+    pub fn into_raw(self) -> *mut core::ffi::c_void {
+        let raw = self.as_raw();
+        core::mem::forget(self);
+        raw
+    }
+
     fn com_ptr(&self) -> &ISetupConfiguration {
         &self.raw
     }
 }
 
+impl SetupConfiguration {
+    // snake_case aliases for the COM-style methods above.
+    #[doc(alias = "EnumInstances")]
+    #[inline]
+    pub fn enum_instances(&self) -> Result<EnumSetupInstances, HRESULT> {
+        self.EnumInstances()
+    }
+
+    #[doc(alias = "EnumAllInstances")]
+    #[inline]
+    pub fn enum_all_instances(&self) -> Result<EnumSetupInstances, HRESULT> {
+        self.EnumAllInstances()
+    }
+
+    #[doc(alias = "GetInstanceForCurrentProcess")]
+    #[inline]
+    pub fn get_instance_for_current_process(&self) -> Result<SetupInstance, HRESULT> {
+        self.GetInstanceForCurrentProcess()
+    }
+
+    #[doc(alias = "GetInstanceForPath")]
+    #[inline]
+    pub fn get_instance_for_path<'w, W: TryInto<WideStr<'w>>>(
+        &self,
+        path: W,
+    ) -> Result<SetupInstance, HRESULT> {
+        self.GetInstanceForPath(path)
+    }
+}
+
+/// Enumerates instances, either one at a time by iterating or in batches.
+///
+/// This isn't [`Clone`] as copies would share the same position.
+/// Use [`Clone`](Self::Clone) to get an independent enumerator.
+#[repr(transparent)]
 pub struct EnumSetupInstances {
     pub raw: IEnumSetupInstances,
 }
@@ -259,23 +513,43 @@ pub struct EnumSetupInstances {
 impl EnumSetupInstances {
     /// Fill the buffer with the next set of instances.
     ///
+    /// Returns `Ok(None)` once there are no more instances.
+    /// The last set may not fill the whole buffer.
+    ///
+    /// Prefer [`next_chunk`](Self::next_chunk), which returns the instances rather than
+    /// a slice borrowing the buffer.
+    ///
+    /// The returned slice borrows `instances`, so the buffer can't be changed while it's in use:
+    ///
+    /// ```compile_fail,E0506
+    /// # fn f(instances: &vssetup::EnumSetupInstances) -> Result<(), vssetup::HRESULT> {
+    /// let mut buffer: [Option<vssetup::SetupInstance>; 2] = Default::default();
+    /// if let Some(fetched) = instances.Next(&mut buffer)? {
+    ///     buffer[0] = None; // error: `buffer` is borrowed by `fetched`
+    ///     println!("{}", fetched.len());
+    /// }
+    /// # Ok(()) }
+    /// ```
+    ///
     /// # Errors
     ///
     /// Can fail with `E_OUTOFMEMORY` if a `SetupInstance` couldn't be allocated.
-    pub fn Next(
+    pub fn Next<'a>(
         &self,
-        instances: &mut [Option<SetupInstance>],
-    ) -> Result<Option<&[SetupInstance]>, HRESULT> {
+        instances: &'a mut [Option<SetupInstance>],
+    ) -> Result<Option<&'a [SetupInstance]>, HRESULT> {
+        // Release anything left in the buffer rather than overwriting it.
+        instances.iter_mut().for_each(|instance| *instance = None);
         unsafe {
             let len: u32 = instances.len().try_into().unwrap_or(u32::MAX);
             let mut fetched = 0;
             let hresult = self
                 .com_ptr()
                 .Next(len, instances.as_mut_ptr().cast(), &mut fetched);
-            if hresult == S_FALSE {
-                Ok(None)
-            } else if hresult.is_err() {
+            if hresult.is_err() {
                 Err(hresult)
+            } else if hresult == S_FALSE && fetched == 0 {
+                Ok(None)
             } else if fetched <= len {
                 Ok(Some(core::slice::from_raw_parts(
                     instances.as_ptr().cast(),
@@ -310,17 +584,147 @@ impl EnumSetupInstances {
             let mut new = None;
             self.com_ptr().Clone(&mut new).ok_hresult()?;
             let new = new.assert_ok()?;
-            Ok(EnumSetupInstances::from_raw(new))
+            Ok(EnumSetupInstances::from_interface(new))
         }
     }
 
+    /// Up to `n` of the next instances, or none if there are no more.
+    ///
+    /// Fewer than `n` instances can be returned before the end, so keep calling this
+    /// until it returns an empty `Vec`. If there's an error no instances are returned,
+    /// and any that were fetched are released.
+    pub fn next_chunk(&self, n: usize) -> Result<Vec<SetupInstance>, HRESULT> {
+        let mut buffer: Vec<Option<SetupInstance>> = Vec::new();
+        buffer.resize_with(n, || None);
+        let len = self.Next(&mut buffer)?.map_or(0, <[_]>::len);
+        buffer.truncate(len);
+        Ok(buffer.into_iter().flatten().collect())
+    }
+
+    /// All the remaining instances.
+    ///
+    /// This fetches several instances at a time, so it makes fewer calls to the setup
+    /// server than iterating does. If there's an error, any instances already fetched are released.
+    pub fn collect_all(&self) -> Result<Vec<SetupInstance>, HRESULT> {
+        const BATCH: usize = 8;
+        let mut instances = Vec::new();
+        let mut buffer: [Option<SetupInstance>; BATCH] = Default::default();
+        while let Some(fetched) = self.Next(&mut buffer)? {
+            let len = fetched.len();
+            if len == 0 {
+                break;
+            }
+            instances.extend(buffer[..len].iter_mut().filter_map(Option::take));
+        }
+        Ok(instances)
+    }
+
+    /// Iterate over the remaining instances, returning any error from [`Next`](Self::Next)
+    /// rather than treating it as the end of the instances.
+    ///
+    /// Iteration stops after an error. Setup's enumerator has already moved past the instance
+    /// that failed, so calling `try_iter` again carries on with the next one; the failed
+    /// instance isn't retried.
+    pub fn try_iter(&self) -> impl Iterator<Item = Result<SetupInstance, HRESULT>> + '_ {
+        let mut done = false;
+        core::iter::from_fn(move || {
+            if done {
+                return None;
+            }
+            let mut instance = None;
+            let hresult = unsafe { self.com_ptr().Next(1, &mut instance, null()) };
+            if hresult == S_FALSE {
+                done = true;
+                None
+            } else if hresult.is_err() {
+                done = true;
+                Some(Err(hresult))
+            } else {
+                let instance = instance
+                    .assert_ok()
+                    .map(|raw| unsafe { SetupInstance::from_interface(raw) });
+                done = instance.is_err();
+                Some(instance)
+            }
+        })
+    }
+
+    /// Only the release-channel instances, skipping prereleases such as Preview.
+    ///
+    /// Instances that can't say whether they're a prerelease are skipped too.
+    /// See [`SetupInstance::is_prerelease`], which treats older versions of setup as releases.
+    pub fn filter_released(self) -> impl Iterator<Item = SetupInstance> {
+        self.filter(|instance| instance.is_prerelease() == Ok(false))
+    }
+
     fn com_ptr(&self) -> &IEnumSetupInstances {
         &self.raw
     }
 
-    unsafe fn from_raw(raw: IEnumSetupInstances) -> EnumSetupInstances {
+    unsafe fn from_interface(raw: IEnumSetupInstances) -> EnumSetupInstances {
         EnumSetupInstances { raw }
     }
+
+    /// Wrap an `IEnumSetupInstances` pointer, taking over one reference to it.
+    ///
+    /// # Safety
+    ///
+    /// The pointer must be a non-null, valid `IEnumSetupInstances` COM pointer.
+    /// The reference is released when the returned value is dropped,
+    /// so the caller must not release it too.
+    pub unsafe fn from_raw(raw: *mut core::ffi::c_void) -> Self {
+        debug_assert!(!raw.is_null());
+        Self {
+            // SAFETY: the caller must make sure this is safe.
+            raw: unsafe { IEnumSetupInstances::from_raw(raw) },
+        }
+    }
+
+    /// The `IEnumSetupInstances` pointer, without adding a reference.
+    ///
+    /// It's only valid for as long as `self` is, unless the caller calls `AddRef`.
+    pub fn as_raw(&self) -> *mut core::ffi::c_void {
+        self.com_ptr().as_raw()
+    }
+
+    /// The `IEnumSetupInstances` pointer, handing this reference over to the caller.
+    ///
+    /// The caller must release it, e.g. by passing it back to [`from_raw`](Self::from_raw).
+    pub fn into_raw(self) -> *mut core::ffi::c_void {
+        let raw = self.as_raw();
+        core::mem::forget(self);
+        raw
+    }
+}
+
+impl EnumSetupInstances {
+    // snake_case aliases for the COM-style methods above.
+    #[doc(alias = "Next")]
+    #[inline]
+    pub fn next_into<'a>(
+        &self,
+        instances: &'a mut [Option<SetupInstance>],
+    ) -> Result<Option<&'a [SetupInstance]>, HRESULT> {
+        self.Next(instances)
+    }
+
+    #[doc(alias = "Skip")]
+    #[inline]
+    pub fn skip_instances(&self, count: u32) -> Result<bool, HRESULT> {
+        self.Skip(count)
+    }
+
+    #[doc(alias = "Reset")]
+    #[inline]
+    pub fn reset(&self) {
+        self.Reset()
+    }
+
+    #[doc(alias = "Clone")]
+    #[inline]
+    pub fn try_clone(&self) -> Result<EnumSetupInstances, HRESULT> {
+        self.Clone()
+    }
 }
 
 impl Iterator for EnumSetupInstances {
@@ -329,18 +733,21 @@ impl Iterator for EnumSetupInstances {
     /// Convinence method for calling [`Next`](Self::Next) in a loop.
     ///
     /// If `Next` returns an error, this will return `None` instead.
+    /// Use [`try_iter`](EnumSetupInstances::try_iter) to tell errors apart from the end of the instances.
     fn next(&mut self) -> Option<Self::Item> {
         let mut instance = None;
         unsafe {
             let hresult = self.com_ptr().Next(1, &mut instance, null());
             match hresult {
-                S_OK => instance.map(|raw| SetupInstance::from_raw(raw)),
+                S_OK => instance.map(|raw| SetupInstance::from_interface(raw)),
                 _ => None,
             }
         }
     }
 }
 
+#[repr(transparent)]
+#[derive(Clone)]
 pub struct SetupInstance {
     raw: ISetupInstance,
 }
@@ -396,21 +803,21 @@ impl SetupInstance {
         }
     }
 
-    pub fn GetDisplayName(&self, lcid: u32) -> Result<BSTR, HRESULT> {
+    pub fn GetDisplayName(&self, lcid: impl Into<Lcid>) -> Result<BSTR, HRESULT> {
         let mut name = BSTR::new();
         unsafe {
             self.raw
-                .GetDisplayName(lcid, &mut name)
+                .GetDisplayName(lcid.into().to_raw(), &mut name)
                 .ok_hresult()
                 .map(|_| name)
         }
     }
 
-    pub fn GetDescription(&self, lcid: LCID) -> Result<BSTR, HRESULT> {
+    pub fn GetDescription(&self, lcid: impl Into<Lcid>) -> Result<BSTR, HRESULT> {
         let mut description = BSTR::new();
         unsafe {
             self.raw
-                .GetDescription(lcid, &mut description)
+                .GetDescription(lcid.into().to_raw(), &mut description)
                 .ok_hresult()
                 .map(|_| description)
         }
@@ -473,12 +880,17 @@ impl SetupInstance {
         }
     }
 
+    /// The product this instance is an install of (e.g. `Microsoft.VisualStudio.Product.Community`).
+    ///
+    /// Returns `Ok(None)` when setup succeeds but has no product reference to give.
+    /// This happens for instances registered by very old installers, which are otherwise valid,
+    /// so don't skip an instance just because it has no product.
     pub fn GetProduct(&self) -> Result<Option<SetupProductReference>, HRESULT> {
         unsafe {
             let instance: ISetupInstance2 = self.com_ptr().cast()?;
             let mut product = None;
             instance.GetProduct(&mut product).ok_hresult()?;
-            Ok(product.map(|raw| SetupProductReference::from_raw(raw)))
+            Ok(product.map(|raw| SetupProductReference::from_interface(raw)))
         }
     }
 
@@ -509,7 +921,7 @@ impl SetupInstance {
             let instance: ISetupInstance2 = self.com_ptr().cast()?;
             let mut properties = None;
             instance.GetProperties(&mut properties).ok_hresult()?;
-            Ok(properties.map(|raw| SetupPropertyStore::from_raw(raw)))
+            Ok(properties.map(|raw| SetupPropertyStore::from_interface(raw)))
         }
     }
 
@@ -518,7 +930,7 @@ impl SetupInstance {
             let instance: ISetupInstance2 = self.com_ptr().cast()?;
             let mut errors = None;
             instance.GetErrors(&mut errors).ok_hresult()?;
-            Ok(errors.map(|raw| SetupErrorState::from_raw(raw)))
+            Ok(errors.map(|raw| SetupErrorState::from_interface(raw)))
         }
     }
 
@@ -526,7 +938,7 @@ impl SetupInstance {
         unsafe {
             self.com_ptr()
                 .cast()
-                .map(|raw| SetupInstanceCatalog::from_raw(raw))
+                .map(|raw| SetupInstanceCatalog::from_interface(raw))
                 .map_err(Into::into)
         }
     }
@@ -535,154 +947,654 @@ impl SetupInstance {
         unsafe {
             self.com_ptr()
                 .cast()
-                .map(|raw| SetupPropertyStore::from_raw(raw))
+                .map(|raw| SetupPropertyStore::from_interface(raw))
                 .map_err(Into::into)
         }
     }
 
-    fn com_ptr(&self) -> &ISetupInstance {
-        &self.raw
+    pub fn to_localized_properties(&self) -> Result<SetupLocalizedProperties, HRESULT> {
+        unsafe {
+            self.com_ptr()
+                .cast()
+                .map(|raw| SetupLocalizedProperties::from_interface(raw))
+                .map_err(Into::into)
+        }
     }
 
-    unsafe fn from_raw(raw: ISetupInstance) -> SetupInstance {
-        SetupInstance { raw }
+    /// The id of the channel this instance was installed from, e.g. `VisualStudio.17.Release`.
+    ///
+    /// This is in the instance's own property store, see [`to_property_store`](Self::to_property_store).
+    /// Returns `Ok(None)` if setup doesn't have it.
+    pub fn channel_id(&self) -> Result<Option<String>, HRESULT> {
+        property_from(self.to_property_store().map(Some), wide_str!("channelId"))
     }
-}
 
-pub struct SetupProductReference {
-    // This is not a typo. `GetProduct` returns a package reference for some reason.
-    raw: ISetupPackageReference,
-}
-
-impl SetupProductReference {
-    pub fn GetIsInstalled(&self) -> Result<bool, HRESULT> {
-        unsafe {
-            let product: ISetupProductReference = self.com_ptr().cast()?;
-            let mut is_installed = 0;
-            product
-                .GetIsInstalled(&mut is_installed)
-                .ok_hresult()
-                .map(|_| is_installed != 0)
-        }
+    /// Where the channel manifest was downloaded from,
+    /// e.g. `https://aka.ms/vs/17/release/channel`.
+    ///
+    /// This is in the instance's own property store, like [`channel_id`](Self::channel_id).
+    pub fn channel_uri(&self) -> Result<Option<String>, HRESULT> {
+        property_from(self.to_property_store().map(Some), wide_str!("channelUri"))
     }
 
-    pub fn GetSupportsExtensions(&self) -> Result<bool, HRESULT> {
-        unsafe {
-            let product: ISetupProductReference2 = self.com_ptr().cast()?;
-            let mut supports_extensions = 0;
-            product
-                .GetSupportsExtensions(&mut supports_extensions)
-                .ok_hresult()
-                .map(|_| supports_extensions != 0)
-        }
+    /// The channel this instance was installed from, or `None` if setup doesn't say.
+    ///
+    /// Use [`ChannelInfo::is_preview`](info::ChannelInfo::is_preview) to tell release and
+    /// preview installs apart.
+    #[cfg(feature = "std")]
+    pub fn channel(&self) -> Result<Option<info::ChannelInfo>, HRESULT> {
+        info::ChannelInfo::from_instance(self)
     }
 
-    fn com_ptr(&self) -> &ISetupPackageReference {
-        &self.raw
+    /// The name the user gave this instance to tell it apart from others, if any.
+    ///
+    /// This is one of the custom properties, see [`GetProperties`](Self::GetProperties).
+    /// Returns `Ok(None)` if it isn't set.
+    pub fn nickname(&self) -> Result<Option<String>, HRESULT> {
+        property_from(self.GetProperties(), wide_str!("nickname"))
     }
 
-    unsafe fn from_raw(raw: ISetupPackageReference) -> SetupProductReference {
-        SetupProductReference { raw }
+    /// The path of the installer's `setup.exe` that installed this instance.
+    ///
+    /// This is one of the custom properties, like [`nickname`](Self::nickname).
+    #[cfg(feature = "std")]
+    pub fn setup_engine_file_path(&self) -> Result<Option<std::path::PathBuf>, HRESULT> {
+        property_from(self.GetProperties(), wide_str!("setupEngineFilePath"))
     }
-}
 
-impl Deref for SetupProductReference {
-    type Target = SetupPackageReference;
-    fn deref(&self) -> &Self::Target {
-        unsafe { &*(self as *const Self as *const SetupPackageReference) }
+    /// Whether this is a prerelease, e.g. Preview.
+    ///
+    /// Returns `false` if setup doesn't say, which is the case for older versions.
+    pub fn is_prerelease(&self) -> Result<bool, HRESULT> {
+        match self.to_catalog() {
+            Ok(catalog) => catalog.IsPrerelease(),
+            Err(E_NOINTERFACE) => Ok(false),
+            Err(error) => Err(error),
+        }
     }
-}
-
-pub struct SetupErrorState {
-    raw: ISetupErrorState,
-}
 
-impl SetupErrorState {
-    pub fn GetFailedPackages(
-        &self,
-    ) -> Result<Option<SafeArray<SetupFailedPackageReference>>, HRESULT> {
-        unsafe {
-            let mut packages = core::ptr::null_mut();
-            self.com_ptr()
-                .GetFailedPackages(&mut packages)
-                .ok_hresult()?;
+    /// The installation version, e.g. `17.9.34723.18`.
+    ///
+    /// Returns `E_UNEXPECTED` if setup returns a version this crate can't parse.
+    pub fn installation_version(&self) -> Result<Version, HRESULT> {
+        strings::bstr_to_string_lossy(&self.GetInstallationVersion()?)
+            .parse()
+            .map_err(|_| E_UNEXPECTED)
+    }
 
-            if packages.is_null() {
-                Ok(None)
-            } else {
-                SafeArray::from_raw(packages.cast()).map(Some)
+    /// The product, e.g. [`ProductId::Community`].
+    ///
+    /// Returns `Ok(None)` if there is no product reference, see [`GetProduct`](Self::GetProduct).
+    /// The installation name (e.g. `VisualStudio/17.9.5+34714.143`) doesn't say which product
+    /// was installed so it's not used in its place; use
+    /// [`GetInstallationName`](Self::GetInstallationName) to show such instances to users.
+    pub fn product_id(&self) -> Result<Option<ProductId>, HRESULT> {
+        match self.GetProduct()? {
+            Some(product) => {
+                let id = strings::bstr_to_string_lossy(&product.GetId()?);
+                Ok(Some(ProductId::from(id.as_str())))
             }
+            None => Ok(None),
         }
     }
 
-    pub fn GetSkippedPackages(&self) -> Result<Option<SafeArray<SetupPackageReference>>, HRESULT> {
-        unsafe {
-            let mut packages = core::ptr::null_mut();
-            self.com_ptr()
-                .GetSkippedPackages(&mut packages)
-                .ok_hresult()?;
-            if packages.is_null() {
-                Ok(None)
-            } else {
-                SafeArray::from_raw(packages.cast()).map(Some)
-            }
+    /// Whether every one of the component (or other package) ids in `ids` is installed,
+    /// e.g. `Microsoft.VisualStudio.Component.VC.Tools.x86.x64`.
+    ///
+    /// Ids are compared ignoring ASCII case, like vswhere's `-requires`.
+    /// The packages are only listed once and stop being read as soon as everything has been found.
+    pub fn has_components<'a>(
+        &self,
+        ids: impl IntoIterator<Item = WideStr<'a>>,
+    ) -> Result<bool, HRESULT> {
+        let mut missing: Vec<&[u16]> = ids.into_iter().map(WideStr::to_slice).collect();
+        if missing.is_empty() {
+            return Ok(true);
         }
+        let packages = self.GetPackages()?;
+        includes_all(&mut missing, packages.iter().map(|package| package.GetId()))
     }
 
-    pub fn GetErrorLogFilePath(&self) -> Result<BSTR, HRESULT> {
-        unsafe {
-            let mut path = BSTR::new();
-            let state: ISetupErrorState2 = self.com_ptr().cast()?;
-            state
-                .GetErrorLogFilePath(&mut path)
-                .ok_hresult()
-                .map(|_| path)
+    /// The installed package for the first of `ids` that's installed, or `None` if none are.
+    ///
+    /// This is like vswhere's `-requiresAny`, with `ids` as alternatives in order of preference,
+    /// e.g. the ARM64 VC tools component then the x64 one. Ids are compared ignoring ASCII case.
+    /// The packages are only listed once.
+    pub fn first_matching_component(
+        &self,
+        ids: &[&str],
+    ) -> Result<Option<SetupPackageReference>, HRESULT> {
+        if ids.is_empty() {
+            return Ok(None);
         }
+        let preferred: Vec<Vec<u16>> = ids.iter().map(|id| id.encode_utf16().collect()).collect();
+        // Take ownership of the packages so the one returned outlives the array.
+        let mut packages = self.GetPackages()?.into_vec();
+        let found = preferred_match(&preferred, packages.iter().map(|package| package.GetId()))?;
+        Ok(found.map(|i| packages.swap_remove(i)))
     }
 
-    pub fn GetLogFilePath(&self) -> Result<BSTR, HRESULT> {
-        unsafe {
-            let mut path = BSTR::new();
-            let state: ISetupErrorState2 = self.com_ptr().cast()?;
-            state.GetLogFilePath(&mut path).ok_hresult().map(|_| path)
+    /// The installed extensions, i.e. packages marked as extensions or of type `Vsix`.
+    pub fn extensions(&self) -> Result<Vec<info::PackageInfo>, HRESULT> {
+        let mut extensions = Vec::new();
+        for package in self.GetPackages()?.iter() {
+            let package = info::PackageInfo::from_package(package)?;
+            if package.is_extension_or_vsix() {
+                extensions.push(package);
+            }
         }
+        Ok(extensions)
     }
 
-    pub fn GetRuntimeError(&self) -> Result<Option<SetupErrorInfo>, HRESULT> {
-        unsafe {
-            let mut info = None;
-            let state: ISetupErrorState3 = self.com_ptr().cast()?;
-            state.GetRuntimeError(&mut info).ok_hresult()?;
-            Ok(info.map(|raw| SetupErrorInfo::from_raw(raw)))
+    /// Whether this instance can provide the MSVC toolset selected by `version_prefix` (e.g. `14.29`).
+    ///
+    /// This is the version passed to `vcvarsall.bat` using `-vcvars_ver`.
+    /// Whole version components must match so `14.29` will match `14.29.30133` but `14.2` won't.
+    ///
+    /// Both the installed `VC\Tools\MSVC\<version>` directories and the side-by-side toolset
+    /// components (e.g. `Microsoft.VisualStudio.Component.VC.14.29.16.11.x86.x64`) are checked.
+    #[cfg(feature = "std")]
+    pub fn supports_toolset(&self, version_prefix: &str) -> Result<bool, HRESULT> {
+        let path = self.installation_path()?;
+        if toolset::dir_has_toolset(&path.join(r"VC\Tools\MSVC"), version_prefix) {
+            return Ok(true);
+        }
+        for package in self.GetPackages()?.iter() {
+            let id = strings::bstr_to_string_lossy(&package.GetId()?);
+            if toolset::is_toolset_component(&id, version_prefix) {
+                return Ok(true);
+            }
         }
+        Ok(false)
     }
 
-    fn com_ptr(&self) -> &ISetupErrorState {
-        &self.raw
+    /// The installation path, e.g. `C:\Program Files\Microsoft Visual Studio\2022\Community`.
+    ///
+    /// The path is converted without any loss, even if it isn't valid UTF-16.
+    #[cfg(feature = "std")]
+    pub fn installation_path(&self) -> Result<std::path::PathBuf, HRESULT> {
+        Ok(strings::bstr_to_pathbuf(&self.GetInstallationPath()?))
     }
 
-    unsafe fn from_raw(raw: ISetupErrorState) -> SetupErrorState {
-        SetupErrorState { raw }
+    /// Like [`resolve_path`](Self::resolve_path) but takes any path and returns a `PathBuf`,
+    /// e.g. resolving `VC\Tools\MSVC` to the full path.
+    ///
+    /// Returns `E_INVALIDARG` if the path contains a null.
+    #[cfg(feature = "std")]
+    pub fn resolve(
+        &self,
+        relative_path: impl AsRef<std::path::Path>,
+    ) -> Result<std::path::PathBuf, HRESULT> {
+        let relative_path = strings::os_str_to_wide(relative_path.as_ref().as_os_str());
+        let resolved = strings::with_wide(relative_path, |path| self.ResolvePath(path))?;
+        Ok(strings::bstr_to_pathbuf(&resolved))
     }
-}
 
-pub struct SetupErrorInfo {
-    raw: ISetupErrorInfo,
-}
+    /// The full path to the product's main executable, e.g. `...\Common7\IDE\devenv.exe`.
+    ///
+    /// Setup gives this relative to the [installation path](Self::installation_path),
+    /// so the two are joined.
+    #[cfg(feature = "std")]
+    pub fn product_path(&self) -> Result<std::path::PathBuf, HRESULT> {
+        let product = strings::bstr_to_pathbuf(&self.GetProductPath()?);
+        Ok(self.installation_path()?.join(product))
+    }
 
-impl SetupErrorInfo {
-    pub fn GetErrorHResult(&self) -> Result<HRESULT, HRESULT> {
-        unsafe {
-            let mut hresult = HRESULT::default();
-            self.com_ptr()
-                .GetErrorHResult(&mut hresult)
-                .ok_hresult()
-                .map(|_| hresult)
-        }
+    /// The path to the installer engine used to install this instance.
+    #[cfg(feature = "std")]
+    pub fn engine_path(&self) -> Result<std::path::PathBuf, HRESULT> {
+        Ok(strings::bstr_to_pathbuf(&self.GetEnginePath()?))
     }
 
-    pub fn GetErrorClassName(&self) -> Result<BSTR, HRESULT> {
-        unsafe {
+    /// When this instance was first installed.
+    ///
+    /// Returns `Ok(None)` if setup doesn't know, see [`FILETIME::to_system_time`].
+    #[cfg(feature = "std")]
+    pub fn install_date(&self) -> Result<Option<std::time::SystemTime>, HRESULT> {
+        Ok(self.GetInstallDate()?.to_system_time())
+    }
+
+    /// Like [`install_date`](Self::install_date), as a `time::OffsetDateTime` in UTC.
+    #[cfg(feature = "time")]
+    pub fn install_date_time(&self) -> Result<Option<time::OffsetDateTime>, HRESULT> {
+        Ok(self.GetInstallDate()?.to_offset_date_time())
+    }
+
+    /// Like [`install_date`](Self::install_date), as a `chrono::DateTime<Utc>`.
+    #[cfg(feature = "chrono")]
+    pub fn install_chrono_date_time(
+        &self,
+    ) -> Result<Option<chrono::DateTime<chrono::Utc>>, HRESULT> {
+        Ok(self.GetInstallDate()?.to_chrono_date_time())
+    }
+
+    /// When this instance was last changed (e.g. installed, modified or updated).
+    ///
+    /// Unlike [`install_date`](Self::install_date) this reflects later changes.
+    /// It's the newest modification time of the instance's `state.json` (under
+    /// `%ProgramData%\Microsoft\VisualStudio\Packages\_Instances`), the `.vsconfig` in the
+    /// installation root and the installation root itself. Any that can't be read are skipped.
+    ///
+    /// Returns `Ok(None)` if none of them can be read.
+    #[cfg(feature = "std")]
+    pub fn last_modified(&self) -> Result<Option<std::time::SystemTime>, HRESULT> {
+        let path = self.installation_path()?;
+        let id = strings::bstr_to_string_lossy(&self.GetInstanceId()?);
+        let program_data = std::env::var_os("ProgramData");
+        let sources = modified::sources(&path, program_data.as_deref(), &id);
+        Ok(modified::newest(&sources))
+    }
+
+    /// Read everything about this instance into an [`InstanceSnapshot`](info::InstanceSnapshot),
+    /// which doesn't need COM.
+    ///
+    /// Use [`snapshot_with`](Self::snapshot_with) to skip reading the packages.
+    #[cfg(feature = "std")]
+    pub fn snapshot(&self) -> Result<info::InstanceSnapshot, HRESULT> {
+        self.snapshot_with(info::SnapshotOptions::default())
+    }
+
+    #[cfg(feature = "std")]
+    pub fn snapshot_with(
+        &self,
+        options: info::SnapshotOptions,
+    ) -> Result<info::InstanceSnapshot, HRESULT> {
+        info::InstanceSnapshot::from_instance(self, options)
+    }
+
+    /// The instance id, which can be compared and used as a map key. See [`InstanceId`].
+    ///
+    /// Returns `E_UNEXPECTED` if setup returns an empty or unprintable id.
+    pub fn instance_id(&self) -> Result<InstanceId, HRESULT> {
+        strings::bstr_to_string_lossy(&self.GetInstanceId()?)
+            .parse()
+            .map_err(|_| E_UNEXPECTED)
+    }
+
+    /// A key for this instance that's suitable for long-term storage. See [`key::unique_key`].
+    ///
+    /// Pass [`system::machine_guid`] to tell apart instances with the same id and path
+    /// on different machines.
+    pub fn unique_key(&self, machine_guid: Option<&str>) -> Result<String, HRESULT> {
+        let id = strings::bstr_to_string_lossy(&self.GetInstanceId()?);
+        let path = strings::bstr_to_string_lossy(&self.GetInstallationPath()?);
+        Ok(key::unique_key(machine_guid, &id, &path))
+    }
+
+    /// The path to `MSBuild.exe`, if this instance has it installed.
+    #[cfg(feature = "std")]
+    pub fn msbuild_path(&self) -> Result<Option<std::path::PathBuf>, HRESULT> {
+        let path = self.installation_path()?;
+        Ok(msbuild::find_msbuild(&path))
+    }
+
+    /// Run this instance's MSBuild with the given arguments and wait for it to finish.
+    ///
+    /// Returns `E_NOTFOUND` if MSBuild is not installed.
+    /// Each argument reaches MSBuild as it is, even if it has spaces or quotes in it.
+    ///
+    /// If the instance has an MSVC toolset, the newest one's directories are put at the front
+    /// of `PATH`, `INCLUDE` and `LIB`. What's already in them is kept.
+    #[cfg(feature = "std")]
+    pub fn run_msbuild(
+        &self,
+        args: &[&std::ffi::OsStr],
+    ) -> Result<std::process::ExitStatus, HRESULT> {
+        use std::os::windows::process::CommandExt;
+
+        let msbuild = self.msbuild_path()?.ok_or(E_NOTFOUND)?;
+        let mut command = std::process::Command::new(msbuild);
+        command.raw_arg(msbuild::command_line(args));
+        let msvc = self.installation_path()?.join(r"VC\Tools\MSVC");
+        if let Some(toolset) = msbuild::newest_toolset(&msvc) {
+            command.envs(msbuild::toolset_env(&toolset, std::env::var_os));
+        }
+        command.status().map_err(|e| match e.raw_os_error() {
+            Some(code) => HRESULT::from_win32(code as u32),
+            None => E_UNEXPECTED,
+        })
+    }
+
+    fn com_ptr(&self) -> &ISetupInstance {
+        &self.raw
+    }
+
+    unsafe fn from_interface(raw: ISetupInstance) -> SetupInstance {
+        SetupInstance { raw }
+    }
+
+    /// Wrap an `ISetupInstance` pointer, taking over one reference to it.
+    ///
+    /// # Safety
+    ///
+    /// The pointer must be a non-null, valid `ISetupInstance` COM pointer.
+    /// The reference is released when the returned value is dropped,
+    /// so the caller must not release it too.
+    pub unsafe fn from_raw(raw: *mut core::ffi::c_void) -> Self {
+        debug_assert!(!raw.is_null());
+        Self {
+            // SAFETY: the caller must make sure this is safe.
+            raw: unsafe { ISetupInstance::from_raw(raw) },
+        }
+    }
+
+    /// The `ISetupInstance` pointer, without adding a reference.
+    ///
+    /// It's only valid for as long as `self` is, unless the caller calls `AddRef`.
+    pub fn as_raw(&self) -> *mut core::ffi::c_void {
+        self.com_ptr().as_raw()
+    }
+
+    /// The `ISetupInstance` pointer, handing this reference over to the caller.
+    ///
+    /// The caller must release it, e.g. by passing it back to [`from_raw`](Self::from_raw).
+    pub fn into_raw(self) -> *mut core::ffi::c_void {
+        let raw = self.as_raw();
+        core::mem::forget(self);
+        raw
+    }
+}
+
+impl SetupInstance {
+    // snake_case aliases for the COM-style methods above.
+    #[doc(alias = "GetInstanceId")]
+    #[inline]
+    pub fn get_instance_id(&self) -> Result<BSTR, HRESULT> {
+        self.GetInstanceId()
+    }
+
+    #[doc(alias = "GetInstallDate")]
+    #[inline]
+    pub fn get_install_date(&self) -> Result<FILETIME, HRESULT> {
+        self.GetInstallDate()
+    }
+
+    #[doc(alias = "GetInstallationName")]
+    #[inline]
+    pub fn get_installation_name(&self) -> Result<BSTR, HRESULT> {
+        self.GetInstallationName()
+    }
+
+    #[doc(alias = "GetInstallationPath")]
+    #[inline]
+    pub fn get_installation_path(&self) -> Result<BSTR, HRESULT> {
+        self.GetInstallationPath()
+    }
+
+    #[doc(alias = "GetInstallationVersion")]
+    #[inline]
+    pub fn get_installation_version(&self) -> Result<BSTR, HRESULT> {
+        self.GetInstallationVersion()
+    }
+
+    #[doc(alias = "GetDisplayName")]
+    #[inline]
+    pub fn get_display_name(&self, lcid: impl Into<Lcid>) -> Result<BSTR, HRESULT> {
+        self.GetDisplayName(lcid)
+    }
+
+    #[doc(alias = "GetDescription")]
+    #[inline]
+    pub fn get_description(&self, lcid: impl Into<Lcid>) -> Result<BSTR, HRESULT> {
+        self.GetDescription(lcid)
+    }
+
+    #[doc(alias = "ResolvePath")]
+    #[inline]
+    pub fn resolve_path<'w, W: TryInto<WideStr<'w>>>(
+        &self,
+        relative_path: W,
+    ) -> Result<BSTR, HRESULT> {
+        self.ResolvePath(relative_path)
+    }
+
+    #[doc(alias = "GetProductPath")]
+    #[inline]
+    pub fn get_product_path(&self) -> Result<BSTR, HRESULT> {
+        self.GetProductPath()
+    }
+
+    #[doc(alias = "GetEnginePath")]
+    #[inline]
+    pub fn get_engine_path(&self) -> Result<BSTR, HRESULT> {
+        self.GetEnginePath()
+    }
+
+    #[doc(alias = "IsLaunchable")]
+    #[inline]
+    pub fn is_launchable(&self) -> Result<bool, HRESULT> {
+        self.IsLaunchable()
+    }
+
+    #[doc(alias = "IsComplete")]
+    #[inline]
+    pub fn is_complete(&self) -> Result<bool, HRESULT> {
+        self.IsComplete()
+    }
+
+    #[doc(alias = "GetProduct")]
+    #[inline]
+    pub fn get_product(&self) -> Result<Option<SetupProductReference>, HRESULT> {
+        self.GetProduct()
+    }
+
+    #[doc(alias = "GetState")]
+    #[inline]
+    pub fn get_state(&self) -> Result<InstanceState, HRESULT> {
+        self.GetState()
+    }
+
+    #[doc(alias = "GetPackages")]
+    #[inline]
+    pub fn get_packages(&self) -> Result<SafeArray<SetupPackageReference>, HRESULT> {
+        self.GetPackages()
+    }
+
+    #[doc(alias = "GetProperties")]
+    #[inline]
+    pub fn get_properties(&self) -> Result<Option<SetupPropertyStore>, HRESULT> {
+        self.GetProperties()
+    }
+
+    #[doc(alias = "GetErrors")]
+    #[inline]
+    pub fn get_errors(&self) -> Result<Option<SetupErrorState>, HRESULT> {
+        self.GetErrors()
+    }
+}
+
+#[repr(transparent)]
+#[derive(Clone)]
+pub struct SetupProductReference {
+    // This is not a typo. `GetProduct` returns a package reference for some reason.
+    raw: ISetupPackageReference,
+}
+
+impl SetupProductReference {
+    pub fn GetIsInstalled(&self) -> Result<bool, HRESULT> {
+        unsafe {
+            let product: ISetupProductReference = self.com_ptr().cast()?;
+            let mut is_installed = 0;
+            product
+                .GetIsInstalled(&mut is_installed)
+                .ok_hresult()
+                .map(|_| is_installed != 0)
+        }
+    }
+
+    pub fn GetSupportsExtensions(&self) -> Result<bool, HRESULT> {
+        unsafe {
+            let product: ISetupProductReference2 = self.com_ptr().cast()?;
+            let mut supports_extensions = 0;
+            product
+                .GetSupportsExtensions(&mut supports_extensions)
+                .ok_hresult()
+                .map(|_| supports_extensions != 0)
+        }
+    }
+
+    fn com_ptr(&self) -> &ISetupPackageReference {
+        &self.raw
+    }
+
+    unsafe fn from_interface(raw: ISetupPackageReference) -> SetupProductReference {
+        SetupProductReference { raw }
+    }
+}
+
+impl SetupProductReference {
+    // snake_case aliases for the COM-style methods above.
+    #[doc(alias = "GetIsInstalled")]
+    #[inline]
+    pub fn get_is_installed(&self) -> Result<bool, HRESULT> {
+        self.GetIsInstalled()
+    }
+
+    #[doc(alias = "GetSupportsExtensions")]
+    #[inline]
+    pub fn get_supports_extensions(&self) -> Result<bool, HRESULT> {
+        self.GetSupportsExtensions()
+    }
+}
+
+impl Deref for SetupProductReference {
+    type Target = SetupPackageReference;
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*(self as *const Self as *const SetupPackageReference) }
+    }
+}
+
+#[repr(transparent)]
+#[derive(Clone)]
+pub struct SetupErrorState {
+    raw: ISetupErrorState,
+}
+
+impl SetupErrorState {
+    pub fn GetFailedPackages(
+        &self,
+    ) -> Result<Option<SafeArray<SetupFailedPackageReference>>, HRESULT> {
+        unsafe {
+            let mut packages = core::ptr::null_mut();
+            self.com_ptr()
+                .GetFailedPackages(&mut packages)
+                .ok_hresult()?;
+
+            if packages.is_null() {
+                Ok(None)
+            } else {
+                SafeArray::from_raw(packages.cast()).map(Some)
+            }
+        }
+    }
+
+    pub fn GetSkippedPackages(&self) -> Result<Option<SafeArray<SetupPackageReference>>, HRESULT> {
+        unsafe {
+            let mut packages = core::ptr::null_mut();
+            self.com_ptr()
+                .GetSkippedPackages(&mut packages)
+                .ok_hresult()?;
+            if packages.is_null() {
+                Ok(None)
+            } else {
+                SafeArray::from_raw(packages.cast()).map(Some)
+            }
+        }
+    }
+
+    pub fn GetErrorLogFilePath(&self) -> Result<BSTR, HRESULT> {
+        unsafe {
+            let mut path = BSTR::new();
+            let state: ISetupErrorState2 = self.com_ptr().cast()?;
+            state
+                .GetErrorLogFilePath(&mut path)
+                .ok_hresult()
+                .map(|_| path)
+        }
+    }
+
+    pub fn GetLogFilePath(&self) -> Result<BSTR, HRESULT> {
+        unsafe {
+            let mut path = BSTR::new();
+            let state: ISetupErrorState2 = self.com_ptr().cast()?;
+            state.GetLogFilePath(&mut path).ok_hresult().map(|_| path)
+        }
+    }
+
+    pub fn GetRuntimeError(&self) -> Result<Option<SetupErrorInfo>, HRESULT> {
+        unsafe {
+            let mut info = None;
+            let state: ISetupErrorState3 = self.com_ptr().cast()?;
+            state.GetRuntimeError(&mut info).ok_hresult()?;
+            Ok(info.map(|raw| SetupErrorInfo::from_interface(raw)))
+        }
+    }
+
+    fn com_ptr(&self) -> &ISetupErrorState {
+        &self.raw
+    }
+
+    unsafe fn from_interface(raw: ISetupErrorState) -> SetupErrorState {
+        SetupErrorState { raw }
+    }
+}
+
+impl SetupErrorState {
+    // snake_case aliases for the COM-style methods above.
+    #[doc(alias = "GetFailedPackages")]
+    #[inline]
+    pub fn get_failed_packages(
+        &self,
+    ) -> Result<Option<SafeArray<SetupFailedPackageReference>>, HRESULT> {
+        self.GetFailedPackages()
+    }
+
+    #[doc(alias = "GetSkippedPackages")]
+    #[inline]
+    pub fn get_skipped_packages(
+        &self,
+    ) -> Result<Option<SafeArray<SetupPackageReference>>, HRESULT> {
+        self.GetSkippedPackages()
+    }
+
+    #[doc(alias = "GetErrorLogFilePath")]
+    #[inline]
+    pub fn get_error_log_file_path(&self) -> Result<BSTR, HRESULT> {
+        self.GetErrorLogFilePath()
+    }
+
+    #[doc(alias = "GetLogFilePath")]
+    #[inline]
+    pub fn get_log_file_path(&self) -> Result<BSTR, HRESULT> {
+        self.GetLogFilePath()
+    }
+
+    #[doc(alias = "GetRuntimeError")]
+    #[inline]
+    pub fn get_runtime_error(&self) -> Result<Option<SetupErrorInfo>, HRESULT> {
+        self.GetRuntimeError()
+    }
+}
+
+#[repr(transparent)]
+#[derive(Clone)]
+pub struct SetupErrorInfo {
+    raw: ISetupErrorInfo,
+}
+
+impl SetupErrorInfo {
+    pub fn GetErrorHResult(&self) -> Result<HRESULT, HRESULT> {
+        unsafe {
+            let mut hresult = HRESULT::default();
+            self.com_ptr()
+                .GetErrorHResult(&mut hresult)
+                .ok_hresult()
+                .map(|_| hresult)
+        }
+    }
+
+    pub fn GetErrorClassName(&self) -> Result<BSTR, HRESULT> {
+        unsafe {
             let mut name = BSTR::new();
             self.com_ptr()
                 .GetErrorClassName(&mut name)
@@ -705,11 +1617,34 @@ impl SetupErrorInfo {
         &self.raw
     }
 
-    unsafe fn from_raw(raw: ISetupErrorInfo) -> SetupErrorInfo {
+    unsafe fn from_interface(raw: ISetupErrorInfo) -> SetupErrorInfo {
         SetupErrorInfo { raw }
     }
 }
 
+impl SetupErrorInfo {
+    // snake_case aliases for the COM-style methods above.
+    #[doc(alias = "GetErrorHResult")]
+    #[inline]
+    pub fn get_error_hresult(&self) -> Result<HRESULT, HRESULT> {
+        self.GetErrorHResult()
+    }
+
+    #[doc(alias = "GetErrorClassName")]
+    #[inline]
+    pub fn get_error_class_name(&self) -> Result<BSTR, HRESULT> {
+        self.GetErrorClassName()
+    }
+
+    #[doc(alias = "GetErrorMessage")]
+    #[inline]
+    pub fn get_error_message(&self) -> Result<BSTR, HRESULT> {
+        self.GetErrorMessage()
+    }
+}
+
+#[repr(transparent)]
+#[derive(Clone)]
 pub struct SetupFailedPackageReference {
     raw: ISetupFailedPackageReference,
 }
@@ -787,13 +1722,62 @@ impl SetupFailedPackageReference {
     }
 }
 
-impl Deref for SetupFailedPackageReference {
-    type Target = SetupPackageReference;
+impl SetupFailedPackageReference {
+    // snake_case aliases for the COM-style methods above.
+    #[doc(alias = "GetLogFilePath")]
+    #[inline]
+    pub fn get_log_file_path(&self) -> Result<BSTR, HRESULT> {
+        self.GetLogFilePath()
+    }
+
+    #[doc(alias = "GetDescription")]
+    #[inline]
+    pub fn get_description(&self) -> Result<BSTR, HRESULT> {
+        self.GetDescription()
+    }
+
+    #[doc(alias = "GetSignature")]
+    #[inline]
+    pub fn get_signature(&self) -> Result<BSTR, HRESULT> {
+        self.GetSignature()
+    }
+
+    #[doc(alias = "GetDetails")]
+    #[inline]
+    pub fn get_details(&self) -> Result<SafeArray<BSTR>, HRESULT> {
+        self.GetDetails()
+    }
+
+    #[doc(alias = "GetAffectedPackages")]
+    #[inline]
+    pub fn get_affected_packages(
+        &self,
+    ) -> Result<Option<SafeArray<SetupPackageReference>>, HRESULT> {
+        self.GetAffectedPackages()
+    }
+
+    #[doc(alias = "GetAction")]
+    #[inline]
+    pub fn get_action(&self) -> Result<BSTR, HRESULT> {
+        self.GetAction()
+    }
+
+    #[doc(alias = "GetReturnCode")]
+    #[inline]
+    pub fn get_return_code(&self) -> Result<BSTR, HRESULT> {
+        self.GetReturnCode()
+    }
+}
+
+impl Deref for SetupFailedPackageReference {
+    type Target = SetupPackageReference;
     fn deref(&self) -> &Self::Target {
         unsafe { &*(self as *const Self as *const SetupPackageReference) }
     }
 }
 
+#[repr(transparent)]
+#[derive(Clone)]
 pub struct SetupPropertyStore {
     raw: ISetupPropertyStore,
 }
@@ -825,15 +1809,259 @@ impl SetupPropertyStore {
         }
     }
 
+    /// Get a value by a name given as a `&str`.
+    ///
+    /// Returns `E_INVALIDARG` if the name contains a null.
+    pub fn get(&self, name: &str) -> Result<Variant, HRESULT> {
+        strings::with_wide(name.encode_utf16(), |name| self.GetValue(name))
+    }
+
+    /// A string value, or `None` if the property doesn't exist or is empty.
+    ///
+    /// Returns `DISP_E_TYPEMISMATCH` if the value isn't a string, rather than `None`,
+    /// so a property of an unexpected type isn't mistaken for a missing one.
+    ///
+    /// ```no_run
+    /// # fn f(properties: vssetup::SetupPropertyStore) -> Result<(), vssetup::HRESULT> {
+    /// if let Some(nickname) = properties.get_string(vssetup::wide_str!("nickname"))? {
+    ///     println!("{nickname}");
+    /// }
+    /// # Ok(()) }
+    /// ```
+    pub fn get_string<'w, W: TryInto<WideStr<'w>>>(
+        &self,
+        name: W,
+    ) -> Result<Option<String>, HRESULT> {
+        typed_value(self.GetValue(name))
+    }
+
+    /// A `bool` value, like [`get_string`](Self::get_string).
+    pub fn get_bool<'w, W: TryInto<WideStr<'w>>>(&self, name: W) -> Result<Option<bool>, HRESULT> {
+        typed_value(self.GetValue(name))
+    }
+
+    /// A number, like [`get_string`](Self::get_string).
+    ///
+    /// Signed numbers are accepted if they aren't negative, otherwise this returns `DISP_E_OVERFLOW`.
+    pub fn get_u64<'w, W: TryInto<WideStr<'w>>>(&self, name: W) -> Result<Option<u64>, HRESULT> {
+        typed_value(self.GetValue(name))
+    }
+
+    /// Every property, read into a map that can be kept after COM is uninitialized.
+    ///
+    /// A property whose value can't be read is included as [`Variant::Unknown`],
+    /// so the map still has every name setup returned.
+    #[cfg(feature = "std")]
+    pub fn to_map(&self) -> Result<std::collections::HashMap<String, Variant>, HRESULT> {
+        let names = self.GetNames()?;
+        Ok(property_map(&names, |name| self.GetValue(name)))
+    }
+
     fn com_ptr(&self) -> &ISetupPropertyStore {
         &self.raw
     }
 
-    unsafe fn from_raw(raw: ISetupPropertyStore) -> SetupPropertyStore {
+    unsafe fn from_interface(raw: ISetupPropertyStore) -> SetupPropertyStore {
         SetupPropertyStore { raw }
     }
 }
 
+impl SetupPropertyStore {
+    // snake_case aliases for the COM-style methods above.
+    #[doc(alias = "GetNames")]
+    #[inline]
+    pub fn get_names(&self) -> Result<SafeArray<BSTR>, HRESULT> {
+        self.GetNames()
+    }
+
+    #[doc(alias = "GetValue")]
+    #[inline]
+    pub fn get_value<'w, W: TryInto<WideStr<'w>>>(&self, name: W) -> Result<Variant, HRESULT> {
+        self.GetValue(name)
+    }
+}
+
+/// Converts a property value, treating a missing or empty value as `None`.
+fn typed_value<T: TryFrom<Variant, Error = HRESULT>>(
+    value: Result<Variant, HRESULT>,
+) -> Result<Option<T>, HRESULT> {
+    match value {
+        Ok(Variant::Empty | Variant::Null) | Err(E_NOTFOUND) => Ok(None),
+        Ok(value) => T::try_from(value).map(Some),
+        Err(error) => Err(error),
+    }
+}
+
+/// A property from `store`, or `None` if either doesn't exist.
+fn property_from<T: TryFrom<Variant, Error = HRESULT>>(
+    store: Result<Option<SetupPropertyStore>, HRESULT>,
+    name: WideStr<'_>,
+) -> Result<Option<T>, HRESULT> {
+    match store {
+        Ok(Some(store)) => typed_value(store.GetValue(name)),
+        // Older versions of setup don't have the property store interfaces.
+        Ok(None) | Err(E_NOINTERFACE) => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+#[cfg(feature = "std")]
+fn property_map<'a>(
+    names: impl IntoIterator<Item = &'a BSTR>,
+    mut value: impl FnMut(&BSTR) -> Result<Variant, HRESULT>,
+) -> std::collections::HashMap<String, Variant> {
+    let mut map = std::collections::HashMap::new();
+    for name in names {
+        let value = value(name).unwrap_or(Variant::Unknown);
+        let previous = map.insert(strings::bstr_to_string_lossy(name), value);
+        // Setup never returns the same name twice. If it did, the last one wins.
+        debug_assert!(previous.is_none(), "duplicate property {name}");
+    }
+    map
+}
+
+/// Get this from [`SetupInstance::to_localized_properties`].
+#[repr(transparent)]
+#[derive(Clone)]
+pub struct SetupLocalizedProperties {
+    raw: ISetupLocalizedProperties,
+}
+
+impl SetupLocalizedProperties {
+    pub fn GetLocalizedProperties(&self) -> Result<Option<SetupLocalizedPropertyStore>, HRESULT> {
+        unsafe {
+            let mut properties = None;
+            self.com_ptr()
+                .GetLocalizedProperties(&mut properties)
+                .ok_hresult()?;
+            Ok(properties.map(|raw| SetupLocalizedPropertyStore::from_interface(raw)))
+        }
+    }
+
+    pub fn GetLocalizedChannelProperties(
+        &self,
+    ) -> Result<Option<SetupLocalizedPropertyStore>, HRESULT> {
+        unsafe {
+            let mut properties = None;
+            self.com_ptr()
+                .GetLocalizedChannelProperties(&mut properties)
+                .ok_hresult()?;
+            Ok(properties.map(|raw| SetupLocalizedPropertyStore::from_interface(raw)))
+        }
+    }
+
+    fn com_ptr(&self) -> &ISetupLocalizedProperties {
+        &self.raw
+    }
+
+    unsafe fn from_interface(raw: ISetupLocalizedProperties) -> SetupLocalizedProperties {
+        SetupLocalizedProperties { raw }
+    }
+}
+
+impl SetupLocalizedProperties {
+    // snake_case aliases for the COM-style methods above.
+    #[doc(alias = "GetLocalizedProperties")]
+    #[inline]
+    pub fn get_localized_properties(&self) -> Result<Option<SetupLocalizedPropertyStore>, HRESULT> {
+        self.GetLocalizedProperties()
+    }
+
+    #[doc(alias = "GetLocalizedChannelProperties")]
+    #[inline]
+    pub fn get_localized_channel_properties(
+        &self,
+    ) -> Result<Option<SetupLocalizedPropertyStore>, HRESULT> {
+        self.GetLocalizedChannelProperties()
+    }
+}
+
+/// Like [`SetupPropertyStore`] but every value is looked up for a given locale.
+#[repr(transparent)]
+#[derive(Clone)]
+pub struct SetupLocalizedPropertyStore {
+    raw: ISetupLocalizedPropertyStore,
+}
+
+impl SetupLocalizedPropertyStore {
+    pub fn GetNames(&self, lcid: impl Into<Lcid>) -> Result<SafeArray<BSTR>, HRESULT> {
+        unsafe {
+            let mut names = core::ptr::null_mut();
+            self.com_ptr()
+                .GetNames(lcid.into().to_raw(), &mut names)
+                .ok_hresult()?;
+            if names.is_null() {
+                debug_assert!(!names.is_null());
+                Err(E_POINTER)
+            } else {
+                SafeArray::from_raw(names.cast())
+            }
+        }
+    }
+
+    pub fn GetValue<'w, W: TryInto<WideStr<'w>>>(
+        &self,
+        name: W,
+        lcid: impl Into<Lcid>,
+    ) -> Result<Variant, HRESULT> {
+        let Ok(name) = name.try_into() else {
+            return Err(E_INVALIDARG);
+        };
+        unsafe {
+            let mut value = core::mem::zeroed();
+            self.com_ptr()
+                .GetValue(name.as_ptr(), lcid.into().to_raw(), &mut value)
+                .ok_hresult()?;
+            Ok(value.into_variant())
+        }
+    }
+
+    /// Get a value by a name given as a `&str`.
+    ///
+    /// Returns `E_INVALIDARG` if the name contains a null.
+    pub fn get(&self, name: &str, lcid: impl Into<Lcid>) -> Result<Variant, HRESULT> {
+        let lcid = lcid.into();
+        strings::with_wide(name.encode_utf16(), |name| self.GetValue(name, lcid))
+    }
+
+    /// The string value of `name` in the language of `lcid`, or a related one.
+    ///
+    /// If there's no value for `lcid` then its language without a region is tried, then English.
+    /// Returns `Ok(None)` if there's no non-empty string for any of them.
+    pub fn lookup(&self, name: &str, lcid: impl Into<Lcid>) -> Result<Option<String>, HRESULT> {
+        localized::lookup(self, name, lcid.into().to_raw())
+    }
+
+    fn com_ptr(&self) -> &ISetupLocalizedPropertyStore {
+        &self.raw
+    }
+
+    unsafe fn from_interface(raw: ISetupLocalizedPropertyStore) -> SetupLocalizedPropertyStore {
+        SetupLocalizedPropertyStore { raw }
+    }
+}
+
+impl SetupLocalizedPropertyStore {
+    // snake_case aliases for the COM-style methods above.
+    #[doc(alias = "GetNames")]
+    #[inline]
+    pub fn get_names(&self, lcid: impl Into<Lcid>) -> Result<SafeArray<BSTR>, HRESULT> {
+        self.GetNames(lcid)
+    }
+
+    #[doc(alias = "GetValue")]
+    #[inline]
+    pub fn get_value<'w, W: TryInto<WideStr<'w>>>(
+        &self,
+        name: W,
+        lcid: impl Into<Lcid>,
+    ) -> Result<Variant, HRESULT> {
+        self.GetValue(name, lcid)
+    }
+}
+
+#[repr(transparent)]
+#[derive(Clone)]
 pub struct SetupPackageReference {
     raw: ISetupPackageReference,
 }
@@ -911,7 +2139,7 @@ impl SetupPackageReference {
         unsafe {
             self.com_ptr()
                 .cast()
-                .map(|raw| SetupPropertyStore::from_raw(raw))
+                .map(|raw| SetupPropertyStore::from_interface(raw))
                 .map_err(Into::into)
         }
     }
@@ -921,6 +2149,59 @@ impl SetupPackageReference {
     }
 }
 
+impl SetupPackageReference {
+    // snake_case aliases for the COM-style methods above.
+    #[doc(alias = "GetId")]
+    #[inline]
+    pub fn get_id(&self) -> Result<BSTR, HRESULT> {
+        self.GetId()
+    }
+
+    #[doc(alias = "GetVersion")]
+    #[inline]
+    pub fn get_version(&self) -> Result<BSTR, HRESULT> {
+        self.GetVersion()
+    }
+
+    #[doc(alias = "GetChip")]
+    #[inline]
+    pub fn get_chip(&self) -> Result<BSTR, HRESULT> {
+        self.GetChip()
+    }
+
+    #[doc(alias = "GetLanguage")]
+    #[inline]
+    pub fn get_language(&self) -> Result<BSTR, HRESULT> {
+        self.GetLanguage()
+    }
+
+    #[doc(alias = "GetBranch")]
+    #[inline]
+    pub fn get_branch(&self) -> Result<BSTR, HRESULT> {
+        self.GetBranch()
+    }
+
+    #[doc(alias = "GetType")]
+    #[inline]
+    pub fn get_type(&self) -> Result<BSTR, HRESULT> {
+        self.GetType()
+    }
+
+    #[doc(alias = "GetUniqueId")]
+    #[inline]
+    pub fn get_unique_id(&self) -> Result<BSTR, HRESULT> {
+        self.GetUniqueId()
+    }
+
+    #[doc(alias = "GetIsExtension")]
+    #[inline]
+    pub fn get_is_extension(&self) -> Result<bool, HRESULT> {
+        self.GetIsExtension()
+    }
+}
+
+#[repr(transparent)]
+#[derive(Clone)]
 pub struct SetupInstanceCatalog {
     raw: ISetupInstanceCatalog,
 }
@@ -929,7 +2210,7 @@ impl SetupInstanceCatalog {
         unsafe {
             let mut catalog = None;
             self.com_ptr().GetCatalogInfo(&mut catalog).ok_hresult()?;
-            Ok(catalog.map(|raw| SetupPropertyStore::from_raw(raw)))
+            Ok(catalog.map(|raw| SetupPropertyStore::from_interface(raw)))
         }
     }
 
@@ -943,19 +2224,219 @@ impl SetupInstanceCatalog {
         }
     }
 
+    /// Read the catalog into a [`CatalogInfo`](info::CatalogInfo), which doesn't need COM.
+    ///
+    /// ```no_run
+    /// # fn f(instance: vssetup::SetupInstance) -> Result<(), vssetup::HRESULT> {
+    /// let info = instance.to_catalog()?.info()?;
+    /// if let Some(version) = info.product_display_version {
+    ///     let channel = if info.is_prerelease { "preview" } else { "release" };
+    ///     println!("{version} ({channel})");
+    /// }
+    /// # Ok(()) }
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn info(&self) -> Result<info::CatalogInfo, HRESULT> {
+        info::CatalogInfo::from_catalog(self)
+    }
+
     fn com_ptr(&self) -> &ISetupInstanceCatalog {
         &self.raw
     }
 
-    unsafe fn from_raw(raw: ISetupInstanceCatalog) -> SetupInstanceCatalog {
+    unsafe fn from_interface(raw: ISetupInstanceCatalog) -> SetupInstanceCatalog {
         SetupInstanceCatalog { raw }
     }
 }
 
+impl SetupInstanceCatalog {
+    // snake_case aliases for the COM-style methods above.
+    #[doc(alias = "GetCatalogInfo")]
+    #[inline]
+    pub fn get_catalog_info(&self) -> Result<Option<SetupPropertyStore>, HRESULT> {
+        self.GetCatalogInfo()
+    }
+
+    #[doc(alias = "IsPrerelease")]
+    #[inline]
+    pub fn is_prerelease(&self) -> Result<bool, HRESULT> {
+        self.IsPrerelease()
+    }
+}
+
+/// Parses version strings the same way setup does.
+///
+/// Get this from [`SetupConfiguration::to_helper`].
+#[repr(transparent)]
+#[derive(Clone)]
+pub struct SetupHelper {
+    raw: ISetupHelper,
+}
+
+impl SetupHelper {
+    /// Parse a dotted version such as `17.9.34723.18`.
+    pub fn ParseVersion<'w, W: TryInto<WideStr<'w>>>(
+        &self,
+        version: W,
+    ) -> Result<PackedVersion, HRESULT> {
+        let Ok(version) = version.try_into() else {
+            return Err(E_INVALIDARG);
+        };
+        unsafe {
+            let mut packed = 0;
+            self.com_ptr()
+                .ParseVersion(version.as_ptr(), &mut packed)
+                .ok_hresult()?;
+            Ok(PackedVersion::from_bits(packed))
+        }
+    }
+
+    /// Parse a version range such as `[16.0,17.0)`.
+    ///
+    /// Exclusive bounds are converted to the nearest inclusive version by setup,
+    /// so the result is always inclusive.
+    pub fn ParseVersionRange<'w, W: TryInto<WideStr<'w>>>(
+        &self,
+        range: W,
+    ) -> Result<RangeInclusive<PackedVersion>, HRESULT> {
+        let Ok(range) = range.try_into() else {
+            return Err(E_INVALIDARG);
+        };
+        unsafe {
+            let (mut min, mut max) = (0, 0);
+            self.com_ptr()
+                .ParseVersionRange(range.as_ptr(), &mut min, &mut max)
+                .ok_hresult()?;
+            Ok(PackedVersion::from_bits(min)..=PackedVersion::from_bits(max))
+        }
+    }
+
+    fn com_ptr(&self) -> &ISetupHelper {
+        &self.raw
+    }
+
+    unsafe fn from_interface(raw: ISetupHelper) -> SetupHelper {
+        SetupHelper { raw }
+    }
+}
+
+impl SetupHelper {
+    // snake_case aliases for the COM-style methods above.
+    #[doc(alias = "ParseVersion")]
+    #[inline]
+    pub fn parse_version<'w, W: TryInto<WideStr<'w>>>(
+        &self,
+        version: W,
+    ) -> Result<PackedVersion, HRESULT> {
+        self.ParseVersion(version)
+    }
+
+    #[doc(alias = "ParseVersionRange")]
+    #[inline]
+    pub fn parse_version_range<'w, W: TryInto<WideStr<'w>>>(
+        &self,
+        range: W,
+    ) -> Result<RangeInclusive<PackedVersion>, HRESULT> {
+        self.ParseVersionRange(range)
+    }
+}
+
+/// The installer policies in effect on this machine.
+///
+/// Administrators set these under `HKLM\SOFTWARE\Policies\Microsoft\VisualStudio\Setup`
+/// (or the same key without `Policies`). Get this from [`SetupConfiguration::to_policy`].
+#[repr(transparent)]
+#[derive(Clone)]
+pub struct SetupPolicy {
+    raw: ISetupPolicy,
+}
+
+impl SetupPolicy {
+    /// Policy names documented for the installer, to pass to [`GetValue`](Self::GetValue).
+    /// Older versions of setup may not return all of them.
+    ///
+    /// - `CachePath`: where package manifests and payloads are cached.
+    /// - `KeepDownloadedPayloads`: whether payloads are kept in the cache after installing.
+    /// - `SharedInstallationPath`: where packages shared between instances are installed.
+    ///   Use [`GetSharedInstallationPath`](Self::GetSharedInstallationPath) for the effective path.
+    /// - `DisableRollback`: whether a failed install is left as-is rather than rolled back.
+    /// - `BackgroundDownloadDisabled`: whether updates are not downloaded in the background.
+    pub const KNOWN_NAMES: &[&str] = &[
+        "CachePath",
+        "KeepDownloadedPayloads",
+        "SharedInstallationPath",
+        "DisableRollback",
+        "BackgroundDownloadDisabled",
+    ];
+
+    /// Where packages shared between instances are installed, taking any policy into account.
+    pub fn GetSharedInstallationPath(&self) -> Result<BSTR, HRESULT> {
+        unsafe {
+            let mut path = BSTR::new();
+            self.com_ptr()
+                .GetSharedInstallationPath(&mut path)
+                .ok_hresult()
+                .map(|_| path)
+        }
+    }
+
+    /// Get a policy by name. See [`KNOWN_NAMES`](Self::KNOWN_NAMES).
+    pub fn GetValue<'w, W: TryInto<WideStr<'w>>>(&self, name: W) -> Result<Variant, HRESULT> {
+        let Ok(name) = name.try_into() else {
+            return Err(E_INVALIDARG);
+        };
+        unsafe {
+            let mut value = core::mem::zeroed();
+            self.com_ptr()
+                .GetValue(name.as_ptr(), &mut value)
+                .ok_hresult()?;
+            Ok(value.into_variant())
+        }
+    }
+
+    /// Get a policy by a name given as a `&str`.
+    ///
+    /// Returns `E_INVALIDARG` if the name contains a null.
+    pub fn get(&self, name: &str) -> Result<Variant, HRESULT> {
+        strings::with_wide(name.encode_utf16(), |name| self.GetValue(name))
+    }
+
+    /// Read all the [known](Self::KNOWN_NAMES) policies.
+    pub fn known_policies(&self) -> Result<info::Policies, HRESULT> {
+        info::Policies::from_policy(self)
+    }
+
+    fn com_ptr(&self) -> &ISetupPolicy {
+        &self.raw
+    }
+
+    unsafe fn from_interface(raw: ISetupPolicy) -> SetupPolicy {
+        SetupPolicy { raw }
+    }
+}
+
+impl SetupPolicy {
+    // snake_case aliases for the COM-style methods above.
+    #[doc(alias = "GetSharedInstallationPath")]
+    #[inline]
+    pub fn get_shared_installation_path(&self) -> Result<BSTR, HRESULT> {
+        self.GetSharedInstallationPath()
+    }
+
+    #[doc(alias = "GetValue")]
+    #[inline]
+    pub fn get_value<'w, W: TryInto<WideStr<'w>>>(&self, name: W) -> Result<Variant, HRESULT> {
+        self.GetValue(name)
+    }
+}
+
 /// An owned slice.
 ///
 /// This is roughly equivalent to a `Box<T>`.
 /// It will deref to a slice of `T` and be freed on drop.
+///
+/// Dropping the array drops every element exactly once, whether `SafeArrayDestroy` does it
+/// (e.g. releasing COM pointers when the array has `FADF_UNKNOWN` set) or this type has to.
 pub struct SafeArray<T> {
     raw: *mut SAFEARRAY,
     _item: PhantomData<*mut T>,
@@ -967,8 +2448,33 @@ impl<T> SafeArray<T> {
     }
 
     pub fn as_slice(&self) -> &[T] {
+        let (data, len) = self.data();
+        if len == 0 {
+            return &[];
+        }
+        unsafe { core::slice::from_raw_parts(data, len) }
+    }
+
+    /// Move the elements into a `Vec` and free the array.
+    pub fn into_vec(self) -> Vec<T> {
+        let this = core::mem::ManuallyDrop::new(self);
+        let (data, len) = this.data();
+        let mut vec = Vec::with_capacity(len);
+        if len != 0 {
+            unsafe {
+                core::ptr::copy_nonoverlapping(data, vec.as_mut_ptr(), len);
+                vec.set_len(len);
+                // The elements now belong to `vec`. Null pointers are skipped by `SafeArrayDestroy`.
+                core::ptr::write_bytes(data, 0, len);
+            }
+        }
+        unsafe { Self::destroy(this.raw) };
+        vec
+    }
+
+    fn data(&self) -> (*mut T, usize) {
         unsafe {
-            core::slice::from_raw_parts(
+            (
                 (*self.raw).pvData.cast::<T>(),
                 (*self.raw).rgsabound[0].cElements as usize,
             )
@@ -981,6 +2487,7 @@ impl<T> SafeArray<T> {
             if (*raw).cDims != 1 {
                 debug_assert_eq!((*raw).cDims, 1);
                 // This cannot happen but when it does return an error in release.
+                let _ = SafeArrayUnlock(raw);
                 Err(E_UNEXPECTED)
             } else {
                 Ok(Self {
@@ -990,6 +2497,13 @@ impl<T> SafeArray<T> {
             }
         }
     }
+
+    unsafe fn destroy(raw: *mut SAFEARRAY) {
+        unsafe {
+            let _ = SafeArrayUnlock(raw);
+            let _ = SafeArrayDestroy(raw);
+        }
+    }
 }
 
 impl<'a, T> IntoIterator for &'a SafeArray<T> {
@@ -1010,25 +2524,122 @@ impl<T> core::ops::Deref for SafeArray<T> {
 
 impl<T> Drop for SafeArray<T> {
     fn drop(&mut self) {
+        // `SafeArrayDestroy` only frees the elements if the features say what they are.
+        // Otherwise drop them here, which releases COM pointers and frees `BSTR`s.
+        let owned = FADF_RECORD | FADF_BSTR | FADF_UNKNOWN | FADF_DISPATCH | FADF_VARIANT;
         unsafe {
-            let _ = SafeArrayUnlock(self.raw);
-            let _ = SafeArrayDestroy(self.raw);
+            if (*self.raw).fFeatures & owned == 0 {
+                let (data, len) = self.data();
+                if len != 0 {
+                    core::ptr::drop_in_place(core::ptr::slice_from_raw_parts_mut(data, len));
+                }
+            }
+            Self::destroy(self.raw);
         }
     }
 }
 
-trait AssertOk {
-    type T;
-    fn assert_ok(self) -> Result<Self::T, HRESULT>;
+/// Writes the wrapper's name and the address of its COM object, e.g. `SetupPolicy(0x1f2b3c4d5e0)`.
+macro_rules! debug_com_ptr {
+    ($($wrapper:ident),+) => {$(
+        impl core::fmt::Debug for $wrapper {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                f.debug_tuple(stringify!($wrapper))
+                    .field(&self.as_raw())
+                    .finish()
+            }
+        }
+    )+};
 }
-impl<T> AssertOk for Option<T> {
-    type T = T;
+/// `as_raw` for the wrappers that don't also have `from_raw` and `into_raw`.
+macro_rules! as_raw {
+    ($($wrapper:ident),+) => {$(
+        impl $wrapper {
+            /// The COM interface pointer, without adding a reference.
+            ///
+            /// It's only valid for as long as `self` is, unless the caller calls `AddRef`.
+            pub fn as_raw(&self) -> *mut core::ffi::c_void {
+                self.com_ptr().as_raw()
+            }
+        }
+    )+};
+}
+as_raw!(
+    SetupProductReference,
+    SetupErrorState,
+    SetupErrorInfo,
+    SetupFailedPackageReference,
+    SetupPropertyStore,
+    SetupLocalizedProperties,
+    SetupLocalizedPropertyStore,
+    SetupPackageReference,
+    SetupInstanceCatalog,
+    SetupHelper,
+    SetupPolicy
+);
 
-    /// Use this for cases where an API that returns success must also have initialized a COM ptr.
-    ///
-    /// Panics in debug mode, returns `Err(E_POINTER)` in release mode.
-    #[inline(always)]
-    fn assert_ok(self) -> Result<T, HRESULT> {
+debug_com_ptr!(
+    SetupConfiguration,
+    EnumSetupInstances,
+    SetupProductReference,
+    SetupErrorState,
+    SetupErrorInfo,
+    SetupFailedPackageReference,
+    SetupPropertyStore,
+    SetupLocalizedProperties,
+    SetupLocalizedPropertyStore,
+    SetupPackageReference,
+    SetupInstanceCatalog,
+    SetupHelper,
+    SetupPolicy
+);
+
+/// Also includes the instance id, or the error from getting it.
+impl core::fmt::Debug for SetupInstance {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let id = self
+            .GetInstanceId()
+            .map(|id| strings::bstr_to_string_lossy(&id));
+        f.debug_struct("SetupInstance")
+            .field("ptr", &self.as_raw())
+            .field("id", &id)
+            .finish()
+    }
+}
+
+/// Whether two interfaces are on the same COM object.
+///
+/// COM defines this as `QueryInterface` for `IUnknown` giving the same pointer for both.
+fn same_object<A: Interface, B: Interface>(a: &A, b: &B) -> bool {
+    match (a.cast::<IUnknown>(), b.cast::<IUnknown>()) {
+        (Ok(a), Ok(b)) => a.as_raw() == b.as_raw(),
+        // Every COM object has to support `IUnknown`, but if one doesn't then the best we can do
+        // is compare the pointers we have.
+        _ => a.as_raw() == b.as_raw(),
+    }
+}
+
+/// Instances are equal if they're the same COM object, even if reached through different enumerations.
+impl PartialEq for SetupInstance {
+    fn eq(&self, other: &Self) -> bool {
+        same_object(self.com_ptr(), other.com_ptr())
+    }
+}
+
+impl Eq for SetupInstance {}
+
+trait AssertOk {
+    type T;
+    fn assert_ok(self) -> Result<Self::T, HRESULT>;
+}
+impl<T> AssertOk for Option<T> {
+    type T = T;
+
+    /// Use this for cases where an API that returns success must also have initialized a COM ptr.
+    ///
+    /// Panics in debug mode, returns `Err(E_POINTER)` in release mode.
+    #[inline(always)]
+    fn assert_ok(self) -> Result<T, HRESULT> {
         // If calling this method then this should really really really never happen.
         debug_assert!(self.is_some());
         self.ok_or(E_POINTER)
@@ -1044,27 +2655,1731 @@ impl OkHresult for HRESULT {
     }
 }
 
-mod api {
-    use super::*;
-    // Use CoIncrementMTA on win8+?
-    #[cfg(not(target_vendor = "win7"))]
-    windows_link::link!("combase.dll" "system" fn CoCreateInstance(
-    rclsid: *const GUID,
-    pUnkOuter: *mut core::ffi::c_void,
-    dwClsContext: u32,
-    riid: *const GUID,
-    ppv: *mut *mut core::ffi::c_void,
-) -> HRESULT);
-    #[cfg(target_vendor = "win7")]
-    windows_link::link!("ole32.dll" "system" fn fn CoCreateInstance(
-    rclsid: *const GUID,
-    pUnkOuter: *mut core::ffi::c_void,
-    dwClsContext: u32,
-    riid: *const GUID,
-    ppv: *mut *mut core::ffi::c_void,
-) -> HRESULT);
-    windows_link::link!("oleaut32.dll" "system" fn SafeArrayLock(psa: *const SAFEARRAY) -> HRESULT);
-    windows_link::link!("oleaut32.dll" "system" fn SafeArrayUnlock(psa: *const SAFEARRAY) -> HRESULT);
-    windows_link::link!("oleaut32.dll" "system" fn SafeArrayDestroy(psa: *const SAFEARRAY) -> HRESULT);
-}
-use api::*;
+use sys::{CoCreateInstance, SafeArrayDestroy, SafeArrayLock, SafeArrayUnlock};
+
+/// Calls `create`, and if that failed because COM isn't initialized,
+/// calls `initialize` and tries once more.
+fn retry_initialized<T>(
+    mut create: impl FnMut() -> Result<T, HRESULT>,
+    initialize: impl FnOnce() -> Result<(), HRESULT>,
+) -> Result<T, HRESULT> {
+    match create() {
+        Err(e) if hresults::is_com_not_initialized(e) => {
+            initialize()?;
+            create()
+        }
+        result => result,
+    }
+}
+
+/// The item with the highest version, or the first of them if there's a tie.
+fn newest<T>(candidates: impl Iterator<Item = (Version, T)>) -> Option<T> {
+    let mut newest: Option<(Version, T)> = None;
+    for (version, item) in candidates {
+        if newest.as_ref().is_none_or(|(newest, _)| version > *newest) {
+            newest = Some((version, item));
+        }
+    }
+    newest.map(|(_, item)| item)
+}
+
+/// Removes each id in `ids` from `missing`, returning `true` as soon as `missing` is empty.
+fn includes_all<I: Deref<Target = [u16]>>(
+    missing: &mut Vec<&[u16]>,
+    ids: impl Iterator<Item = Result<I, HRESULT>>,
+) -> Result<bool, HRESULT> {
+    for id in ids {
+        let id = id?;
+        missing.retain(|required| !strings::eq_ignore_ascii_case(required, &id));
+        if missing.is_empty() {
+            return Ok(true);
+        }
+    }
+    Ok(missing.is_empty())
+}
+
+/// The index of the id that comes earliest in `preferred`, stopping early if it's the first.
+fn preferred_match<I: Deref<Target = [u16]>>(
+    preferred: &[Vec<u16>],
+    ids: impl Iterator<Item = Result<I, HRESULT>>,
+) -> Result<Option<usize>, HRESULT> {
+    let mut best: Option<(usize, usize)> = None;
+    for (i, id) in ids.enumerate() {
+        let id = id?;
+        let rank = preferred
+            .iter()
+            .position(|wanted| strings::eq_ignore_ascii_case(wanted, &id));
+        if let Some(rank) = rank
+            && best.is_none_or(|(best, _)| rank < best)
+        {
+            best = Some((rank, i));
+            if rank == 0 {
+                break;
+            }
+        }
+    }
+    Ok(best.map(|(_, i)| i))
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use super::{SafeArray, SetupPackageReference};
+    use core::ffi::c_void;
+    use core::sync::atomic::{AtomicU32, Ordering::SeqCst};
+    use std::string::String;
+    use std::vec::Vec;
+
+    /// Every COM-style method should have a snake_case alias.
+    #[test]
+    fn snake_case_aliases() {
+        let source = include_str!("lib.rs");
+        let mut missing = std::vec::Vec::new();
+        for line in source.lines() {
+            let Some(rest) = line.trim_start().strip_prefix("pub fn ") else {
+                continue;
+            };
+            let name: String = rest
+                .chars()
+                .take_while(|c| c.is_ascii_alphanumeric() || *c == '_')
+                .collect();
+            if !name.starts_with(|c: char| c.is_ascii_uppercase()) {
+                continue;
+            }
+            let alias = match name.as_str() {
+                // These would otherwise be shadowed by, or shadow, `Iterator` methods.
+                "Next" => String::from("next_into"),
+                "Skip" => String::from("skip_instances"),
+                "Clone" => String::from("try_clone"),
+                _ => to_snake_case(&name),
+            };
+            let forward = std::format!("pub fn {alias}");
+            let doc_alias = std::format!("#[doc(alias = \"{name}\")]");
+            if !source.contains(&forward) || !source.contains(&doc_alias) {
+                missing.push(name);
+            }
+        }
+        assert!(
+            missing.is_empty(),
+            "missing snake_case aliases for {missing:?}"
+        );
+    }
+
+    #[test]
+    fn newest() {
+        let v = |s: &str| s.parse::<super::Version>().unwrap();
+        assert_eq!(super::newest::<u32>(core::iter::empty()), None);
+        let table: &[(&[&str], usize)] = &[
+            (&["17.9"], 0),
+            (&["17.9.34723.18", "17.10.35013.160"], 1),
+            (&["17.10.35013.160", "17.9.34723.18"], 0),
+            (&["16.11.34601.136", "17.0", "15.9.28307.2094"], 1),
+            // Ties go to the first enumerated.
+            (&["17.9", "17.9", "16.0"], 0),
+            (&["16.0", "17.9", "17.9"], 1),
+        ];
+        for (versions, expected) in table {
+            let candidates = versions.iter().enumerate().map(|(i, s)| (v(s), i));
+            assert_eq!(super::newest(candidates), Some(*expected), "{versions:?}");
+        }
+    }
+
+    #[test]
+    fn includes_all() {
+        let packages = [
+            "Microsoft.VisualStudio.Component.VC.Tools.x86.x64",
+            "Microsoft.VisualStudio.Component.Windows11SDK.22621",
+            "Microsoft.VisualStudio.Workload.VCTools",
+        ];
+        let table: &[(&[&str], bool)] = &[
+            (&[], true),
+            (&["Microsoft.VisualStudio.Workload.VCTools"], true),
+            (&["microsoft.visualstudio.workload.vctools"], true),
+            (
+                &[
+                    "MICROSOFT.VISUALSTUDIO.COMPONENT.VC.TOOLS.X86.X64",
+                    "Microsoft.VisualStudio.Component.Windows11SDK.22621",
+                ],
+                true,
+            ),
+            // Prefixes of real ids aren't matches.
+            (&["Microsoft.VisualStudio.Component.VC.Tools"], false),
+            (&["Microsoft.VisualStudio.Component.Windows11SDK"], false),
+            (
+                &[
+                    "Microsoft.VisualStudio.Workload.VCTools",
+                    "Microsoft.VisualStudio.Component.VC.Tools.ARM64",
+                ],
+                false,
+            ),
+        ];
+        let wide = |s: &str| s.encode_utf16().collect::<Vec<u16>>();
+        for (required, expected) in table {
+            let required: Vec<Vec<u16>> = required.iter().map(|s| wide(s)).collect();
+            let mut missing: Vec<&[u16]> = required.iter().map(Vec::as_slice).collect();
+            let ids = packages.iter().map(|s| Ok(wide(s)));
+            assert_eq!(
+                super::includes_all(&mut missing, ids),
+                Ok(*expected),
+                "{required:?}"
+            );
+        }
+
+        // Reading stops once everything is found, so later errors don't matter.
+        let found = wide("Microsoft.VisualStudio.Workload.VCTools");
+        let ids = [Ok(found.clone()), Err(super::E_UNEXPECTED)];
+        assert_eq!(
+            super::includes_all(&mut std::vec![found.as_slice()], ids.into_iter()),
+            Ok(true)
+        );
+        let ids = [Ok(wide("Other")), Err(super::E_UNEXPECTED)];
+        assert_eq!(
+            super::includes_all(&mut std::vec![found.as_slice()], ids.into_iter()),
+            Err(super::E_UNEXPECTED)
+        );
+    }
+
+    #[test]
+    fn preferred_match() {
+        const ARM64: &str = "Microsoft.VisualStudio.Component.VC.Tools.ARM64";
+        const X64: &str = "Microsoft.VisualStudio.Component.VC.Tools.x86.x64";
+        let wide = |s: &str| s.encode_utf16().collect::<Vec<u16>>();
+        let table: &[(&[&str], &[&str], Option<usize>)] = &[
+            (&[ARM64, X64], &["Other", X64, ARM64], Some(2)),
+            (&[X64, ARM64], &["Other", X64, ARM64], Some(1)),
+            (&[ARM64, X64], &[X64], Some(0)),
+            (
+                &[ARM64, X64],
+                &["OTHER", "microsoft.visualstudio.component.vc.tools.arm64"],
+                Some(1),
+            ),
+            (
+                &["Microsoft.VisualStudio.Component.VC.Tools"],
+                &[ARM64, X64],
+                None,
+            ),
+            (&[], &[ARM64, X64], None),
+            (&[ARM64], &[], None),
+        ];
+        for (preferred, installed, expected) in table {
+            let preferred: Vec<Vec<u16>> = preferred.iter().map(|s| wide(s)).collect();
+            let ids = installed.iter().map(|s| Ok(wide(s)));
+            assert_eq!(
+                super::preferred_match(&preferred, ids),
+                Ok(*expected),
+                "{installed:?}"
+            );
+        }
+
+        // Reading stops at the most preferred id, but errors before then are returned.
+        let preferred = [wide(ARM64), wide(X64)];
+        let ids = [Ok(wide(ARM64)), Err(super::E_UNEXPECTED)];
+        assert_eq!(
+            super::preferred_match(&preferred, ids.into_iter()),
+            Ok(Some(0))
+        );
+        let ids = [Ok(wide(X64)), Err(super::E_UNEXPECTED)];
+        assert_eq!(
+            super::preferred_match(&preferred, ids.into_iter()),
+            Err(super::E_UNEXPECTED)
+        );
+    }
+
+    /// A COM object that only counts its references.
+    #[repr(C)]
+    struct FakeUnknown {
+        vtable: &'static super::IUnknown_Vtbl,
+        refs: AtomicU32,
+    }
+
+    static FAKE_VTABLE: super::IUnknown_Vtbl = FAKE_VTABLE_BASE;
+
+    const FAKE_VTABLE_BASE: super::IUnknown_Vtbl = super::IUnknown_Vtbl {
+        QueryInterface: fake_query_interface,
+        AddRef: fake_add_ref,
+        Release: fake_release,
+    };
+
+    /// Only implements `IUnknown`, which gives back the same object.
+    unsafe extern "system" fn fake_query_interface(
+        this: *mut c_void,
+        iid: *const super::GUID,
+        interface: *mut *mut c_void,
+    ) -> super::HRESULT {
+        use super::Interface;
+        if unsafe { *iid } != super::IUnknown::IID {
+            return super::E_UNEXPECTED;
+        }
+        unsafe {
+            fake_add_ref(this);
+            interface.write(this);
+        }
+        super::S_OK
+    }
+
+    /// Like [`FAKE_VTABLE`] but says it doesn't implement any other interfaces,
+    /// like older versions of setup.
+    static NO_INTERFACE_VTABLE: super::IUnknown_Vtbl = super::IUnknown_Vtbl {
+        QueryInterface: fake_no_interface,
+        AddRef: fake_add_ref,
+        Release: fake_release,
+    };
+
+    unsafe extern "system" fn fake_no_interface(
+        _: *mut c_void,
+        _: *const super::GUID,
+        _: *mut *mut c_void,
+    ) -> super::HRESULT {
+        super::E_NOINTERFACE
+    }
+
+    unsafe extern "system" fn fake_add_ref(this: *mut c_void) -> u32 {
+        unsafe { (*this.cast::<FakeUnknown>()).refs.fetch_add(1, SeqCst) + 1 }
+    }
+
+    unsafe extern "system" fn fake_release(this: *mut c_void) -> u32 {
+        unsafe { (*this.cast::<FakeUnknown>()).refs.fetch_sub(1, SeqCst) - 1 }
+    }
+
+    fn new_fakes(count: usize) -> Vec<FakeUnknown> {
+        (0..count)
+            .map(|_| FakeUnknown {
+                vtable: &FAKE_VTABLE,
+                refs: AtomicU32::new(1),
+            })
+            .collect()
+    }
+
+    fn refs(fakes: &[FakeUnknown]) -> Vec<u32> {
+        fakes.iter().map(|fake| fake.refs.load(SeqCst)).collect()
+    }
+
+    /// An array holding the only reference to each fake.
+    /// Without `FADF_UNKNOWN`, `SafeArrayDestroy` won't release the elements.
+    fn fake_array(fakes: &[FakeUnknown], fadf_unknown: bool) -> SafeArray<SetupPackageReference> {
+        const VT_UNKNOWN: u16 = 13;
+        unsafe {
+            let raw = crate::sys::SafeArrayCreateVector(VT_UNKNOWN, 0, fakes.len() as u32);
+            assert!(!raw.is_null());
+            assert_ne!((*raw).fFeatures & super::FADF_UNKNOWN, 0);
+            if !fadf_unknown {
+                (*raw).fFeatures &= !super::FADF_UNKNOWN;
+            }
+            let data = (*raw).pvData.cast::<*const FakeUnknown>();
+            for (i, fake) in fakes.iter().enumerate() {
+                data.add(i).write(fake);
+            }
+            SafeArray::from_raw(raw).unwrap()
+        }
+    }
+
+    /// An instance taking the only reference to `fake`.
+    fn fake_instance(fake: &FakeUnknown) -> super::SetupInstance {
+        use super::Interface;
+        unsafe {
+            super::SetupInstance::from_interface(super::ISetupInstance::from_raw(
+                core::ptr::from_ref(fake).cast_mut().cast(),
+            ))
+        }
+    }
+
+    type InstanceVtable = <super::ISetupInstance as super::Interface>::Vtable;
+
+    /// Like [`FakeUnknown`], but with an `ISetupInstance` vtable.
+    #[repr(C)]
+    struct FakeInstance {
+        vtable: &'static InstanceVtable,
+        refs: AtomicU32,
+    }
+
+    /// Instances whose `GetInstanceId` and `GetInstallationPath` succeed or fail.
+    /// Nothing else is implemented.
+    static FAKE_INSTANCE_VTABLE: InstanceVtable =
+        fake_instance_vtable(fake_instance_id, fake_installation_path, fake_resolve_path);
+    static FAILING_INSTANCE_VTABLE: InstanceVtable = fake_instance_vtable(
+        fake_unimplemented,
+        fake_unimplemented,
+        fake_unimplemented_path,
+    );
+    /// A broken instance without an id, but still with an installation path.
+    static PATH_ONLY_INSTANCE_VTABLE: InstanceVtable = fake_instance_vtable(
+        fake_unimplemented,
+        fake_installation_path,
+        fake_resolve_path,
+    );
+
+    type BstrGetter = unsafe extern "system" fn(*mut c_void, *mut super::BSTR) -> super::HRESULT;
+    type PathResolver = unsafe extern "system" fn(
+        *mut c_void,
+        super::LPCOLESTR,
+        *mut super::BSTR,
+    ) -> super::HRESULT;
+
+    const fn fake_instance_vtable(
+        id: BstrGetter,
+        path: BstrGetter,
+        resolve: PathResolver,
+    ) -> InstanceVtable {
+        InstanceVtable {
+            base__: FAKE_VTABLE_BASE,
+            GetInstanceId: id,
+            GetInstallDate: fake_unimplemented_date,
+            GetInstallationName: fake_unimplemented,
+            GetInstallationPath: path,
+            GetInstallationVersion: fake_unimplemented,
+            GetDisplayName: fake_unimplemented_localized,
+            GetDescription: fake_unimplemented_localized,
+            ResolvePath: resolve,
+        }
+    }
+
+    unsafe extern "system" fn fake_instance_id(
+        _: *mut c_void,
+        id: *mut super::BSTR,
+    ) -> super::HRESULT {
+        unsafe { *id = super::BSTR::from("c0ffee42") };
+        super::S_OK
+    }
+
+    /// `C:\VS` followed by an unpaired surrogate.
+    const FAKE_INSTALLATION_PATH: [u16; 6] = [
+        b'C' as u16,
+        b':' as u16,
+        b'\\' as u16,
+        b'V' as u16,
+        b'S' as u16,
+        0xD800,
+    ];
+
+    unsafe extern "system" fn fake_installation_path(
+        _: *mut c_void,
+        path: *mut super::BSTR,
+    ) -> super::HRESULT {
+        unsafe { *path = super::strings::bstr_from_units(&FAKE_INSTALLATION_PATH) };
+        super::S_OK
+    }
+
+    /// Appends the relative path to the installation path as it is, without normalizing it.
+    unsafe extern "system" fn fake_resolve_path(
+        _: *mut c_void,
+        relative: super::LPCOLESTR,
+        resolved: *mut super::BSTR,
+    ) -> super::HRESULT {
+        let relative = unsafe { super::WideStr::from_ptr(relative).unwrap().to_slice() };
+        let mut path = FAKE_INSTALLATION_PATH.to_vec();
+        path.push(u16::from(b'\\'));
+        path.extend_from_slice(relative);
+        unsafe { *resolved = super::strings::bstr_from_units(&path) };
+        super::S_OK
+    }
+
+    unsafe extern "system" fn fake_unimplemented(
+        _: *mut c_void,
+        _: *mut super::BSTR,
+    ) -> super::HRESULT {
+        super::E_UNEXPECTED
+    }
+
+    unsafe extern "system" fn fake_unimplemented_date(
+        _: *mut c_void,
+        _: *mut super::FILETIME,
+    ) -> super::HRESULT {
+        super::E_UNEXPECTED
+    }
+
+    unsafe extern "system" fn fake_unimplemented_localized(
+        _: *mut c_void,
+        _: super::LCID,
+        _: *mut super::BSTR,
+    ) -> super::HRESULT {
+        super::E_UNEXPECTED
+    }
+
+    unsafe extern "system" fn fake_unimplemented_path(
+        _: *mut c_void,
+        _: super::LPCOLESTR,
+        _: *mut super::BSTR,
+    ) -> super::HRESULT {
+        super::E_UNEXPECTED
+    }
+
+    type Instance2Vtable = <super::ISetupInstance2 as super::Interface>::Vtable;
+
+    /// Like [`FakeInstance`], but with an `ISetupInstance2` vtable.
+    #[repr(C)]
+    struct FakeInstance2 {
+        vtable: &'static Instance2Vtable,
+        refs: AtomicU32,
+    }
+
+    fn fake_instance2() -> FakeInstance2 {
+        FakeInstance2 {
+            vtable: &FAKE_INSTANCE2_VTABLE,
+            refs: AtomicU32::new(1),
+        }
+    }
+
+    /// A complete instance with no packages or product, implementing `ISetupInstance2`.
+    /// Its installation path is [`FAKE_INSTALLATION_PATH`].
+    static FAKE_INSTANCE2_VTABLE: Instance2Vtable = Instance2Vtable {
+        base__: InstanceVtable {
+            base__: super::IUnknown_Vtbl {
+                QueryInterface: fake_instance2_query_interface,
+                AddRef: fake_add_ref,
+                Release: fake_release,
+            },
+            GetInstanceId: fake_instance_id,
+            GetInstallDate: fake_install_date,
+            GetInstallationName: fake_installation_name,
+            GetInstallationPath: fake_installation_path,
+            GetInstallationVersion: fake_installation_version,
+            GetDisplayName: fake_display_name,
+            GetDescription: fake_description,
+            ResolvePath: fake_resolve_path,
+        },
+        GetState: fake_state,
+        GetPackages: fake_no_packages,
+        GetProduct: fake_no_product,
+        GetProductPath: fake_unimplemented,
+        GetErrors: fake_no_errors,
+        IsLaunchable: fake_true,
+        IsComplete: fake_true,
+        GetProperties: fake_no_properties,
+        GetEnginePath: fake_unimplemented,
+    };
+
+    /// Implements `IUnknown`, `ISetupInstance` and `ISetupInstance2`, and nothing else.
+    unsafe extern "system" fn fake_instance2_query_interface(
+        this: *mut c_void,
+        iid: *const super::GUID,
+        interface: *mut *mut c_void,
+    ) -> super::HRESULT {
+        use super::Interface;
+        let iid = unsafe { *iid };
+        let known = [
+            super::IUnknown::IID,
+            super::ISetupInstance::IID,
+            super::ISetupInstance2::IID,
+        ];
+        if !known.contains(&iid) {
+            return super::E_NOINTERFACE;
+        }
+        unsafe {
+            fake_add_ref(this);
+            interface.write(this);
+        }
+        super::S_OK
+    }
+
+    const FAKE_INSTALL_DATE: u64 = 133_500_000_000_000_000;
+
+    unsafe extern "system" fn fake_install_date(
+        _: *mut c_void,
+        date: *mut super::FILETIME,
+    ) -> super::HRESULT {
+        unsafe {
+            *date = super::FILETIME {
+                dwLowDateTime: FAKE_INSTALL_DATE as u32,
+                dwHighDateTime: (FAKE_INSTALL_DATE >> 32) as u32,
+            }
+        };
+        super::S_OK
+    }
+
+    unsafe extern "system" fn fake_installation_name(
+        _: *mut c_void,
+        name: *mut super::BSTR,
+    ) -> super::HRESULT {
+        unsafe { *name = super::BSTR::from("VisualStudio/17.9.5+34714.143") };
+        super::S_OK
+    }
+
+    unsafe extern "system" fn fake_installation_version(
+        _: *mut c_void,
+        version: *mut super::BSTR,
+    ) -> super::HRESULT {
+        unsafe { *version = super::BSTR::from("17.9.34714.143") };
+        super::S_OK
+    }
+
+    /// The name in English, or German for LCID 0x407.
+    unsafe extern "system" fn fake_display_name(
+        _: *mut c_void,
+        lcid: super::LCID,
+        name: *mut super::BSTR,
+    ) -> super::HRESULT {
+        let text = if lcid == 0x407 {
+            "Visual Studio Gemeinschaft"
+        } else {
+            "Visual Studio Community"
+        };
+        unsafe { *name = super::BSTR::from(text) };
+        super::S_OK
+    }
+
+    unsafe extern "system" fn fake_description(
+        _: *mut c_void,
+        _: super::LCID,
+        description: *mut super::BSTR,
+    ) -> super::HRESULT {
+        unsafe { *description = super::BSTR::from("Überall 🦀") };
+        super::S_OK
+    }
+
+    unsafe extern "system" fn fake_state(
+        _: *mut c_void,
+        state: *mut super::InstanceState,
+    ) -> super::HRESULT {
+        unsafe { *state = super::InstanceState::eComplete };
+        super::S_OK
+    }
+
+    unsafe extern "system" fn fake_no_packages(
+        _: *mut c_void,
+        packages: *mut *mut super::SAFEARRAY,
+    ) -> super::HRESULT {
+        const VT_UNKNOWN: u16 = 13;
+        unsafe { *packages = crate::sys::SafeArrayCreateVector(VT_UNKNOWN, 0, 0) };
+        super::S_OK
+    }
+
+    unsafe extern "system" fn fake_no_product(
+        _: *mut c_void,
+        product: *mut Option<super::ISetupPackageReference>,
+    ) -> super::HRESULT {
+        unsafe { *product = None };
+        super::S_OK
+    }
+
+    unsafe extern "system" fn fake_no_errors(
+        _: *mut c_void,
+        errors: *mut Option<super::ISetupErrorState>,
+    ) -> super::HRESULT {
+        unsafe { *errors = None };
+        super::S_OK
+    }
+
+    unsafe extern "system" fn fake_no_properties(
+        _: *mut c_void,
+        properties: *mut Option<super::ISetupPropertyStore>,
+    ) -> super::HRESULT {
+        unsafe { *properties = None };
+        super::S_OK
+    }
+
+    unsafe extern "system" fn fake_true(
+        _: *mut c_void,
+        value: *mut super::VARIANT_BOOL,
+    ) -> super::HRESULT {
+        unsafe { *value = -1 };
+        super::S_OK
+    }
+
+    type ConfigurationVtable = <super::ISetupConfiguration as super::Interface>::Vtable;
+
+    /// A configuration where every path under [`FAKE_INSTALLATION_PATH`] belongs to one instance.
+    /// It remembers the last path it was asked about.
+    #[repr(C)]
+    struct FakeConfiguration {
+        vtable: &'static ConfigurationVtable,
+        refs: AtomicU32,
+        instance: FakeInstance,
+        last_path: std::sync::Mutex<Vec<u16>>,
+        /// The path of the "current process".
+        process_path: &'static [u16],
+        /// If set, every lookup fails with this.
+        failure: Option<super::HRESULT>,
+    }
+
+    static FAKE_CONFIGURATION_VTABLE: ConfigurationVtable = ConfigurationVtable {
+        base__: FAKE_VTABLE_BASE,
+        EnumInstances: fake_enum_instances,
+        GetInstanceForCurrentProcess: fake_instance_for_current_process,
+        GetInstanceForPath: fake_instance_for_path,
+    };
+
+    fn fake_configuration(
+        process_path: &'static [u16],
+        failure: Option<super::HRESULT>,
+    ) -> FakeConfiguration {
+        FakeConfiguration {
+            vtable: &FAKE_CONFIGURATION_VTABLE,
+            refs: AtomicU32::new(1),
+            instance: FakeInstance {
+                vtable: &FAKE_INSTANCE_VTABLE,
+                refs: AtomicU32::new(1),
+            },
+            last_path: std::sync::Mutex::new(Vec::new()),
+            process_path,
+            failure,
+        }
+    }
+
+    /// A configuration taking the only reference to `fake`.
+    fn fake_setup(fake: &FakeConfiguration) -> super::SetupConfiguration {
+        unsafe { super::SetupConfiguration::from_raw(core::ptr::from_ref(fake).cast_mut().cast()) }
+    }
+
+    unsafe extern "system" fn fake_enum_instances(
+        _: *mut c_void,
+        _: *mut Option<super::IEnumSetupInstances>,
+    ) -> super::HRESULT {
+        super::E_UNEXPECTED
+    }
+
+    unsafe extern "system" fn fake_instance_for_current_process(
+        this: *mut c_void,
+        instance: *mut Option<super::ISetupInstance>,
+    ) -> super::HRESULT {
+        let fake = unsafe { &*this.cast::<FakeConfiguration>() };
+        unsafe { fake_lookup(fake, fake.process_path, instance) }
+    }
+
+    unsafe extern "system" fn fake_instance_for_path(
+        this: *mut c_void,
+        path: *const u16,
+        instance: *mut Option<super::ISetupInstance>,
+    ) -> super::HRESULT {
+        let fake = unsafe { &*this.cast::<FakeConfiguration>() };
+        let path = unsafe { super::WideStr::from_ptr(path).unwrap().to_slice() };
+        *fake.last_path.lock().unwrap() = path.to_vec();
+        unsafe { fake_lookup(fake, path, instance) }
+    }
+
+    unsafe fn fake_lookup(
+        fake: &FakeConfiguration,
+        path: &[u16],
+        instance: *mut Option<super::ISetupInstance>,
+    ) -> super::HRESULT {
+        use super::Interface;
+        if let Some(failure) = fake.failure {
+            return failure;
+        }
+        if !path.starts_with(&FAKE_INSTALLATION_PATH) {
+            return super::E_NOTFOUND;
+        }
+        fake.instance.refs.fetch_add(1, SeqCst);
+        let raw = core::ptr::from_ref(&fake.instance).cast_mut().cast();
+        unsafe { *instance = Some(super::ISetupInstance::from_raw(raw)) };
+        super::S_OK
+    }
+
+    #[test]
+    fn debug() {
+        let fakes = new_fakes(1);
+        let package = SetupPackageReference {
+            raw: unsafe {
+                use super::Interface;
+                super::ISetupPackageReference::from_raw(
+                    core::ptr::from_ref(&fakes[0]).cast_mut().cast(),
+                )
+            },
+        };
+        assert_eq!(
+            std::format!("{package:?}"),
+            std::format!("SetupPackageReference({:p})", &fakes[0])
+        );
+        drop(package);
+
+        for (vtable, id) in [
+            (&FAKE_INSTANCE_VTABLE, "Ok(\"c0ffee42\")"),
+            (&FAILING_INSTANCE_VTABLE, "Err(HRESULT(0x8000FFFF))"),
+        ] {
+            let fake = FakeInstance {
+                vtable,
+                refs: AtomicU32::new(1),
+            };
+            let instance = fake_instance(unsafe { &*core::ptr::from_ref(&fake).cast() });
+            assert_eq!(
+                std::format!("{instance:?}"),
+                std::format!("SetupInstance {{ ptr: {:p}, id: {id} }}", &fake)
+            );
+        }
+    }
+
+    /// Another interface on `object`, with its own pointer, like a tear-off interface.
+    #[repr(C)]
+    struct FakeTearOff<'a> {
+        vtable: &'static super::IUnknown_Vtbl,
+        refs: AtomicU32,
+        object: &'a FakeUnknown,
+    }
+
+    static TEAR_OFF_VTABLE: super::IUnknown_Vtbl = super::IUnknown_Vtbl {
+        QueryInterface: fake_tear_off_query_interface,
+        AddRef: fake_add_ref,
+        Release: fake_release,
+    };
+
+    unsafe extern "system" fn fake_tear_off_query_interface(
+        this: *mut c_void,
+        iid: *const super::GUID,
+        interface: *mut *mut c_void,
+    ) -> super::HRESULT {
+        let object = unsafe { (*this.cast::<FakeTearOff<'_>>()).object };
+        unsafe {
+            fake_query_interface(
+                core::ptr::from_ref(object).cast_mut().cast(),
+                iid,
+                interface,
+            )
+        }
+    }
+
+    #[test]
+    fn com_identity() {
+        let fakes = new_fakes(2);
+        let instance = fake_instance(&fakes[0]);
+        assert_eq!(instance, instance.clone());
+        assert_ne!(instance, fake_instance(&fakes[1]));
+
+        let tear_off = FakeTearOff {
+            vtable: &TEAR_OFF_VTABLE,
+            refs: AtomicU32::new(1),
+            object: &fakes[0],
+        };
+        let other = fake_instance(unsafe { &*core::ptr::from_ref(&tear_off).cast() });
+        assert_eq!(instance, other);
+        drop(other);
+        assert_eq!(tear_off.refs.load(SeqCst), 0);
+        assert_eq!(refs(&fakes), [1, 0]);
+    }
+
+    #[test]
+    fn union_instances() {
+        use crate::query::union_instances;
+
+        let fake = |vtable| FakeInstance {
+            vtable,
+            refs: AtomicU32::new(1),
+        };
+        let fakes = [
+            fake(&FAKE_INSTANCE_VTABLE),
+            fake(&FAKE_INSTANCE_VTABLE),
+            fake(&PATH_ONLY_INSTANCE_VTABLE),
+            fake(&PATH_ONLY_INSTANCE_VTABLE),
+            fake(&FAILING_INSTANCE_VTABLE),
+            fake(&FAILING_INSTANCE_VTABLE),
+        ];
+        let instances: Vec<super::SetupInstance> = fakes
+            .iter()
+            .map(|fake| fake_instance(unsafe { &*core::ptr::from_ref(fake).cast() }))
+            .collect();
+        let [
+            with_id,
+            other_with_id,
+            path_only,
+            other_path_only,
+            failing,
+            other_failing,
+        ] = &instances[..]
+        else {
+            unreachable!()
+        };
+        let union = |first: &[&super::SetupInstance], second: &[&super::SetupInstance]| {
+            union_instances(
+                first.iter().copied().cloned(),
+                second.iter().copied().cloned(),
+            )
+        };
+
+        // The same object from both enumerations.
+        assert_eq!(
+            union(&[failing], &[failing]),
+            core::slice::from_ref(failing)
+        );
+        // Different objects for the same instance id.
+        assert_eq!(
+            union(&[with_id], &[other_with_id]),
+            core::slice::from_ref(with_id)
+        );
+        // Without ids, the installation paths are compared.
+        assert_eq!(
+            union(&[path_only], &[other_path_only]),
+            core::slice::from_ref(path_only)
+        );
+        assert_eq!(
+            union(&[with_id], &[path_only]),
+            core::slice::from_ref(with_id)
+        );
+        // Instances without either can't be told apart, so both are kept in order.
+        assert_eq!(
+            union(&[failing, with_id], &[other_failing, failing]),
+            [failing.clone(), with_id.clone(), other_failing.clone()]
+        );
+
+        drop(instances);
+        for fake in &fakes {
+            assert_eq!(fake.refs.load(SeqCst), 0);
+        }
+    }
+
+    #[test]
+    fn raw_round_trip() {
+        use super::{EnumSetupInstances, SetupConfiguration, SetupInstance};
+        let fakes = new_fakes(3);
+        let ptrs: Vec<*mut c_void> = fakes
+            .iter()
+            .map(|fake| core::ptr::from_ref(fake).cast_mut().cast())
+            .collect();
+        unsafe {
+            let instance = SetupInstance::from_raw(ptrs[0]);
+            assert_eq!(instance.as_raw(), ptrs[0]);
+            assert_eq!(instance.into_raw(), ptrs[0]);
+            let instances = EnumSetupInstances::from_raw(ptrs[1]);
+            assert_eq!(instances.as_raw(), ptrs[1]);
+            assert_eq!(instances.into_raw(), ptrs[1]);
+            let setup = SetupConfiguration::from_raw(ptrs[2]);
+            assert_eq!(setup.as_raw(), ptrs[2]);
+            assert_eq!(setup.into_raw(), ptrs[2]);
+        }
+        // Neither wrapping nor unwrapping changes the reference count.
+        assert_eq!(refs(&fakes), [1, 1, 1]);
+        unsafe {
+            drop(SetupInstance::from_raw(ptrs[0]));
+            drop(EnumSetupInstances::from_raw(ptrs[1]));
+            drop(SetupConfiguration::from_raw(ptrs[2]));
+        }
+        assert_eq!(refs(&fakes), [0, 0, 0]);
+    }
+
+    #[test]
+    fn wrap_existing_configuration() {
+        let fakes = new_fakes(1);
+        let ptr = core::ptr::from_ref(&fakes[0]).cast_mut().cast();
+        // The caller keeps their own reference and gives the wrapper another.
+        unsafe { fake_add_ref(ptr) };
+        let setup = unsafe { super::SetupConfiguration::from_raw(ptr) };
+        assert_eq!(setup.as_raw(), ptr);
+        let clone = setup.clone();
+        assert_eq!(refs(&fakes), [3]);
+        drop(setup);
+        drop(clone);
+        // Each wrapper released exactly its own reference.
+        assert_eq!(refs(&fakes), [1]);
+    }
+
+    #[test]
+    fn as_raw() {
+        use super::Interface;
+        let fakes = new_fakes(1);
+        let ptr: *mut c_void = core::ptr::from_ref(&fakes[0]).cast_mut().cast();
+        macro_rules! check {
+            ($($wrapper:ident: $interface:ident),+) => {$({
+                unsafe { fake_add_ref(ptr) };
+                let wrapper = super::$wrapper {
+                    raw: unsafe { super::$interface::from_raw(ptr) },
+                };
+                assert_eq!(wrapper.as_raw(), ptr, stringify!($wrapper));
+                assert_eq!(wrapper.com_ptr().as_raw(), ptr, stringify!($wrapper));
+            })+};
+        }
+        check!(
+            SetupConfiguration: ISetupConfiguration,
+            EnumSetupInstances: IEnumSetupInstances,
+            SetupInstance: ISetupInstance,
+            SetupProductReference: ISetupPackageReference,
+            SetupErrorState: ISetupErrorState,
+            SetupErrorInfo: ISetupErrorInfo,
+            SetupFailedPackageReference: ISetupFailedPackageReference,
+            SetupPropertyStore: ISetupPropertyStore,
+            SetupLocalizedProperties: ISetupLocalizedProperties,
+            SetupLocalizedPropertyStore: ISetupLocalizedPropertyStore,
+            SetupPackageReference: ISetupPackageReference,
+            SetupInstanceCatalog: ISetupInstanceCatalog,
+            SetupHelper: ISetupHelper,
+            SetupPolicy: ISetupPolicy
+        );
+        assert_eq!(refs(&fakes), [1]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn installation_path_is_lossless() {
+        let fake = FakeInstance {
+            vtable: &FAKE_INSTANCE_VTABLE,
+            refs: AtomicU32::new(1),
+        };
+        let instance = fake_instance(unsafe { &*core::ptr::from_ref(&fake).cast() });
+        let path = instance.installation_path().unwrap();
+        assert_eq!(
+            super::strings::os_str_to_wide(path.as_os_str()).collect::<Vec<u16>>(),
+            FAKE_INSTALLATION_PATH
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn resolve() {
+        let fake = FakeInstance {
+            vtable: &FAKE_INSTANCE_VTABLE,
+            refs: AtomicU32::new(1),
+        };
+        let instance = fake_instance(unsafe { &*core::ptr::from_ref(&fake).cast() });
+        for relative in ["VC\\Tools\\MSVC", "VC/Tools/MSVC", "Überprüfung\\产品 🦀"] {
+            let mut expected = FAKE_INSTALLATION_PATH.to_vec();
+            expected.push(u16::from(b'\\'));
+            expected.extend(relative.encode_utf16());
+            let resolved = instance.resolve(relative).unwrap();
+            assert_eq!(
+                super::strings::os_str_to_wide(resolved.as_os_str()).collect::<Vec<u16>>(),
+                expected,
+                "{relative}"
+            );
+        }
+        assert_eq!(instance.resolve("VC\0Tools"), Err(super::E_INVALIDARG));
+    }
+
+    #[test]
+    fn get_instance_for_path() {
+        let fake = fake_configuration(&[], None);
+        let setup = fake_setup(&fake);
+        let inside: Vec<u16> = FAKE_INSTALLATION_PATH.iter().copied().chain([0]).collect();
+        let instance = setup.GetInstanceForPath(&inside[..]).unwrap();
+        assert_eq!(
+            instance.as_raw(),
+            core::ptr::from_ref(&fake.instance).cast_mut().cast()
+        );
+        assert_eq!(fake.instance.refs.load(SeqCst), 2);
+        drop(instance);
+        assert_eq!(
+            setup.GetInstanceForPath(&[u16::from(b'C'), 0][..]).err(),
+            Some(super::E_NOTFOUND)
+        );
+        assert_eq!(fake.instance.refs.load(SeqCst), 1);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn instance_for_path() {
+        let fake = fake_configuration(&[], None);
+        let setup = fake_setup(&fake);
+
+        let mut long = FAKE_INSTALLATION_PATH.to_vec();
+        for _ in 0..40 {
+            long.extend("\\subfolder".encode_utf16());
+        }
+        assert!(long.len() > 260);
+        let verbatim: Vec<u16> = "\\\\?\\".encode_utf16().chain(long.clone()).collect();
+        let outside: Vec<u16> = "C:\\Temp\\不是".encode_utf16().collect();
+        for (path, found) in [(&long, true), (&verbatim, false), (&outside, false)] {
+            let instance = setup.instance_for_path(super::strings::bstr_to_os_string(path));
+            assert_eq!(*fake.last_path.lock().unwrap(), *path);
+            assert_eq!(
+                instance.unwrap().map(|instance| instance.as_raw()),
+                found.then(|| core::ptr::from_ref(&fake.instance).cast_mut().cast())
+            );
+        }
+        assert_eq!(fake.instance.refs.load(SeqCst), 1);
+        assert_eq!(
+            setup.instance_for_path("C:\\VS\0").err(),
+            Some(super::E_INVALIDARG)
+        );
+
+        let fake = fake_configuration(&[], Some(super::E_UNEXPECTED));
+        let setup = fake_setup(&fake);
+        assert_eq!(
+            setup.instance_for_path("C:\\Temp").err(),
+            Some(super::E_UNEXPECTED)
+        );
+    }
+
+    #[test]
+    fn instance_for_current_process() {
+        const OUTSIDE: &[u16] = &[b'C' as u16, b':' as u16, b'\\' as u16];
+        let inside = fake_configuration(&FAKE_INSTALLATION_PATH, None);
+        let instance = fake_setup(&inside).instance_for_current_process().unwrap();
+        assert_eq!(
+            instance.map(|instance| instance.as_raw()),
+            Some(core::ptr::from_ref(&inside.instance).cast_mut().cast())
+        );
+        assert_eq!(inside.instance.refs.load(SeqCst), 1);
+
+        let outside = fake_configuration(OUTSIDE, None);
+        let instance = fake_setup(&outside).instance_for_current_process();
+        assert!(matches!(instance, Ok(None)));
+
+        let failing = fake_configuration(&FAKE_INSTALLATION_PATH, Some(super::E_UNEXPECTED));
+        let instance = fake_setup(&failing).instance_for_current_process();
+        assert_eq!(instance.err(), Some(super::E_UNEXPECTED));
+    }
+
+    #[test]
+    fn retry_initialized() {
+        use super::hresults::{CO_E_NOTINITIALIZED, E_FAIL, REGDB_E_CLASSNOTREG};
+        use core::cell::Cell;
+        // What `create` returns each time it's called, what `initialize` returns,
+        // the result and how many times `create` and `initialize` were called.
+        let table: [(&[Result<u32, super::HRESULT>], _, _, _); 5] = [
+            (&[Ok(1)], Ok(()), Ok(1), (1, 0)),
+            (
+                &[Err(REGDB_E_CLASSNOTREG)],
+                Ok(()),
+                Err(REGDB_E_CLASSNOTREG),
+                (1, 0),
+            ),
+            (&[Err(CO_E_NOTINITIALIZED), Ok(2)], Ok(()), Ok(2), (2, 1)),
+            (
+                &[Err(CO_E_NOTINITIALIZED), Err(CO_E_NOTINITIALIZED)],
+                Ok(()),
+                Err(CO_E_NOTINITIALIZED),
+                (2, 1),
+            ),
+            (
+                &[Err(CO_E_NOTINITIALIZED)],
+                Err(E_FAIL),
+                Err(E_FAIL),
+                (1, 1),
+            ),
+        ];
+        for (i, (creates, initialize, expected, calls)) in table.into_iter().enumerate() {
+            let created = Cell::new(0);
+            let initialized = Cell::new(0);
+            let result = super::retry_initialized(
+                || {
+                    created.set(created.get() + 1);
+                    creates[created.get() - 1]
+                },
+                || {
+                    initialized.set(initialized.get() + 1);
+                    initialize
+                },
+            );
+            assert_eq!(result, expected, "case {i}");
+            assert_eq!((created.get(), initialized.get()), calls, "case {i}");
+        }
+    }
+
+    #[test]
+    fn product_and_prerelease_fallbacks() {
+        let fake = fake_instance2();
+        let instance = fake_instance(unsafe { &*core::ptr::from_ref(&fake).cast() });
+        // Without a product reference, there's no product rather than one made up from the
+        // installation name.
+        assert_eq!(instance.product_id(), Ok(None));
+        // Without a catalog, it's not a prerelease.
+        assert_eq!(instance.is_prerelease(), Ok(false));
+    }
+
+    type PropertyStoreVtable = <super::ISetupPropertyStore as super::Interface>::Vtable;
+
+    /// Like [`FakeUnknown`], but with an `ISetupPropertyStore` vtable.
+    #[repr(C)]
+    struct FakePropertyStore {
+        vtable: &'static PropertyStoreVtable,
+        refs: AtomicU32,
+    }
+
+    /// A store with only a `nickname` of "Preview". `GetNames` isn't implemented.
+    static FAKE_PROPERTY_STORE_VTABLE: PropertyStoreVtable = PropertyStoreVtable {
+        base__: FAKE_VTABLE_BASE,
+        GetNames: fake_unimplemented_names,
+        GetValue: fake_nickname_value,
+    };
+
+    unsafe extern "system" fn fake_unimplemented_names(
+        _: *mut c_void,
+        _: *mut *mut super::SAFEARRAY,
+    ) -> super::HRESULT {
+        super::E_UNEXPECTED
+    }
+
+    unsafe extern "system" fn fake_nickname_value(
+        _: *mut c_void,
+        name: super::LPCOLESTR,
+        value: *mut super::VARIANT,
+    ) -> super::HRESULT {
+        let name = unsafe { super::WideStr::from_ptr(name).unwrap() };
+        if name != wide_str!("nickname") {
+            return super::E_NOTFOUND;
+        }
+        unsafe { value.write(super::VARIANT::from_bstr(super::BSTR::from("Preview"))) };
+        super::S_OK
+    }
+
+    #[test]
+    fn well_known_properties() {
+        use super::{DISP_E_TYPEMISMATCH, E_FAIL, E_NOINTERFACE, Interface, property_from};
+
+        let fake = FakePropertyStore {
+            vtable: &FAKE_PROPERTY_STORE_VTABLE,
+            refs: AtomicU32::new(1),
+        };
+        let store = || unsafe {
+            let raw = core::ptr::from_ref(&fake).cast_mut().cast();
+            fake_add_ref(raw);
+            Ok(Some(super::SetupPropertyStore::from_interface(
+                super::ISetupPropertyStore::from_raw(raw),
+            )))
+        };
+        let nickname = wide_str!("nickname");
+        assert_eq!(
+            property_from::<String>(store(), nickname),
+            Ok(Some("Preview".into()))
+        );
+        assert_eq!(
+            property_from::<bool>(store(), nickname),
+            Err(DISP_E_TYPEMISMATCH)
+        );
+        // Missing properties, and missing stores.
+        assert_eq!(
+            property_from::<String>(store(), wide_str!("channelId")),
+            Ok(None)
+        );
+        assert_eq!(property_from::<String>(Ok(None), nickname), Ok(None));
+        assert_eq!(
+            property_from::<String>(Err(E_NOINTERFACE), nickname),
+            Ok(None)
+        );
+        assert_eq!(property_from::<String>(Err(E_FAIL), nickname), Err(E_FAIL));
+        #[cfg(feature = "std")]
+        assert_eq!(
+            property_from::<std::path::PathBuf>(store(), nickname),
+            Ok(Some("Preview".into()))
+        );
+        assert_eq!(fake.refs.load(SeqCst), 1);
+
+        // An instance without custom properties, or a property store of its own.
+        let fake = fake_instance2();
+        let instance = fake_instance(unsafe { &*core::ptr::from_ref(&fake).cast() });
+        assert_eq!(instance.channel_id(), Ok(None));
+        assert_eq!(instance.channel_uri(), Ok(None));
+        assert_eq!(instance.nickname(), Ok(None));
+        #[cfg(feature = "std")]
+        assert_eq!(instance.setup_engine_file_path(), Ok(None));
+        #[cfg(feature = "std")]
+        assert_eq!(instance.channel(), Ok(None));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn install_date() {
+        use std::time::{Duration, SystemTime};
+
+        let fake = fake_instance2();
+        let instance = fake_instance(unsafe { &*core::ptr::from_ref(&fake).cast() });
+        // 2024-01-17T21:20:00Z
+        let expected = SystemTime::UNIX_EPOCH + Duration::from_secs(1_705_526_400);
+        assert_eq!(instance.install_date(), Ok(Some(expected)));
+        assert_eq!(
+            u64::from(instance.GetInstallDate().unwrap()),
+            FAKE_INSTALL_DATE
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn snapshot() {
+        use super::info::{InstanceSnapshot, SnapshotOptions};
+        fn send_sync<T: Send + Sync + 'static>(_: &T) {}
+
+        let fake = fake_instance2();
+        let instance = fake_instance(unsafe { &*core::ptr::from_ref(&fake).cast() });
+        let snapshot = instance.snapshot().unwrap();
+        send_sync(&snapshot);
+        let expected = InstanceSnapshot {
+            instance_id: "c0ffee42".parse().unwrap(),
+            display_name: "Visual Studio Community".into(),
+            description: "Überall 🦀".into(),
+            installation_name: "VisualStudio/17.9.5+34714.143".into(),
+            installation_path: snapshot.installation_path.clone(),
+            installation_version: super::Version::new(17, 9, 34714, 143),
+            install_date: FAKE_INSTALL_DATE,
+            state: super::InstanceState::eComplete,
+            is_launchable: true,
+            is_complete: true,
+            product_id: None,
+            packages: Some(Vec::new()),
+        };
+        assert_eq!(snapshot, expected);
+        assert_eq!(
+            super::strings::os_str_to_wide(snapshot.installation_path.as_os_str())
+                .collect::<Vec<u16>>(),
+            FAKE_INSTALLATION_PATH
+        );
+
+        let options = SnapshotOptions::default().packages(false).lcid(0x407);
+        assert_eq!(options.lcid, super::Lcid::from(0x407_u32));
+        let german = instance.snapshot_with(options).unwrap();
+        assert_eq!(german.display_name, "Visual Studio Gemeinschaft");
+        assert_eq!(german.packages, None);
+
+        // Nothing is left referring to the instance.
+        drop(instance);
+        assert_eq!(fake.refs.load(SeqCst), 0);
+        let snapshot = std::thread::spawn(move || snapshot).join().unwrap();
+        assert_eq!(snapshot, expected);
+    }
+
+    #[test]
+    fn vswhere_json() {
+        use alloc::format;
+        use serde_json::Value;
+
+        /// Every key in `json` must also be in `fixture`, at the same place and of the same type.
+        fn assert_shape(json: &Value, fixture: &Value, path: &str) {
+            match (json, fixture) {
+                (Value::Array(json), Value::Array(fixture)) => {
+                    for json in json {
+                        assert_shape(json, &fixture[0], path);
+                    }
+                }
+                (Value::Object(json), Value::Object(fixture)) => {
+                    for (key, json) in json {
+                        let fixture = fixture.get(key).unwrap_or_else(|| panic!("{path}.{key}"));
+                        assert_shape(json, fixture, &format!("{path}.{key}"));
+                    }
+                }
+                (Value::String(_), Value::String(_))
+                | (Value::Bool(_), Value::Bool(_))
+                | (Value::Number(_), Value::Number(_)) => {}
+                _ => panic!("{path}: {json} isn't like {fixture}"),
+            }
+        }
+
+        let fake = fake_instance2();
+        let instance = fake_instance(unsafe { &*core::ptr::from_ref(&fake).cast() });
+        let json = super::vswhere::to_json([&instance, &instance]);
+        let fixture = include_str!("../tests/fixtures/vswhere.json");
+        let parsed: Value = serde_json::from_str(&json).unwrap();
+        assert_shape(&parsed, &serde_json::from_str(fixture).unwrap(), "");
+
+        // The keys that were written are in the same order as in the fixture.
+        fn keys(json: &str) -> Vec<&str> {
+            json.lines()
+                .filter_map(|line| line.strip_prefix("    \"")?.split_once('"'))
+                .map(|(key, _)| key)
+                .collect()
+        }
+        let one = super::vswhere::to_json([&instance]);
+        let mut in_fixture = keys(fixture).into_iter();
+        for key in keys(&one) {
+            assert!(in_fixture.any(|k| k == key), "{key} is out of order");
+        }
+
+        // Failed calls are left out: there's no product, engine path, catalog or properties.
+        let expected = r#"
+  {
+    "instanceId": "c0ffee42",
+    "installDate": "2024-01-17T21:20:00Z",
+    "installationName": "VisualStudio/17.9.5+34714.143",
+    "installationPath": "C:\\VS�",
+    "installationVersion": "17.9.34714.143",
+    "state": 4294967295,
+    "isComplete": true,
+    "isLaunchable": true,
+    "isRebootRequired": false,
+    "displayName": "Visual Studio Community",
+    "description": "Überall 🦀"
+  }"#;
+        assert_eq!(json, format!("[{expected},{expected}\n]\n"));
+        assert_eq!(super::vswhere::to_json([]), "[]\n");
+        drop(instance);
+        assert_eq!(fake.refs.load(SeqCst), 0);
+    }
+
+    #[test]
+    fn clone_adds_a_reference() {
+        let fakes = new_fakes(1);
+        let instance = fake_instance(&fakes[0]);
+        let clones: Vec<_> = (0..3).map(|_| instance.clone()).collect();
+        assert_eq!(refs(&fakes), [4]);
+        drop(instance);
+        assert_eq!(refs(&fakes), [3]);
+        drop(clones);
+        assert_eq!(refs(&fakes), [0]);
+    }
+
+    #[test]
+    fn prerelease_without_catalog() {
+        let fakes = [
+            FakeUnknown {
+                vtable: &NO_INTERFACE_VTABLE,
+                refs: AtomicU32::new(1),
+            },
+            FakeUnknown {
+                vtable: &FAKE_VTABLE,
+                refs: AtomicU32::new(1),
+            },
+        ];
+        // Older versions of setup don't have a catalog, and their instances are releases.
+        assert_eq!(fake_instance(&fakes[0]).is_prerelease(), Ok(false));
+        // Any other error is passed on.
+        assert_eq!(
+            fake_instance(&fakes[1]).is_prerelease(),
+            Err(super::E_UNEXPECTED)
+        );
+        assert_eq!(refs(&fakes), [0, 0]);
+    }
+
+    #[test]
+    fn checked_operations() {
+        use crate::error::Operation;
+
+        // A failed cast is reported as such, rather than as the method that needed it.
+        let fake = FakeUnknown {
+            vtable: &NO_INTERFACE_VTABLE,
+            refs: AtomicU32::new(1),
+        };
+        let instance = fake_instance(&fake);
+        let error = instance.checked().GetState().unwrap_err();
+        assert_eq!(error.hresult(), super::E_NOINTERFACE);
+        assert_eq!(
+            error.operation(),
+            Some(Operation::QueryInterface(crate::guids::IID_ISetupInstance2))
+        );
+        let error = instance.checked().to_catalog().unwrap_err();
+        assert_eq!(
+            error.operation(),
+            Some(Operation::QueryInterface(
+                crate::guids::IID_ISetupInstanceCatalog
+            ))
+        );
+        drop(instance);
+        assert_eq!(fake.refs.load(SeqCst), 0);
+
+        let fake = FakeInstance {
+            vtable: &FAILING_INSTANCE_VTABLE,
+            refs: AtomicU32::new(1),
+        };
+        let instance = fake_instance(unsafe { &*core::ptr::from_ref(&fake).cast() });
+        let error = instance.checked().GetInstanceId().unwrap_err();
+        assert_eq!(error.hresult(), super::E_UNEXPECTED);
+        assert_eq!(error.operation(), Some(Operation::GetInstanceId));
+        let error = instance.checked().GetInstallationPath().unwrap_err();
+        assert_eq!(error.operation(), Some(Operation::GetInstallationPath));
+        drop(instance);
+        assert_eq!(fake.refs.load(SeqCst), 0);
+    }
+
+    type EnumVtable = <super::IEnumSetupInstances as super::Interface>::Vtable;
+
+    /// An enumerator that plays back a script of results from `Next`, then `S_FALSE`.
+    #[repr(C)]
+    struct FakeEnum<'a> {
+        vtable: &'static EnumVtable,
+        script: &'a [Result<&'a FakeUnknown, super::HRESULT>],
+        next: core::cell::Cell<usize>,
+    }
+
+    static FAKE_ENUM_VTABLE: EnumVtable = EnumVtable {
+        base__: super::IUnknown_Vtbl {
+            QueryInterface: fake_query_interface,
+            // The enumerator is borrowed from the test, so there's nothing to count.
+            AddRef: fake_enum_ref,
+            Release: fake_enum_ref,
+        },
+        Next: fake_enum_next,
+        Skip: fake_enum_skip,
+        Reset: fake_enum_reset,
+        Clone: fake_enum_clone,
+    };
+
+    unsafe extern "system" fn fake_enum_ref(_: *mut c_void) -> u32 {
+        1
+    }
+
+    /// Fetches up to `count` instances, stopping early at an error in the script.
+    /// The error is returned on its own by the next call, like a server that fails part-way.
+    unsafe extern "system" fn fake_enum_next(
+        this: *mut c_void,
+        count: u32,
+        instances: *mut Option<super::ISetupInstance>,
+        fetched: *mut u32,
+    ) -> super::HRESULT {
+        use super::Interface;
+        let this = unsafe { &*this.cast::<FakeEnum<'_>>() };
+        let mut len = 0;
+        while len < count {
+            match this.script.get(this.next.get()) {
+                Some(Ok(fake)) => {
+                    let raw = core::ptr::from_ref(*fake).cast_mut().cast();
+                    unsafe {
+                        fake_add_ref(raw);
+                        instances
+                            .add(len as usize)
+                            .write(Some(super::ISetupInstance::from_raw(raw)));
+                    }
+                    this.next.set(this.next.get() + 1);
+                    len += 1;
+                }
+                Some(Err(error)) if len == 0 => {
+                    this.next.set(this.next.get() + 1);
+                    return *error;
+                }
+                Some(Err(_)) => break,
+                None => {
+                    if !fetched.is_null() {
+                        unsafe { fetched.write(len) };
+                    }
+                    return super::S_FALSE;
+                }
+            }
+        }
+        if !fetched.is_null() {
+            unsafe { fetched.write(len) };
+        }
+        super::S_OK
+    }
+
+    unsafe extern "system" fn fake_enum_skip(_: *mut c_void, _: u32) -> super::HRESULT {
+        super::E_UNEXPECTED
+    }
+
+    unsafe extern "system" fn fake_enum_reset(_: *mut c_void) -> super::HRESULT {
+        super::E_UNEXPECTED
+    }
+
+    unsafe extern "system" fn fake_enum_clone(
+        _: *mut c_void,
+        _: *mut Option<super::IEnumSetupInstances>,
+    ) -> super::HRESULT {
+        super::E_UNEXPECTED
+    }
+
+    fn fake_enum<'a>(fake: &'a FakeEnum<'a>) -> super::EnumSetupInstances {
+        use super::Interface;
+        super::EnumSetupInstances {
+            raw: unsafe {
+                super::IEnumSetupInstances::from_raw(core::ptr::from_ref(fake).cast_mut().cast())
+            },
+        }
+    }
+
+    #[test]
+    fn try_iter_surfaces_errors() {
+        let fakes = new_fakes(4);
+        let script = [
+            Ok(&fakes[0]),
+            Err(super::E_UNEXPECTED),
+            Ok(&fakes[1]),
+            Ok(&fakes[2]),
+        ];
+        let fake = FakeEnum {
+            vtable: &FAKE_ENUM_VTABLE,
+            script: &script,
+            next: core::cell::Cell::new(0),
+        };
+        let instances = fake_enum(&fake);
+        let results: Vec<_> = instances
+            .try_iter()
+            .map(|instance| instance.map(|_| ()))
+            .collect();
+        assert_eq!(results, [Ok(()), Err(super::E_UNEXPECTED)]);
+        // Trying again carries on after the error, until the end of the instances.
+        assert_eq!(instances.try_iter().count(), 2);
+        assert_eq!(instances.try_iter().count(), 0);
+        assert_eq!(refs(&fakes), [1, 1, 1, 1]);
+
+        // The lossy iterator can't tell the error from the end.
+        let fake = FakeEnum {
+            next: core::cell::Cell::new(0),
+            ..fake
+        };
+        let mut instances = fake_enum(&fake);
+        assert_eq!(instances.by_ref().count(), 1);
+        assert_eq!(instances.count(), 2);
+        assert_eq!(refs(&fakes), [1, 1, 1, 1]);
+    }
+
+    #[test]
+    fn collect_all_in_batches() {
+        for count in [0, 1, 7, 8, 20] {
+            let fakes = new_fakes(count);
+            let script: Vec<_> = fakes.iter().map(Ok).collect();
+            let fake = FakeEnum {
+                vtable: &FAKE_ENUM_VTABLE,
+                script: &script,
+                next: core::cell::Cell::new(0),
+            };
+            let instances = fake_enum(&fake).collect_all().unwrap();
+            assert_eq!(instances.len(), count);
+            for (instance, fake) in instances.iter().zip(&fakes) {
+                // An instance is a pointer to the COM object, so the order can be checked.
+                let ptr = unsafe { *core::ptr::from_ref(instance).cast::<*const FakeUnknown>() };
+                assert!(core::ptr::eq(ptr, fake));
+            }
+            assert!(refs(&fakes).iter().all(|&refs| refs == 2), "{count}");
+            drop(instances);
+            assert!(refs(&fakes).iter().all(|&refs| refs == 1), "{count}");
+        }
+    }
+
+    #[test]
+    fn next_chunk() {
+        let fakes = new_fakes(5);
+        let mut script: Vec<_> = fakes.iter().map(Ok).collect();
+        script.insert(3, Err(super::E_UNEXPECTED));
+        let fake = FakeEnum {
+            vtable: &FAKE_ENUM_VTABLE,
+            script: &script,
+            next: core::cell::Cell::new(0),
+        };
+        let instances = fake_enum(&fake);
+        let first = instances.next_chunk(2).unwrap();
+        assert_eq!(first.len(), 2);
+        // Stops short of the error.
+        assert_eq!(instances.next_chunk(4).unwrap().len(), 1);
+        assert_eq!(refs(&fakes), [2, 2, 1, 1, 1]);
+        assert_eq!(instances.next_chunk(4).err(), Some(super::E_UNEXPECTED));
+        // The end comes part-way through a chunk.
+        assert_eq!(instances.next_chunk(4).unwrap().len(), 2);
+        assert_eq!(instances.next_chunk(4).unwrap().len(), 0);
+        assert_eq!(instances.next_chunk(0).unwrap().len(), 0);
+        drop(first);
+        assert_eq!(refs(&fakes), [1, 1, 1, 1, 1]);
+    }
+
+    #[test]
+    fn collect_all_releases_on_error() {
+        let fakes = new_fakes(12);
+        // The error comes part-way through the second batch.
+        let mut script: Vec<_> = fakes.iter().map(Ok).collect();
+        script.insert(10, Err(super::E_UNEXPECTED));
+        let fake = FakeEnum {
+            vtable: &FAKE_ENUM_VTABLE,
+            script: &script,
+            next: core::cell::Cell::new(0),
+        };
+        assert_eq!(
+            fake_enum(&fake).collect_all().err(),
+            Some(super::E_UNEXPECTED)
+        );
+        assert!(refs(&fakes).iter().all(|&refs| refs == 1));
+    }
+
+    #[test]
+    fn safe_array_releases_elements_once() {
+        for fadf_unknown in [true, false] {
+            // Dropped without being accessed.
+            let fakes = new_fakes(4);
+            drop(fake_array(&fakes, fadf_unknown));
+            assert_eq!(refs(&fakes), [0; 4], "{fadf_unknown}");
+
+            // Every element referenced again while iterating.
+            let fakes = new_fakes(4);
+            let array = fake_array(&fakes, fadf_unknown);
+            let clones: Vec<_> = array.iter().cloned().collect();
+            assert_eq!(refs(&fakes), [2; 4], "{fadf_unknown}");
+            drop(clones);
+            assert_eq!(refs(&fakes), [1; 4], "{fadf_unknown}");
+            drop(array);
+            assert_eq!(refs(&fakes), [0; 4], "{fadf_unknown}");
+
+            // Iteration stopped early, with the array dropped before the clones.
+            let fakes = new_fakes(4);
+            let array = fake_array(&fakes, fadf_unknown);
+            let clones: Vec<_> = array.iter().take(2).cloned().collect();
+            assert_eq!(refs(&fakes), [2, 2, 1, 1], "{fadf_unknown}");
+            drop(array);
+            assert_eq!(refs(&fakes), [1, 1, 0, 0], "{fadf_unknown}");
+            drop(clones);
+            assert_eq!(refs(&fakes), [0; 4], "{fadf_unknown}");
+
+            // Moved out of the array.
+            let fakes = new_fakes(4);
+            let vec = fake_array(&fakes, fadf_unknown).into_vec();
+            assert_eq!(refs(&fakes), [1; 4], "{fadf_unknown}");
+            drop(vec);
+            assert_eq!(refs(&fakes), [0; 4], "{fadf_unknown}");
+        }
+    }
+
+    #[test]
+    fn empty_safe_array() {
+        let array = fake_array(&[], false);
+        assert!(array.is_empty());
+        assert!(array.into_vec().is_empty());
+    }
+
+    #[test]
+    fn typed_values() {
+        use super::{
+            BSTR, DISP_E_OVERFLOW, DISP_E_TYPEMISMATCH, E_FAIL, E_NOTFOUND, Variant, typed_value,
+        };
+
+        let string = |value| typed_value::<String>(value);
+        assert_eq!(
+            string(Ok(Variant::Bstr(BSTR::from("Preview")))),
+            Ok(Some("Preview".into()))
+        );
+        assert_eq!(
+            string(Ok(Variant::Bstr(BSTR::new()))),
+            Ok(Some(String::new()))
+        );
+        assert_eq!(string(Err(E_NOTFOUND)), Ok(None));
+        assert_eq!(string(Ok(Variant::Empty)), Ok(None));
+        assert_eq!(string(Ok(Variant::Null)), Ok(None));
+        assert_eq!(string(Ok(Variant::Bool(true))), Err(DISP_E_TYPEMISMATCH));
+        assert_eq!(string(Ok(Variant::Unknown)), Err(DISP_E_TYPEMISMATCH));
+        assert_eq!(string(Err(E_FAIL)), Err(E_FAIL));
+
+        let bool = |value| typed_value::<bool>(value);
+        assert_eq!(bool(Ok(Variant::Bool(false))), Ok(Some(false)));
+        assert_eq!(bool(Err(E_NOTFOUND)), Ok(None));
+        assert_eq!(bool(Ok(Variant::Unsigned(1))), Err(DISP_E_TYPEMISMATCH));
+        assert_eq!(
+            bool(Ok(Variant::Bstr(BSTR::from("true")))),
+            Err(DISP_E_TYPEMISMATCH)
+        );
+
+        let u64 = |value| typed_value::<u64>(value);
+        assert_eq!(u64(Ok(Variant::Unsigned(u64::MAX))), Ok(Some(u64::MAX)));
+        assert_eq!(u64(Ok(Variant::Signed(7))), Ok(Some(7)));
+        assert_eq!(u64(Ok(Variant::Signed(-7))), Err(DISP_E_OVERFLOW));
+        assert_eq!(u64(Err(E_NOTFOUND)), Ok(None));
+        assert_eq!(
+            u64(Ok(Variant::Bstr(BSTR::from("7")))),
+            Err(DISP_E_TYPEMISMATCH)
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn property_maps() {
+        use super::{BSTR, E_FAIL, Variant, property_map};
+
+        let map = property_map(&[], |_| unreachable!());
+        assert!(map.is_empty());
+
+        let names = [
+            "nickname",
+            "channelId",
+            "isPrerelease",
+            "campaignId",
+            "bad",
+            "über",
+        ]
+        .map(BSTR::from);
+        let map = property_map(&names, |name| {
+            Ok(match &*super::strings::bstr_to_string_lossy(name) {
+                "nickname" => Variant::Bstr(BSTR::from("Preview")),
+                "channelId" => Variant::Bstr(BSTR::from("VisualStudio.17.Release")),
+                "isPrerelease" => Variant::Bool(false),
+                "campaignId" => Variant::Unsigned(2030),
+                "über" => Variant::Empty,
+                _ => return Err(E_FAIL),
+            })
+        });
+        assert_eq!(map.len(), 6);
+        assert_eq!(map["nickname"], "Preview");
+        assert_eq!(map["channelId"], "VisualStudio.17.Release");
+        assert_eq!(map["isPrerelease"], false);
+        assert_eq!(map["campaignId"], 2030);
+        assert_eq!(map["über"], Variant::Empty);
+        assert!(matches!(map["bad"], Variant::Unknown));
+    }
+
+    fn to_snake_case(name: &str) -> String {
+        let mut snake = String::new();
+        let mut prev_lower = false;
+        for c in name.chars() {
+            if c.is_ascii_uppercase() && prev_lower {
+                snake.push('_');
+            }
+            prev_lower = c.is_ascii_lowercase();
+            snake.push(c.to_ascii_lowercase());
+        }
+        snake
+    }
+}
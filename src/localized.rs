@@ -0,0 +1,127 @@
+//! Looking up localized properties with a fallback to related languages.
+
+use alloc::string::String;
+
+use crate::{E_NOTFOUND, HRESULT, LCID, SetupLocalizedPropertyStore, Variant, strings};
+
+/// `MAKELCID(MAKELANGID(LANG_ENGLISH, SUBLANG_ENGLISH_US), SORT_DEFAULT)`
+const EN_US: LCID = 0x0409;
+
+/// The locales to try, in order, when looking up a value for `lcid`.
+///
+/// That's the requested locale, its language with no region, then US English and English.
+pub(crate) fn fallback_chain(lcid: LCID) -> impl Iterator<Item = LCID> {
+    // The primary language id is the low 10 bits of the LCID.
+    let chain = [lcid, lcid & 0x3FF, EN_US, EN_US & 0x3FF];
+    chain
+        .into_iter()
+        .enumerate()
+        .filter(move |&(i, lcid)| lcid != 0 && !chain[..i].contains(&lcid))
+        .map(|(_, lcid)| lcid)
+}
+
+/// Localized strings by name and exact locale, which [`lookup`] searches through.
+pub(crate) trait LocalizedStore {
+    /// The value for exactly this locale, or `None` if there isn't one.
+    fn value(&self, name: &str, lcid: LCID) -> Result<Option<String>, HRESULT>;
+}
+
+impl LocalizedStore for SetupLocalizedPropertyStore {
+    fn value(&self, name: &str, lcid: LCID) -> Result<Option<String>, HRESULT> {
+        match self.get(name, lcid) {
+            Ok(Variant::Bstr(value)) if !value.is_empty() => {
+                Ok(Some(strings::bstr_to_string_lossy(&value)))
+            }
+            Ok(_) | Err(E_NOTFOUND) => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+}
+
+/// The value of `name` in the first locale of the [`fallback_chain`] that has one.
+pub(crate) fn lookup(
+    store: &impl LocalizedStore,
+    name: &str,
+    lcid: LCID,
+) -> Result<Option<String>, HRESULT> {
+    for lcid in fallback_chain(lcid) {
+        if let Some(value) = store.value(name, lcid)? {
+            return Ok(Some(value));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::E_UNEXPECTED;
+    use crate::fake_store::FakeStore;
+    use alloc::vec::Vec;
+
+    const DE_DE: LCID = 0x0407;
+    const DE: LCID = 0x0007;
+    const FR_CA: LCID = 0x0C0C;
+    const EN_GB: LCID = 0x0809;
+    const EN: LCID = 0x0009;
+
+    impl LocalizedStore for FakeStore<'_, (&str, LCID), &str> {
+        fn value(&self, name: &str, lcid: LCID) -> Result<Option<String>, HRESULT> {
+            let value = self.find(|&(n, l)| n == name && l == lcid)?;
+            Ok(value.map(|&value| String::from(value)))
+        }
+    }
+
+    const RELEASE: FakeStore<(&str, LCID), &str> = FakeStore(&[
+        (("title", EN_US), Ok("Current")),
+        (("title", DE), Ok("Aktuell")),
+        (
+            ("description", EN_US),
+            Ok("Stable releases of Visual Studio"),
+        ),
+        (("broken", EN_US), Err(E_UNEXPECTED)),
+    ]);
+
+    #[test]
+    fn chains() {
+        let table: &[(LCID, &[LCID])] = &[
+            (DE_DE, &[DE_DE, DE, EN_US, EN]),
+            (DE, &[DE, EN_US, EN]),
+            (EN_US, &[EN_US, EN]),
+            (EN_GB, &[EN_GB, EN, EN_US]),
+            (0, &[EN_US, EN]),
+        ];
+        for &(lcid, expected) in table {
+            assert_eq!(
+                fallback_chain(lcid).collect::<Vec<_>>(),
+                expected,
+                "{lcid:#x}"
+            );
+        }
+    }
+
+    #[test]
+    fn lookups() {
+        let table = [
+            ("title", EN_US, Some("Current")),
+            ("title", DE_DE, Some("Aktuell")),
+            ("title", FR_CA, Some("Current")),
+            ("title", EN_GB, Some("Current")),
+            (
+                "description",
+                DE_DE,
+                Some("Stable releases of Visual Studio"),
+            ),
+            ("missing", EN_US, None),
+        ];
+        for (name, lcid, expected) in table {
+            assert_eq!(
+                lookup(&RELEASE, name, lcid),
+                Ok(expected.map(String::from)),
+                "{name} {lcid:#x}"
+            );
+        }
+        assert_eq!(lookup(&FakeStore(&[]), "title", EN_US), Ok(None));
+        assert_eq!(lookup(&RELEASE, "broken", EN_US), Err(E_UNEXPECTED));
+    }
+}
@@ -0,0 +1,105 @@
+//! Working out when an instance was last changed, from the files setup writes.
+
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use std::vec::Vec;
+
+/// The files whose modification times show an instance changing.
+///
+/// - `state.json` in the instance's `_Instances` directory, rewritten by every install, modify or update.
+/// - The `.vsconfig` in the installation root, if the instance has one.
+/// - The installation root itself, which changes as files are added or removed.
+///
+/// All of these are considered equally and the newest wins; the order only matters for the tests.
+pub(crate) fn sources(
+    installation_path: &Path,
+    program_data: Option<&OsStr>,
+    id: &str,
+) -> Vec<PathBuf> {
+    let mut sources = Vec::new();
+    if let Some(program_data) = program_data {
+        sources.push(
+            Path::new(program_data)
+                .join(r"Microsoft\VisualStudio\Packages\_Instances")
+                .join(id)
+                .join("state.json"),
+        );
+    }
+    sources.push(installation_path.join(".vsconfig"));
+    sources.push(installation_path.to_path_buf());
+    sources
+}
+
+/// The newest modification time of the given paths, ignoring any that can't be read.
+pub(crate) fn newest(paths: &[PathBuf]) -> Option<SystemTime> {
+    paths
+        .iter()
+        .filter_map(|path| {
+            std::fs::metadata(path)
+                .and_then(|meta| meta.modified())
+                .ok()
+        })
+        .max()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp_dir::TempDir;
+    use std::fs::File;
+    use std::time::Duration;
+
+    fn touch(path: &Path, time: SystemTime) {
+        File::options()
+            .create(true)
+            .append(true)
+            .open(path)
+            .unwrap()
+            .set_modified(time)
+            .unwrap();
+    }
+
+    #[test]
+    fn newest_source_wins() {
+        let root = TempDir::new("modified");
+        let install = root.join("install");
+        let program_data = root.join("ProgramData");
+        let sources = sources(&install, Some(program_data.as_os_str()), "abcd1234");
+        let [state, vsconfig, install_dir] = &sources[..] else {
+            panic!("unexpected sources {sources:?}");
+        };
+        assert!(state.ends_with(Path::new("abcd1234").join("state.json")));
+
+        // Nothing exists yet.
+        assert_eq!(newest(&sources), None);
+
+        // The installation directory's own time is "now" so use times after that.
+        let now = SystemTime::now();
+        let day = Duration::from_secs(24 * 60 * 60);
+        std::fs::create_dir_all(&install).unwrap();
+        std::fs::create_dir_all(state.parent().unwrap()).unwrap();
+        touch(state, now + day);
+        touch(vsconfig, now + 2 * day);
+        assert_eq!(newest(&sources), Some(now + 2 * day));
+
+        touch(state, now + 3 * day);
+        assert_eq!(newest(&sources), Some(now + 3 * day));
+
+        // Missing sources are skipped, leaving only the directory.
+        std::fs::remove_file(state).unwrap();
+        std::fs::remove_file(vsconfig).unwrap();
+        let dir_time = std::fs::metadata(install_dir).unwrap().modified().unwrap();
+        assert_eq!(newest(&sources), Some(dir_time));
+        assert!(dir_time < now + day);
+    }
+
+    #[test]
+    fn without_program_data() {
+        let install = Path::new(r"C:\VS");
+        assert_eq!(
+            sources(install, None, "abcd1234"),
+            [install.join(".vsconfig"), install.to_path_buf()]
+        );
+    }
+}
@@ -0,0 +1,217 @@
+//! Locating MSBuild within an instance, and running it.
+
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+use std::vec::Vec;
+
+use crate::Version;
+
+/// Where MSBuild lives relative to the installation path, newest layout first.
+///
+/// Visual Studio 2019 and later use `Current`, Visual Studio 2017 used `15.0`.
+const MSBUILD_PATHS: &[&str] = &[
+    r"MSBuild\Current\Bin\MSBuild.exe",
+    r"MSBuild\15.0\Bin\MSBuild.exe",
+];
+
+/// Find `MSBuild.exe` under an installation path.
+pub(crate) fn find_msbuild(installation_path: &Path) -> Option<PathBuf> {
+    MSBUILD_PATHS
+        .iter()
+        .map(|relative| installation_path.join(relative))
+        .find(|path| path.is_file())
+}
+
+/// The toolset's `bin` directory for this host, and the target directory under `bin` and `lib`.
+const HOST_AND_TARGET: (&str, &str) = if cfg!(target_arch = "aarch64") {
+    ("Hostarm64", "arm64")
+} else if cfg!(target_arch = "x86") {
+    ("Hostx86", "x86")
+} else {
+    ("Hostx64", "x64")
+};
+
+/// The newest toolset under `VC\Tools\MSVC`, e.g. `VC\Tools\MSVC\14.38.33130`.
+///
+/// Directories whose names aren't versions are ignored.
+pub(crate) fn newest_toolset(msvc_dir: &Path) -> Option<PathBuf> {
+    std::fs::read_dir(msvc_dir)
+        .ok()?
+        .flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_dir()))
+        .filter_map(|entry| {
+            let version: Version = entry.file_name().to_str()?.parse().ok()?;
+            Some((version, entry.path()))
+        })
+        .max_by_key(|(version, _)| *version)
+        .map(|(_, path)| path)
+}
+
+/// The variables to set to build with the toolset in `toolset_dir`.
+///
+/// Its directories are put in front of those already in `PATH`, `INCLUDE` and `LIB`,
+/// as read by `existing`, so nothing the user has set up is lost.
+pub(crate) fn toolset_env(
+    toolset_dir: &Path,
+    existing: impl Fn(&'static str) -> Option<OsString>,
+) -> [(&'static str, OsString); 3] {
+    let (host, target) = HOST_AND_TARGET;
+    let dirs = [
+        ("PATH", toolset_dir.join("bin").join(host).join(target)),
+        ("INCLUDE", toolset_dir.join("include")),
+        ("LIB", toolset_dir.join("lib").join(target)),
+    ];
+    dirs.map(|(name, dir)| (name, prepend(&dir, existing(name))))
+}
+
+/// `dir` followed by the entries of a `;` separated list, if there are any.
+fn prepend(dir: &Path, list: Option<OsString>) -> OsString {
+    let mut value = OsString::from(dir);
+    if let Some(list) = list.filter(|list| !list.is_empty()) {
+        value.push(";");
+        value.push(list);
+    }
+    value
+}
+
+/// The arguments as one command line, quoted so `CommandLineToArgvW` splits them back out as they are.
+pub(crate) fn command_line(args: &[&OsStr]) -> OsString {
+    let mut line = OsString::new();
+    for (i, arg) in args.iter().enumerate() {
+        if i > 0 {
+            line.push(" ");
+        }
+        line.push(quote(arg));
+    }
+    line
+}
+
+/// Quote an argument if it's empty or has spaces, tabs or quotes in it.
+///
+/// Backslashes are only special before a quote, where they must be doubled,
+/// and so they're also doubled before the closing quote.
+fn quote(arg: &OsStr) -> OsString {
+    let bytes = arg.as_encoded_bytes();
+    if !bytes.is_empty() && !bytes.iter().any(|b| matches!(b, b' ' | b'\t' | b'"')) {
+        return OsString::from(arg);
+    }
+    let mut quoted = Vec::with_capacity(bytes.len() + 2);
+    quoted.push(b'"');
+    let mut backslashes = 0;
+    for &b in bytes {
+        match b {
+            b'\\' => backslashes += 1,
+            b'"' => {
+                quoted.extend(core::iter::repeat_n(b'\\', backslashes + 1));
+                backslashes = 0;
+            }
+            _ => backslashes = 0,
+        }
+        quoted.push(b);
+    }
+    quoted.extend(core::iter::repeat_n(b'\\', backslashes));
+    quoted.push(b'"');
+    // SAFETY: only ASCII was added, at the ends or next to other ASCII.
+    unsafe { OsString::from_encoded_bytes_unchecked(quoted) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp_dir::TempDir;
+
+    #[test]
+    fn layouts() {
+        let root = TempDir::new("msbuild");
+        let vs2017 = root.join("2017");
+        let vs2022 = root.join("2022");
+        let both = root.join("both");
+        for (install, relative) in [
+            (&vs2017, MSBUILD_PATHS[1]),
+            (&vs2022, MSBUILD_PATHS[0]),
+            (&both, MSBUILD_PATHS[0]),
+            (&both, MSBUILD_PATHS[1]),
+        ] {
+            let exe = install.join(relative);
+            std::fs::create_dir_all(exe.parent().unwrap()).unwrap();
+            std::fs::write(&exe, b"").unwrap();
+        }
+        // A directory isn't an executable.
+        std::fs::create_dir_all(root.join("dir").join(MSBUILD_PATHS[0])).unwrap();
+
+        assert_eq!(find_msbuild(&vs2017), Some(vs2017.join(MSBUILD_PATHS[1])));
+        assert_eq!(find_msbuild(&vs2022), Some(vs2022.join(MSBUILD_PATHS[0])));
+        assert_eq!(find_msbuild(&both), Some(both.join(MSBUILD_PATHS[0])));
+        assert_eq!(find_msbuild(&root.join("dir")), None);
+        assert_eq!(find_msbuild(&root.join("missing")), None);
+    }
+
+    #[test]
+    fn quoting() {
+        let table = [
+            ("-version", "-version"),
+            (r"C:\src\app.sln", r"C:\src\app.sln"),
+            ("", r#""""#),
+            (
+                "-p:Configuration=Release Debug",
+                r#""-p:Configuration=Release Debug""#,
+            ),
+            (r#"-p:Name="x""#, r#""-p:Name=\"x\"""#),
+            (r"C:\Program Files\", r#""C:\Program Files\\""#),
+            (r#"a\"b c"#, r#""a\\\"b c""#),
+            ("tab\there", "\"tab\there\""),
+            ("Überall 🦀", "\"Überall 🦀\""),
+        ];
+        for (arg, expected) in table {
+            assert_eq!(quote(OsStr::new(arg)), OsStr::new(expected), "{arg}");
+        }
+        let args = ["-t:Build", "my app.sln", ""].map(OsStr::new);
+        assert_eq!(command_line(&args), r#"-t:Build "my app.sln" """#);
+        assert_eq!(command_line(&[]), "");
+    }
+
+    #[test]
+    fn environment() {
+        let toolset = Path::new(r"C:\VS\VC\Tools\MSVC\14.38.33130");
+        let (host, target) = HOST_AND_TARGET;
+        let bin = toolset.join("bin").join(host).join(target);
+        let include = toolset.join("include");
+        let lib = toolset.join("lib").join(target);
+
+        let env = toolset_env(toolset, |name| match name {
+            "PATH" => Some(r"C:\Windows\system32;C:\Windows".into()),
+            "INCLUDE" => Some(r"C:\sdk\include".into()),
+            _ => Some(OsString::new()),
+        });
+        let expected = |dir: &Path, rest: &str| {
+            let mut value = OsString::from(dir);
+            value.push(rest);
+            value
+        };
+        assert_eq!(
+            env,
+            [
+                ("PATH", expected(&bin, r";C:\Windows\system32;C:\Windows")),
+                ("INCLUDE", expected(&include, r";C:\sdk\include")),
+                // An empty list is replaced rather than left with a trailing separator.
+                ("LIB", expected(&lib, "")),
+            ]
+        );
+        let env = toolset_env(toolset, |_| None);
+        assert_eq!(
+            env.map(|(_, value)| value),
+            [&bin, &include, &lib].map(OsString::from)
+        );
+    }
+
+    #[test]
+    fn toolsets() {
+        let root = TempDir::new("msbuild-toolset");
+        for name in ["14.9.1", "14.38.33130", "14.29.30133", "not-a-version"] {
+            std::fs::create_dir_all(root.join(name)).unwrap();
+        }
+        std::fs::write(root.join("14.40.0"), b"not a directory").unwrap();
+        assert_eq!(newest_toolset(&root), Some(root.join("14.38.33130")));
+        assert_eq!(newest_toolset(&root.join("missing")), None);
+    }
+}
@@ -0,0 +1,271 @@
+//! Finding which instance a file belongs to.
+//!
+//! Tools such as language servers ask this for many files under the same few installations.
+//! [`InstanceOwnerCache`] remembers each installation path it has seen so that any path under it
+//! can be answered without calling into COM again. Paths with `..` components always ask setup.
+//!
+//! ## Example
+//!
+//! ```rust
+//! # fn main() -> Result<(), vssetup::HRESULT> {
+//! # vssetup::com::initialize()?;
+//! use std::path::Path;
+//! use vssetup::owner::InstanceOwnerCache;
+//!
+//! let mut owners = InstanceOwnerCache::new(vssetup::SetupConfiguration::new()?);
+//! let cl = Path::new(r"C:\Program Files\Microsoft Visual Studio\2022\Community\VC\Tools\MSVC\14.38.33130\bin\Hostx64\x64\cl.exe");
+//! if let Some(owner) = owners.lookup(cl)? {
+//!     println!("{} is part of {}", cl.display(), owner.id);
+//! }
+//! # Ok(()) }
+//! ```
+
+use std::path::{Path, PathBuf};
+use std::string::String;
+use std::vec::Vec;
+
+use crate::{HRESULT, SetupConfiguration, strings};
+
+/// An instance that owns a path.
+///
+/// This is an owned copy of the instance's details, so it can outlive the COM objects.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct InstanceRef {
+    /// The instance id, as returned by [`SetupInstance::get_instance_id`](crate::SetupInstance::get_instance_id).
+    pub id: String,
+    /// The root directory of the instance.
+    pub installation_path: PathBuf,
+}
+
+/// Caches the results of [`SetupConfiguration::get_instance_for_path`].
+///
+/// Once a path is found to belong to an instance, any other path under that instance's
+/// installation path is resolved from the cache. Paths are compared case-insensitively,
+/// treating `/` and `\` the same and ignoring any `\\?\` prefix.
+/// Paths containing `..` are never resolved from the cache, as they may lead out of an instance.
+pub struct InstanceOwnerCache {
+    setup: SetupConfiguration,
+    owners: Vec<(Vec<u16>, InstanceRef)>,
+}
+
+impl InstanceOwnerCache {
+    pub fn new(setup: SetupConfiguration) -> Self {
+        Self {
+            setup,
+            owners: Vec::new(),
+        }
+    }
+
+    /// Find the instance that `path` belongs to.
+    ///
+    /// Returns `Ok(None)` if no instance owns the path.
+    /// Paths that don't belong to any instance are not cached.
+    pub fn lookup(&mut self, path: &Path) -> Result<Option<InstanceRef>, HRESULT> {
+        let wide: Vec<u16> = strings::os_str_to_wide(path.as_os_str()).collect();
+        if let Some(owner) = find(&self.owners, &normalize(&wide)) {
+            return Ok(Some(owner.clone()));
+        }
+
+        let Some(instance) = self.setup.instance_for_path(path)? else {
+            return Ok(None);
+        };
+        let installation_path = instance.get_installation_path()?;
+        let owner = InstanceRef {
+            id: strings::bstr_to_string_lossy(&instance.get_instance_id()?),
+            installation_path: strings::bstr_to_pathbuf(&installation_path),
+        };
+        self.owners
+            .push((normalize(&installation_path), owner.clone()));
+        Ok(Some(owner))
+    }
+
+    /// Forget every cached owner.
+    ///
+    /// Call this when instances may have been installed, moved or removed.
+    pub fn invalidate(&mut self) {
+        self.owners.clear();
+    }
+}
+
+const SEP: u16 = b'\\' as u16;
+const UNC: &[u16] = &[SEP, SEP];
+const VERBATIM: &[u16] = &[SEP, SEP, b'?' as u16, SEP];
+const VERBATIM_UNC: &[u16] = &[
+    SEP,
+    SEP,
+    b'?' as u16,
+    SEP,
+    b'U' as u16,
+    b'N' as u16,
+    b'C' as u16,
+    SEP,
+];
+
+/// Normalize a path so that two spellings of the same path compare equal.
+///
+/// The path is uppercased, `/` becomes `\`, a `\\?\` or `\\?\UNC\` prefix is removed,
+/// repeated separators and `.` components are collapsed and any trailing separator is dropped.
+/// `..` components are left alone as resolving them could change the meaning of the path.
+pub(crate) fn normalize(path: &[u16]) -> Vec<u16> {
+    let mut upper = strings::to_uppercase(path);
+    for c in &mut upper {
+        if *c == u16::from(b'/') {
+            *c = SEP;
+        }
+    }
+
+    // Collapse separators first, keeping a leading `\\` or `\`.
+    let leading = upper.iter().take(2).take_while(|&&c| c == SEP).count();
+    let mut normalized = upper[..leading].to_vec();
+    let components = upper[leading..]
+        .split(|&c| c == SEP)
+        .filter(|c| !c.is_empty() && *c != [u16::from(b'.')]);
+    for (i, component) in components.enumerate() {
+        if i > 0 {
+            normalized.push(SEP);
+        }
+        normalized.extend_from_slice(component);
+    }
+
+    // Collapsing can itself produce a verbatim prefix (e.g. from `\\\?\`) so strip them all.
+    loop {
+        if let Some(rest) = normalized.strip_prefix(VERBATIM_UNC) {
+            normalized = [UNC, rest].concat();
+        } else if let Some(rest) = normalized.strip_prefix(VERBATIM) {
+            normalized = rest.to_vec();
+        } else {
+            return normalized;
+        }
+    }
+}
+
+/// Whether the normalized `path` is `root` or is under it.
+fn is_under(root: &[u16], path: &[u16]) -> bool {
+    path.starts_with(root) && (path.len() == root.len() || path[root.len()] == SEP)
+}
+
+/// Find the owner with the longest root containing the normalized `path`.
+///
+/// Returns `None` if `path` has a `..` component, as a path such as `C:\VS\2022\..\2019`
+/// starts with `C:\VS\2022` but isn't under it.
+fn find<'a>(owners: &'a [(Vec<u16>, InstanceRef)], path: &[u16]) -> Option<&'a InstanceRef> {
+    const PARENT: &[u16] = &[b'.' as u16, b'.' as u16];
+    if path
+        .split(|&c| c == SEP)
+        .any(|component| component == PARENT)
+    {
+        return None;
+    }
+    owners
+        .iter()
+        .filter(|(root, _)| is_under(root, path))
+        .max_by_key(|(root, _)| root.len())
+        .map(|(_, owner)| owner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::vec;
+
+    fn wide(s: &str) -> Vec<u16> {
+        s.encode_utf16().collect()
+    }
+
+    fn norm(s: &str) -> Vec<u16> {
+        normalize(&wide(s))
+    }
+
+    #[test]
+    fn normalization() {
+        let table = [
+            (r"C:\Program Files\VS", r"C:\PROGRAM FILES\VS"),
+            (r"c:/program files/vs/", r"C:\PROGRAM FILES\VS"),
+            (r"C:\\Program Files\.\VS\\", r"C:\PROGRAM FILES\VS"),
+            (r"\\?\C:\Program Files\VS", r"C:\PROGRAM FILES\VS"),
+            (r"//?/c:/Program Files/VS", r"C:\PROGRAM FILES\VS"),
+            (r"\\?\UNC\server\share\VS", r"\\SERVER\SHARE\VS"),
+            (r"\\?\unc\server\share\VS", r"\\SERVER\SHARE\VS"),
+            (r"\\server\share\\VS\", r"\\SERVER\SHARE\VS"),
+            (r"//server/share/VS", r"\\SERVER\SHARE\VS"),
+            (r"\VS\..\x", r"\VS\..\X"),
+            (r"C:\", "C:"),
+            ("C:", "C:"),
+            ("", ""),
+            // Found by fuzzing: collapsing the separators exposes a verbatim prefix.
+            (r"\\\?\C:\VS", r"C:\VS"),
+            (r"\\?\UNC\?\C:\VS", r"C:\VS"),
+            // `ß` uppercases to two characters so is left alone.
+            ("C:\\Straße\\Ünïcode", "C:\\STRAßE\\ÜNÏCODE"),
+        ];
+        for (path, expected) in table {
+            assert_eq!(norm(path), wide(expected), "{path}");
+        }
+    }
+
+    #[test]
+    fn idempotent() {
+        for path in [
+            r"\\\?\C:\VS",
+            r"\\?\UNC\?\UNC\server",
+            r"\\\\server\share",
+            r"\\?\",
+            r"\\.\pipe\name",
+        ] {
+            let once = norm(path);
+            assert_eq!(normalize(&once), once, "{path}");
+        }
+    }
+
+    #[test]
+    fn unpaired_surrogates_are_kept() {
+        let path = [u16::from(b'a'), 0xD800, u16::from(b'/')];
+        assert_eq!(normalize(&path), [u16::from(b'A'), 0xD800]);
+    }
+
+    #[test]
+    fn under() {
+        let root = norm(r"C:\VS\2022");
+        assert!(is_under(&root, &norm(r"C:\VS\2022")));
+        assert!(is_under(&root, &norm(r"c:/vs/2022/VC/bin/cl.exe")));
+        assert!(is_under(&root, &norm(r"\\?\C:\VS\2022\VC")));
+        assert!(!is_under(&root, &norm(r"C:\VS\2022-preview\VC")));
+        assert!(!is_under(&root, &norm(r"C:\VS")));
+        assert!(!is_under(&root, &norm(r"D:\VS\2022")));
+        assert!(is_under(&norm(r"C:\"), &norm(r"C:\VS")));
+    }
+
+    #[test]
+    fn longest_root_wins() {
+        let owner = |id: &str| InstanceRef {
+            id: id.into(),
+            installation_path: PathBuf::new(),
+        };
+        let owners = vec![
+            (norm(r"C:\VS"), owner("outer")),
+            (norm(r"C:\VS\Nested"), owner("inner")),
+        ];
+        let id = |path: &str| find(&owners, &norm(path)).map(|owner| owner.id.as_str());
+        assert_eq!(id(r"C:\VS\Nested\cl.exe"), Some("inner"));
+        assert_eq!(id(r"C:\VS\NestedOther\cl.exe"), Some("outer"));
+        assert_eq!(id(r"C:\VS"), Some("outer"));
+        assert_eq!(id(r"C:\Other"), None);
+    }
+
+    #[test]
+    fn parent_components_skip_the_cache() {
+        let owners = vec![(
+            norm(r"C:\VS\2022"),
+            InstanceRef {
+                id: "2022".into(),
+                installation_path: PathBuf::new(),
+            },
+        )];
+        let id = |path: &str| find(&owners, &norm(path)).map(|owner| owner.id.as_str());
+        assert_eq!(id(r"C:\VS\2022\x"), Some("2022"));
+        assert_eq!(id(r"C:\VS\2022\..\2019\x"), None);
+        assert_eq!(id(r"C:\VS\2022\VC\..\x"), None);
+        // Only whole components count.
+        assert_eq!(id(r"C:\VS\2022\..x\y"), Some("2022"));
+    }
+}
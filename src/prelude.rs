@@ -0,0 +1,25 @@
+//! The items nearly every user needs, for glob importing.
+//!
+//! ```
+//! use vssetup::prelude::*;
+//!
+//! fn main() -> Result<(), HRESULT> {
+//!     com::initialize()?;
+//!     let setup = SetupConfiguration::new()?;
+//!     for instance in setup.enum_all_instances()? {
+//!         println!("{}", strings::bstr_to_string_lossy(&instance.get_display_name(Lcid::USER_DEFAULT)?));
+//!     }
+//!     Ok(())
+//! }
+//! ```
+//!
+//! Everything here is also available from its usual path.
+//! Items are only added in minor releases and never removed except in a major release,
+//! but as a new item could clash with a name in your code, prefer importing from the usual
+//! paths in code that must keep compiling against any future minor release.
+//!
+//! Names likely to clash, such as [`Error`](crate::Error), are deliberately left out.
+
+pub use crate::{
+    BSTR, HRESULT, Lcid, SetupConfiguration, SetupInstance, Variant, com, strings, wide_str,
+};
@@ -0,0 +1,126 @@
+//! A one-shot check for a usable Visual Studio.
+
+use alloc::format;
+use alloc::string::String;
+
+use crate::integrate::{Arch, COMPONENT_HOSTS, COMPONENT_PREFIX};
+use crate::{HRESULT, InstanceQuery, PackedVersion, SetupConfiguration, com};
+
+/// What [`installed`] looks for.
+///
+/// The default accepts any complete, launchable instance, including prereleases.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Requirements {
+    /// Require the latest MSVC build tools targeting this architecture.
+    pub vc_tools: Option<Arch>,
+    /// Require at least this installation version.
+    pub min_version: Option<PackedVersion>,
+}
+
+impl Requirements {
+    pub fn vc_tools(mut self, arch: Arch) -> Self {
+        self.vc_tools = Some(arch);
+        self
+    }
+
+    pub fn min_version(mut self, version: PackedVersion) -> Self {
+        self.min_version = Some(version);
+        self
+    }
+
+    /// A query for the instances meeting the requirements.
+    ///
+    /// Prereleases count, as any usable instance will do.
+    fn query<'a>(&self, setup: &'a SetupConfiguration) -> InstanceQuery<'a> {
+        let mut query = setup.query().include_prerelease(true);
+        if let Some(arch) = self.vc_tools {
+            query = query.requires(&[vc_tools_component(arch).as_str()]);
+        }
+        if let Some(min) = self.min_version {
+            query = query.versions(min..=PackedVersion::MAX);
+        }
+        query
+    }
+}
+
+/// The component providing the latest MSVC build tools for an architecture.
+fn vc_tools_component(arch: Arch) -> String {
+    let [x86_x64, arm, arm64, _arm64ec] = COMPONENT_HOSTS;
+    let host = match arch {
+        Arch::X86 | Arch::X64 => x86_x64,
+        Arch::Arm => arm,
+        Arch::Arm64 => arm64,
+    };
+    format!("{COMPONENT_PREFIX}Tools{host}")
+}
+
+/// Whether the Visual Studio Installer is on this machine, so [`SetupConfiguration::new`] can succeed.
+///
+/// This doesn't mean any instances are installed, only that setup can be asked about them.
+/// Like [`installed`], COM is initialized if needed and any error is treated as `false`.
+/// To tell why `SetupConfiguration::new` failed, see
+/// [`hresults::is_installer_missing`](crate::hresults::is_installer_missing).
+pub fn is_installer_available() -> bool {
+    let initialized = com::initialize().is_ok();
+    let available = SetupConfiguration::new().is_ok();
+    if initialized {
+        // SAFETY: the configuration has been dropped.
+        unsafe { com::uninitialize() };
+    }
+    available
+}
+
+/// Whether any installed instance meets the requirements.
+///
+/// This is best-effort: COM is initialized if needed and any error is treated as `false`.
+///
+/// ```rust
+/// use vssetup::{Requirements, integrate::Arch};
+///
+/// if vssetup::installed(Requirements::default().vc_tools(Arch::X64)) {
+///     println!("MSVC is available");
+/// }
+/// ```
+pub fn installed(requirements: Requirements) -> bool {
+    // If COM is already initialized in a different mode it can still be used, so only
+    // uninitialize if this call was the one to initialize it.
+    let initialized = com::initialize().is_ok();
+    let found = any_instance_meets(&requirements).unwrap_or(false);
+    if initialized {
+        // SAFETY: every COM object used above has been dropped.
+        unsafe { com::uninitialize() };
+    }
+    found
+}
+
+fn any_instance_meets(requirements: &Requirements) -> Result<bool, HRESULT> {
+    let setup = SetupConfiguration::new()?;
+    Ok(requirements.query(&setup).find_first()?.is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vc_tools_components() {
+        let table = [
+            (
+                Arch::X86,
+                "Microsoft.VisualStudio.Component.VC.Tools.x86.x64",
+            ),
+            (
+                Arch::X64,
+                "Microsoft.VisualStudio.Component.VC.Tools.x86.x64",
+            ),
+            (Arch::Arm, "Microsoft.VisualStudio.Component.VC.Tools.ARM"),
+            (
+                Arch::Arm64,
+                "Microsoft.VisualStudio.Component.VC.Tools.ARM64",
+            ),
+        ];
+        for (arch, expected) in table {
+            assert_eq!(vc_tools_component(arch), expected, "{arch:?}");
+        }
+    }
+}
@@ -0,0 +1,184 @@
+//! Product ids, such as `Microsoft.VisualStudio.Product.Community`.
+
+use alloc::string::String;
+use core::cmp::Ordering;
+use core::fmt;
+
+const PREFIX: &str = "Microsoft.VisualStudio.Product.";
+
+/// The product of an instance, e.g. Community or Build Tools.
+///
+/// Products are ordered by [`ProductId::PRECEDENCE`], then any other products by their full id.
+/// This order won't change between versions of this crate, except to add new known products.
+///
+/// ```
+/// use vssetup::ProductId;
+///
+/// let product = ProductId::from("Microsoft.VisualStudio.Product.BuildTools");
+/// assert_eq!(product, ProductId::BuildTools);
+/// assert_eq!(format!("{product}"), "Build Tools");
+/// assert!(ProductId::Enterprise < product);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ProductId {
+    Enterprise,
+    Professional,
+    Community,
+    BuildTools,
+    TeamExplorer,
+    TestAgent,
+    TestController,
+    /// Any other product, holding the full id.
+    Other(String),
+}
+
+impl ProductId {
+    /// The known products in order: the editions from most to least featured,
+    /// Build Tools, then the other known products alphabetically.
+    pub const PRECEDENCE: &[ProductId] = &[
+        Self::Enterprise,
+        Self::Professional,
+        Self::Community,
+        Self::BuildTools,
+        Self::TeamExplorer,
+        Self::TestAgent,
+        Self::TestController,
+    ];
+
+    /// The full id, e.g. `Microsoft.VisualStudio.Product.Community`.
+    pub fn full_id(&self) -> &str {
+        match self {
+            Self::Enterprise => "Microsoft.VisualStudio.Product.Enterprise",
+            Self::Professional => "Microsoft.VisualStudio.Product.Professional",
+            Self::Community => "Microsoft.VisualStudio.Product.Community",
+            Self::BuildTools => "Microsoft.VisualStudio.Product.BuildTools",
+            Self::TeamExplorer => "Microsoft.VisualStudio.Product.TeamExplorer",
+            Self::TestAgent => "Microsoft.VisualStudio.Product.TestAgent",
+            Self::TestController => "Microsoft.VisualStudio.Product.TestController",
+            Self::Other(id) => id,
+        }
+    }
+
+    fn rank(&self) -> usize {
+        Self::PRECEDENCE
+            .iter()
+            .position(|known| known == self)
+            .unwrap_or(Self::PRECEDENCE.len())
+    }
+}
+
+/// Ids are matched case-insensitively, as setup does.
+impl From<&str> for ProductId {
+    fn from(id: &str) -> Self {
+        Self::PRECEDENCE
+            .iter()
+            .find(|known| known.full_id().eq_ignore_ascii_case(id))
+            .cloned()
+            .unwrap_or_else(|| Self::Other(String::from(id)))
+    }
+}
+
+impl Ord for ProductId {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.rank()
+            .cmp(&other.rank())
+            .then_with(|| self.full_id().cmp(other.full_id()))
+    }
+}
+
+impl PartialOrd for ProductId {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// The short, friendly name, e.g. `Build Tools`.
+///
+/// Other products are written without the `Microsoft.VisualStudio.Product.` prefix, if they have it.
+impl fmt::Display for ProductId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Enterprise => "Enterprise",
+            Self::Professional => "Professional",
+            Self::Community => "Community",
+            Self::BuildTools => "Build Tools",
+            Self::TeamExplorer => "Team Explorer",
+            Self::TestAgent => "Test Agent",
+            Self::TestController => "Test Controller",
+            Self::Other(id) => id.strip_prefix(PREFIX).unwrap_or(id),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::format;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn precedence() {
+        // Downstream diffs rely on this order, so changing it is a breaking change.
+        assert_eq!(
+            ProductId::PRECEDENCE
+                .iter()
+                .map(|product| format!("{product}"))
+                .collect::<Vec<_>>(),
+            [
+                "Enterprise",
+                "Professional",
+                "Community",
+                "Build Tools",
+                "Team Explorer",
+                "Test Agent",
+                "Test Controller",
+            ]
+        );
+        for pair in ProductId::PRECEDENCE.windows(2) {
+            assert!(pair[0] < pair[1], "{pair:?}");
+        }
+
+        let mut products: Vec<ProductId> = [
+            "Microsoft.VisualStudio.Product.SqlServer",
+            "Microsoft.VisualStudio.Product.BuildTools",
+            "Contoso.Product",
+            "Microsoft.VisualStudio.Product.TestAgent",
+            "Microsoft.VisualStudio.Product.Enterprise",
+            "Microsoft.VisualStudio.Product.Community",
+        ]
+        .into_iter()
+        .map(ProductId::from)
+        .collect();
+        products.sort();
+        assert_eq!(
+            products,
+            [
+                ProductId::Enterprise,
+                ProductId::Community,
+                ProductId::BuildTools,
+                ProductId::TestAgent,
+                ProductId::Other("Contoso.Product".into()),
+                ProductId::Other("Microsoft.VisualStudio.Product.SqlServer".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn ids() {
+        for product in ProductId::PRECEDENCE {
+            assert_eq!(&ProductId::from(product.full_id()), product);
+            assert!(product.full_id().starts_with(PREFIX));
+        }
+        assert_eq!(
+            ProductId::from("microsoft.visualstudio.product.community"),
+            ProductId::Community
+        );
+        let other = ProductId::from("Microsoft.VisualStudio.Product.SqlServer");
+        assert_eq!(other.full_id(), "Microsoft.VisualStudio.Product.SqlServer");
+        assert_eq!(format!("{other}"), "SqlServer");
+        assert_eq!(
+            format!("{}", ProductId::from("VisualStudio")),
+            "VisualStudio"
+        );
+    }
+}
@@ -0,0 +1,616 @@
+//! Finding instances by product, components, version and so on, like `vswhere` does.
+
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::RangeInclusive;
+
+use crate::{
+    BSTR, HRESULT, PackedVersion, ProductId, SetupConfiguration, SetupInstance, Version, newest,
+    strings,
+};
+
+/// Finds the instances matching all of the given filters.
+///
+/// Get this from [`SetupConfiguration::query`]. Only complete, launchable instances are considered
+/// unless [`include_incomplete`](Self::include_incomplete) is used.
+/// Instances that fail to answer a query are skipped, so one broken instance doesn't hide the others.
+///
+/// ```rust
+/// # fn main() -> Result<(), vssetup::HRESULT> {
+/// # vssetup::com::initialize();
+/// let setup = vssetup::SetupConfiguration::new()?;
+/// let build_tools = setup
+///     .query()
+///     .products(&["Microsoft.VisualStudio.Product.BuildTools"])
+///     .requires(&["Microsoft.VisualStudio.Component.VC.Tools.x86.x64"])
+///     .version_range("[16.0,)")
+///     .latest()
+///     .find_first()?;
+/// # Ok(()) }
+/// ```
+#[must_use]
+pub struct InstanceQuery<'a> {
+    setup: &'a SetupConfiguration,
+    filter: Filter,
+    latest: bool,
+    /// Whether to combine `EnumAllInstances` with `EnumInstances`, see [`union_instances`].
+    union_enumerations: bool,
+}
+
+impl<'a> InstanceQuery<'a> {
+    pub(crate) fn new(setup: &'a SetupConfiguration) -> Self {
+        Self {
+            setup,
+            filter: Filter::default(),
+            latest: false,
+            union_enumerations: false,
+        }
+    }
+
+    /// Only include these products, e.g. `Microsoft.VisualStudio.Product.BuildTools`.
+    ///
+    /// Unlike `vswhere`, all products are included by default.
+    /// Calling this more than once adds to the list.
+    pub fn products(mut self, products: &[&str]) -> Self {
+        self.filter
+            .products
+            .extend(products.iter().copied().map(String::from));
+        self
+    }
+
+    /// Only include instances with all of these packages installed, e.g.
+    /// `Microsoft.VisualStudio.Component.VC.Tools.x86.x64`.
+    ///
+    /// Each is either a package id or a [`Requirement::at_least`] a version.
+    /// Calling this more than once adds to the list.
+    pub fn requires<R: Clone + Into<Requirement>>(mut self, requirements: &[R]) -> Self {
+        self.filter
+            .requires
+            .extend(requirements.iter().cloned().map(Into::into));
+        self
+    }
+
+    /// Only include instances that can provide the MSVC toolset selected by `version_prefix`,
+    /// as passed to `vcvarsall.bat` using `-vcvars_ver` (e.g. `14.29`).
+    ///
+    /// See [`SetupInstance::supports_toolset`] for how this is checked.
+    /// Calling this more than once requires all of the toolsets.
+    #[cfg(feature = "std")]
+    pub fn requires_toolset(mut self, version_prefix: &str) -> Self {
+        self.filter.toolsets.push(String::from(version_prefix));
+        self
+    }
+
+    /// Only include instances with a version in this range, e.g. `[16.0,17.0)`.
+    /// See [`PackedVersion::parse_range`] for the syntax.
+    ///
+    /// If the range is invalid, finding instances returns `E_INVALIDARG`.
+    pub fn version_range(mut self, range: &str) -> Self {
+        self.filter.version_range = Some(PackedVersion::parse_range(range));
+        self
+    }
+
+    /// Only include instances with a version in this range.
+    pub(crate) fn versions(mut self, range: RangeInclusive<PackedVersion>) -> Self {
+        self.filter.version_range = Some(Ok(range));
+        self
+    }
+
+    /// Whether to include prereleases, e.g. Preview. They're excluded by default.
+    pub fn include_prerelease(mut self, include: bool) -> Self {
+        self.filter.include_prerelease = include;
+        self
+    }
+
+    /// Whether to include instances that aren't complete or launchable, e.g. an install that
+    /// failed or is waiting for a reboot. They're excluded by default.
+    ///
+    /// When included, instances from both [`EnumAllInstances`](SetupConfiguration::EnumAllInstances)
+    /// and [`EnumInstances`](SetupConfiguration::EnumInstances) are used, without duplicates,
+    /// as some versions of setup return an instance from one but not the other.
+    pub fn include_incomplete(mut self, include: bool) -> Self {
+        self.filter.include_incomplete = include;
+        self.union_enumerations = include;
+        self
+    }
+
+    /// Only return the matching instance with the highest version.
+    /// If there's a tie, the first one enumerated is returned.
+    pub fn latest(mut self) -> Self {
+        self.latest = true;
+        self
+    }
+
+    /// All the matching instances, in the order setup enumerates them.
+    pub fn find_all(self) -> Result<Vec<SetupInstance>, HRESULT> {
+        self.run().map(QueryResult::into_instances)
+    }
+
+    /// Like [`find_all`](Self::find_all), but also says why instances were rejected
+    /// for not meeting a [`requires`](Self::requires) requirement.
+    ///
+    /// ```rust
+    /// # fn main() -> Result<(), vssetup::HRESULT> {
+    /// # vssetup::com::initialize();
+    /// use vssetup::Requirement;
+    ///
+    /// let setup = vssetup::SetupConfiguration::new()?;
+    /// let vc_tools = Requirement::at_least(
+    ///     "Microsoft.VisualStudio.Component.VC.Tools.x86.x64",
+    ///     "17.8".parse().unwrap(),
+    /// );
+    /// let result = setup.query().requires(&[vc_tools]).run()?;
+    /// for rejection in result.rejections() {
+    ///     println!("{:?} doesn't have {}", rejection.instance, rejection.unmet);
+    /// }
+    /// # Ok(()) }
+    /// ```
+    pub fn run(self) -> Result<QueryResult, HRESULT> {
+        if let Some(Err(error)) = self.filter.version_range {
+            return Err(error);
+        }
+        let mut matches = Vec::new();
+        let mut rejections = Vec::new();
+        for instance in self.instances()? {
+            match self.filter.check(&instance) {
+                Ok(Outcome::Match(version)) => matches.push((version, instance)),
+                Ok(Outcome::Unmet(i)) => rejections.push(Rejection {
+                    instance,
+                    unmet: self.filter.requires[i].clone(),
+                }),
+                Ok(Outcome::NoMatch) | Err(_) => {}
+            }
+        }
+        let instances = if self.latest {
+            newest(matches.into_iter()).into_iter().collect()
+        } else {
+            matches.into_iter().map(|(_, instance)| instance).collect()
+        };
+        Ok(QueryResult {
+            instances,
+            rejections,
+        })
+    }
+
+    /// The first matching instance, or the [`latest`](Self::latest) if that was asked for.
+    pub fn find_first(self) -> Result<Option<SetupInstance>, HRESULT> {
+        if self.latest {
+            return self
+                .find_all()
+                .map(|instances| instances.into_iter().next());
+        }
+        if let Some(Err(error)) = self.filter.version_range {
+            return Err(error);
+        }
+        Ok(self
+            .instances()?
+            .into_iter()
+            .find(|instance| matches!(self.filter.check(instance), Ok(Outcome::Match(_)))))
+    }
+
+    /// The instances to filter, in the order setup enumerates them.
+    fn instances(&self) -> Result<Vec<SetupInstance>, HRESULT> {
+        let all = self.setup.EnumAllInstances()?;
+        if self.union_enumerations {
+            Ok(union_instances(all, self.setup.EnumInstances()?))
+        } else {
+            Ok(all.collect())
+        }
+    }
+}
+
+/// The instances from both enumerations without duplicates, in the order they're first seen.
+///
+/// Instances are duplicates if they're the same COM object or have the same instance id.
+/// If either id can't be read, as for some broken instances, their installation paths are
+/// compared instead. Instances with neither are only duplicates of the same COM object.
+pub(crate) fn union_instances(
+    first: impl IntoIterator<Item = SetupInstance>,
+    second: impl IntoIterator<Item = SetupInstance>,
+) -> Vec<SetupInstance> {
+    struct Seen {
+        instance: SetupInstance,
+        id: Option<BSTR>,
+        path: Option<BSTR>,
+    }
+    let mut seen: Vec<Seen> = Vec::new();
+    for instance in first.into_iter().chain(second) {
+        let id = instance.GetInstanceId().ok();
+        let path = instance.GetInstallationPath().ok();
+        let duplicate = seen.iter().any(|other| {
+            other.instance == instance
+                || match (&id, &other.id) {
+                    (Some(id), Some(other)) => id == other,
+                    _ => path.is_some() && path == other.path,
+                }
+        });
+        if !duplicate {
+            seen.push(Seen { instance, id, path });
+        }
+    }
+    seen.into_iter().map(|seen| seen.instance).collect()
+}
+
+/// A package that instances must have, e.g. for [`InstanceQuery::requires`].
+///
+/// A package id on its own converts into a requirement that any version will meet.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Requirement {
+    id: String,
+    wide: Vec<u16>,
+    min_version: Option<Version>,
+}
+
+impl Requirement {
+    /// Require `id` with a version of at least `version`, e.g. a component shipped with 17.8.
+    ///
+    /// The version is the package's own, which isn't always the same as the instance's.
+    /// A package whose version is missing or can't be parsed doesn't meet this.
+    pub fn at_least(id: &str, version: Version) -> Self {
+        Self {
+            min_version: Some(version),
+            ..Self::from(id)
+        }
+    }
+
+    /// The package id, e.g. `Microsoft.VisualStudio.Component.VC.Tools.x86.x64`.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// The minimum version, if there is one.
+    pub fn min_version(&self) -> Option<Version> {
+        self.min_version
+    }
+
+    /// Whether a package with this id and version meets the requirement.
+    fn met_by(&self, id: &[u16], version: impl FnOnce() -> Option<Version>) -> bool {
+        strings::eq_ignore_ascii_case(&self.wide, id)
+            && self
+                .min_version
+                .is_none_or(|min| version().is_some_and(|version| version >= min))
+    }
+}
+
+impl From<&str> for Requirement {
+    fn from(id: &str) -> Self {
+        Self {
+            id: String::from(id),
+            wide: id.encode_utf16().collect(),
+            min_version: None,
+        }
+    }
+}
+
+impl fmt::Debug for Requirement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Requirement")
+            .field("id", &self.id)
+            .field("min_version", &self.min_version)
+            .finish()
+    }
+}
+
+/// The id, followed by ` >= ` and the minimum version if there is one.
+impl fmt::Display for Requirement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.id)?;
+        if let Some(min) = self.min_version {
+            write!(f, " >= {min}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The instances found by [`InstanceQuery::run`].
+#[derive(Debug, Clone)]
+pub struct QueryResult {
+    instances: Vec<SetupInstance>,
+    rejections: Vec<Rejection>,
+}
+
+impl QueryResult {
+    /// The matching instances, as [`InstanceQuery::find_all`] would return.
+    pub fn instances(&self) -> &[SetupInstance] {
+        &self.instances
+    }
+
+    pub fn into_instances(self) -> Vec<SetupInstance> {
+        self.instances
+    }
+
+    /// The instances that passed every other filter but didn't meet a requirement,
+    /// in the order setup enumerates them.
+    pub fn rejections(&self) -> &[Rejection] {
+        &self.rejections
+    }
+}
+
+/// An instance rejected by a query, see [`QueryResult::rejections`].
+#[derive(Debug, Clone)]
+pub struct Rejection {
+    pub instance: SetupInstance,
+    /// The first of the requirements, in the order given, that the instance doesn't meet.
+    pub unmet: Requirement,
+}
+
+/// The outcome of filtering one instance.
+enum Outcome {
+    Match(Version),
+    NoMatch,
+    /// The index of the first requirement the instance doesn't meet.
+    Unmet(usize),
+}
+
+#[derive(Default)]
+struct Filter {
+    products: Vec<String>,
+    requires: Vec<Requirement>,
+    #[cfg(feature = "std")]
+    toolsets: Vec<String>,
+    version_range: Option<Result<RangeInclusive<PackedVersion>, HRESULT>>,
+    include_prerelease: bool,
+    include_incomplete: bool,
+}
+
+/// What's known about an instance when filtering it.
+struct Facts<'a> {
+    /// `None` for instances without a product reference, which only match if any product will do.
+    product: Option<&'a str>,
+    version: Version,
+    prerelease: bool,
+}
+
+impl Filter {
+    fn check(&self, instance: &SetupInstance) -> Result<Outcome, HRESULT> {
+        if !self.include_incomplete && (!instance.IsComplete()? || !instance.IsLaunchable()?) {
+            return Ok(Outcome::NoMatch);
+        }
+        let product = instance.product_id()?;
+        let facts = Facts {
+            product: product.as_ref().map(ProductId::full_id),
+            version: instance.installation_version()?,
+            prerelease: instance.is_prerelease()?,
+        };
+        if !self.accepts(&facts) {
+            return Ok(Outcome::NoMatch);
+        }
+        // Checked last as there can be thousands of packages to list.
+        if !self.requires.is_empty() {
+            let packages = instance.GetPackages()?;
+            let unmet = first_unmet(
+                &self.requires,
+                packages.iter(),
+                |package| package.GetId(),
+                |package| package.GetVersion(),
+            )?;
+            if let Some(i) = unmet {
+                return Ok(Outcome::Unmet(i));
+            }
+        }
+        #[cfg(feature = "std")]
+        for version_prefix in &self.toolsets {
+            if !instance.supports_toolset(version_prefix)? {
+                return Ok(Outcome::NoMatch);
+            }
+        }
+        Ok(Outcome::Match(facts.version))
+    }
+
+    fn accepts(&self, facts: &Facts<'_>) -> bool {
+        let products = self.products.is_empty()
+            || facts.product.is_some_and(|id| {
+                self.products
+                    .iter()
+                    .any(|product| product.eq_ignore_ascii_case(id))
+            });
+        let version = match &self.version_range {
+            Some(Ok(range)) => range.contains(&PackedVersion::from(facts.version)),
+            Some(Err(_)) => false,
+            None => true,
+        };
+        products && version && (self.include_prerelease || !facts.prerelease)
+    }
+}
+
+/// The index of the first of `requirements` that none of the `packages` meet.
+///
+/// The packages are read once, stopping as soon as every requirement is met.
+/// A package's version is only read if a requirement with the same id needs it.
+fn first_unmet<P>(
+    requirements: &[Requirement],
+    packages: impl IntoIterator<Item = P>,
+    id: impl Fn(&P) -> Result<BSTR, HRESULT>,
+    version: impl Fn(&P) -> Result<BSTR, HRESULT>,
+) -> Result<Option<usize>, HRESULT> {
+    let mut met = vec![false; requirements.len()];
+    let mut remaining = requirements.len();
+    for package in packages {
+        if remaining == 0 {
+            break;
+        }
+        let package_id = id(&package)?;
+        let mut package_version = None;
+        for (requirement, met) in requirements.iter().zip(&mut met) {
+            let version = || {
+                *package_version.get_or_insert_with(|| {
+                    let version = version(&package).ok()?;
+                    strings::bstr_to_string_lossy(&version).parse().ok()
+                })
+            };
+            if !*met && requirement.met_by(&package_id, version) {
+                *met = true;
+                remaining -= 1;
+            }
+        }
+    }
+    Ok(met.iter().position(|met| !met))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMMUNITY: &str = "Microsoft.VisualStudio.Product.Community";
+    const BUILD_TOOLS: &str = "Microsoft.VisualStudio.Product.BuildTools";
+
+    /// Required packages and toolsets aren't part of this as they're checked by `SetupInstance`.
+    fn filter(products: &[&str], range: Option<&str>, prerelease: bool) -> Filter {
+        Filter {
+            products: products.iter().copied().map(String::from).collect(),
+            requires: Vec::new(),
+            #[cfg(feature = "std")]
+            toolsets: Vec::new(),
+            version_range: range.map(PackedVersion::parse_range),
+            include_prerelease: prerelease,
+            include_incomplete: false,
+        }
+    }
+
+    #[test]
+    fn filters() {
+        let instance = Facts {
+            product: Some(BUILD_TOOLS),
+            version: "17.9.34723.18".parse().unwrap(),
+            prerelease: false,
+        };
+        let preview = Facts {
+            product: Some(COMMUNITY),
+            version: "17.10.35004.147".parse().unwrap(),
+            prerelease: true,
+        };
+
+        let table = [
+            (filter(&[], None, false), true, false),
+            (filter(&[], None, true), true, true),
+            (filter(&[BUILD_TOOLS], None, true), true, false),
+            (filter(&[COMMUNITY, BUILD_TOOLS], None, true), true, true),
+            (
+                filter(&["microsoft.visualstudio.product.buildtools"], None, false),
+                true,
+                false,
+            ),
+            (filter(&[], Some("[17.0,17.10)"), true), true, false),
+            (filter(&[], Some("17.10"), true), false, true),
+            (filter(&[], Some("[16.0,17.0)"), true), false, false),
+            (filter(&[], Some("not a range"), true), false, false),
+            (filter(&[BUILD_TOOLS], Some("[17.0,)"), false), true, false),
+        ];
+        for (i, (filter, expected_instance, expected_preview)) in table.iter().enumerate() {
+            assert_eq!(filter.accepts(&instance), *expected_instance, "case {i}");
+            assert_eq!(filter.accepts(&preview), *expected_preview, "case {i}");
+        }
+
+        // Instances from old installers may have no product. They're only kept if any will do.
+        let no_product = Facts {
+            product: None,
+            ..instance
+        };
+        assert!(filter(&[], None, false).accepts(&no_product));
+        assert!(!filter(&[BUILD_TOOLS], None, false).accepts(&no_product));
+    }
+
+    #[test]
+    fn requirements() {
+        use crate::hresults::{E_NOTFOUND, E_UNEXPECTED};
+        use core::cell::Cell;
+
+        const VC_X64: &str = "Microsoft.VisualStudio.Component.VC.Tools.x86.x64";
+        const VC_ARM64: &str = "Microsoft.VisualStudio.Component.VC.Tools.ARM64";
+        const SDK: &str = "Microsoft.VisualStudio.Component.Windows11SDK.22621";
+        let v = |version: &str| -> Version { version.parse().unwrap() };
+
+        // The same component can be listed more than once, e.g. for different chips.
+        let packages: &[(&str, Option<&str>)] = &[
+            (VC_X64, Some("17.7.34009.444")),
+            (SDK, None),
+            (VC_ARM64, Some("not a version")),
+            (VC_X64, Some("17.9.34511.75")),
+        ];
+        let versions_read = Cell::new(0);
+        let unmet = |requirements: &[Requirement]| {
+            first_unmet(
+                requirements,
+                packages,
+                |(id, _)| Ok(BSTR::from(*id)),
+                |(_, version)| {
+                    versions_read.set(versions_read.get() + 1);
+                    version.map(BSTR::from).ok_or(E_NOTFOUND)
+                },
+            )
+            .unwrap()
+        };
+
+        let table = [
+            (vec![], None),
+            (vec![Requirement::from(VC_X64)], None),
+            (
+                vec![Requirement::from(
+                    "microsoft.visualstudio.component.vc.tools.arm64",
+                )],
+                None,
+            ),
+            (
+                vec![Requirement::from(
+                    "Microsoft.VisualStudio.Component.VC.Tools",
+                )],
+                Some(0),
+            ),
+            (vec![Requirement::at_least(VC_X64, v("17.8"))], None),
+            (
+                vec![Requirement::at_least(VC_X64, v("17.9.34511.75"))],
+                None,
+            ),
+            (vec![Requirement::at_least(VC_X64, v("17.10"))], Some(0)),
+            // Missing and unparseable versions don't meet a minimum.
+            (vec![Requirement::at_least(SDK, v("17.0"))], Some(0)),
+            (vec![Requirement::at_least(VC_ARM64, v("0.0"))], Some(0)),
+            (
+                vec![
+                    Requirement::from(SDK),
+                    Requirement::at_least(VC_X64, v("18.0")),
+                    Requirement::from("Missing"),
+                ],
+                Some(1),
+            ),
+        ];
+        for (i, (requirements, expected)) in table.iter().enumerate() {
+            assert_eq!(unmet(requirements), *expected, "case {i}");
+        }
+
+        // Versions are only read for packages that a requirement with a version needs.
+        versions_read.set(0);
+        assert_eq!(
+            unmet(&[Requirement::from(VC_X64), Requirement::from(SDK)]),
+            None
+        );
+        assert_eq!(versions_read.get(), 0);
+        assert_eq!(unmet(&[Requirement::at_least(VC_X64, v("17.0"))]), None);
+        assert_eq!(versions_read.get(), 1);
+
+        // Reading stops once everything is met, so later errors aren't seen.
+        let failing = [Ok(VC_X64), Err(E_UNEXPECTED)];
+        let result = first_unmet(
+            &[Requirement::from(VC_X64)],
+            failing,
+            |id| id.map(BSTR::from),
+            |_| Err(E_NOTFOUND),
+        );
+        assert_eq!(result, Ok(None));
+        let result = first_unmet(
+            &[Requirement::from(SDK)],
+            failing,
+            |id| id.map(BSTR::from),
+            |_| Err(E_NOTFOUND),
+        );
+        assert_eq!(result, Err(E_UNEXPECTED));
+
+        assert_eq!(
+            alloc::format!("{}", Requirement::at_least(VC_X64, v("17.8"))),
+            alloc::format!("{VC_X64} >= 17.8.0.0")
+        );
+        assert_eq!(alloc::format!("{}", Requirement::from(SDK)), SDK);
+    }
+}
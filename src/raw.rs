@@ -1,13 +1,54 @@
+//! The raw COM interfaces wrapped by this crate, for calling anything that isn't wrapped yet.
+//!
+//! This is only public with the `unstable-raw` feature and is exempt from semver:
+//! anything here may change in any release, including patch releases.
+//!
+//! The methods are called exactly as they are in C, so it's up to the caller to check the
+//! returned `HRESULT` and to free anything returned. Interfaces are reference counted,
+//! so cloning one calls `AddRef` and dropping it calls `Release`.
+//!
+//! ```rust
+//! # #[cfg(feature = "unstable-raw")]
+//! # fn main() -> Result<(), vssetup::HRESULT> {
+//! use vssetup::raw::{ISetupConfiguration, ISetupHelper, Interface};
+//! use vssetup::{SetupConfiguration, com, wide_str};
+//!
+//! com::initialize();
+//! let setup = SetupConfiguration::new()?;
+//! // The raw interface takes over the wrapper's reference.
+//! let setup: ISetupConfiguration = unsafe { Interface::from_raw(setup.into_raw()) };
+//! let helper: ISetupHelper = setup.cast()?;
+//! let mut version = 0;
+//! let hresult = unsafe { helper.ParseVersion(wide_str!("17.9").as_ptr(), &mut version) };
+//! if hresult.is_err() {
+//!     return Err(hresult);
+//! }
+//! assert_eq!(version >> 48, 17);
+//! # Ok(()) }
+//! # #[cfg(not(feature = "unstable-raw"))]
+//! # fn main() {}
+//! ```
+
 use core::ffi::c_void;
 use core::fmt;
+use core::ops;
+use core::str::FromStr;
 
-use crate::{OkHresult, defs::*};
+#[cfg(not(feature = "unstable-raw"))]
+use crate::defs::*;
+use crate::{OkHresult, hresults::*};
 
 use windows_result::HRESULT;
 use windows_strings::BSTR;
 
+#[cfg(feature = "unstable-raw")]
+pub use crate::defs::{
+    FILETIME, GUID, IUnknown, IUnknown_Vtbl, LCID, LPCOLESTR, SAFEARRAY, SAFEARRAYBOUND, VARIANT,
+    VARIANT_BOOL, VARIANT_DATA,
+};
+
 #[repr(transparent)]
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(PartialEq, Eq, Clone, Copy)]
 pub struct InstanceState {
     value: i32,
 }
@@ -23,19 +64,189 @@ impl InstanceState {
     };
 }
 
+impl InstanceState {
+    /// The individual flags and their names, as used by [`Display`](fmt::Display) and [`FromStr`].
+    const FLAGS: [(Self, &'static str); 4] = [
+        (Self::eLocal, "Local"),
+        (Self::eRegistered, "Registered"),
+        (Self::eNoRebootRequired, "NoRebootRequired"),
+        (Self::eNoErrors, "NoErrors"),
+    ];
+
+    /// Every named flag.
+    const NAMED: Self = Self {
+        value: Self::eLocal.value
+            | Self::eRegistered.value
+            | Self::eNoRebootRequired.value
+            | Self::eNoErrors.value,
+    };
+
+    /// The state from its unsigned value, as printed by `vswhere`.
+    pub const fn from_raw(raw: u32) -> Self {
+        Self { value: raw as i32 }
+    }
+
+    /// The unsigned value, as printed by `vswhere` (e.g. `4294967295` when complete).
+    pub const fn to_raw(self) -> u32 {
+        self.value as u32
+    }
+
+    /// Whether every flag set in `other` is also set in `self`.
+    ///
+    /// ```
+    /// use vssetup::InstanceState;
+    ///
+    /// let state = InstanceState::eLocal | InstanceState::eRegistered;
+    /// assert!(state.contains(InstanceState::eLocal));
+    /// assert!(!state.contains(InstanceState::eLocal | InstanceState::eNoErrors));
+    /// assert!(InstanceState::eComplete.contains(state));
+    /// ```
+    pub const fn contains(self, other: Self) -> bool {
+        self.to_raw() & other.to_raw() == other.to_raw()
+    }
+
+    /// The named flags that are set, in the order they're declared.
+    ///
+    /// [`eComplete`](Self::eComplete) has every flag set.
+    pub fn iter_flags(self) -> impl Iterator<Item = Self> {
+        Self::FLAGS
+            .into_iter()
+            .map(|(flag, _)| flag)
+            .filter(move |flag| self.contains(*flag))
+    }
+
+    /// Whether the instance is on this machine.
+    pub const fn is_local(self) -> bool {
+        self.contains(Self::eLocal)
+    }
+
+    /// Whether the instance is registered with the installer.
+    pub const fn is_registered(self) -> bool {
+        self.contains(Self::eRegistered)
+    }
+
+    /// Whether the last install, modify or update didn't need a reboot.
+    pub const fn no_reboot_required(self) -> bool {
+        self.contains(Self::eNoRebootRequired)
+    }
+
+    /// Whether the last install, modify or update didn't fail.
+    pub const fn no_errors(self) -> bool {
+        self.contains(Self::eNoErrors)
+    }
+}
+
+impl ops::BitOr for InstanceState {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self::from_raw(self.to_raw() | rhs.to_raw())
+    }
+}
+
+impl ops::BitOrAssign for InstanceState {
+    fn bitor_assign(&mut self, rhs: Self) {
+        *self = *self | rhs;
+    }
+}
+
+impl ops::BitAnd for InstanceState {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self {
+        Self::from_raw(self.to_raw() & rhs.to_raw())
+    }
+}
+
+impl ops::BitAndAssign for InstanceState {
+    fn bitand_assign(&mut self, rhs: Self) {
+        *self = *self & rhs;
+    }
+}
+
+impl ops::Not for InstanceState {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        Self::from_raw(!self.to_raw())
+    }
+}
+
+/// Writes `None`, `Complete` or the set flags separated by ` | `, e.g. `Local | Registered`.
+/// Any unnamed bits are written last, in hex, e.g. `Local | Unknown(0x80000000)`.
+///
+/// Use [`to_raw`](InstanceState::to_raw) for the decimal form `vswhere` prints.
 impl fmt::Display for InstanceState {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         if *self == InstanceState::eNone {
-            f.write_str("None")
+            return f.write_str("None");
         } else if *self == InstanceState::eComplete {
-            f.write_str("Complete")
-        } else {
-            // TODO: Do better than a raw value
-            f.write_fmt(format_args!("Incomplete({})", self.value))
+            return f.write_str("Complete");
+        }
+        let mut separator = "";
+        for (flag, name) in Self::FLAGS {
+            if self.contains(flag) {
+                f.write_str(separator)?;
+                f.write_str(name)?;
+                separator = " | ";
+            }
         }
+        let unknown = self.to_raw() & !Self::NAMED.to_raw();
+        if unknown != 0 {
+            write!(f, "{separator}Unknown({unknown:#x})")?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes the flags as [`Display`](fmt::Display) does, e.g. `InstanceState(Local | Registered)`.
+impl fmt::Debug for InstanceState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "InstanceState({self})")
     }
 }
 
+/// Parses either the decimal form printed by `vswhere` or the form written by
+/// [`Display`](fmt::Display). Flag names and numbers may be mixed, e.g. `Local | 16`,
+/// and the spaces around `|` are optional.
+///
+/// Returns `E_INVALIDARG` for an unknown name, an empty part or a number that doesn't fit in a `u32`.
+impl FromStr for InstanceState {
+    type Err = HRESULT;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut raw = 0;
+        for part in s.split('|') {
+            let part = part.trim_matches(' ');
+            raw |= match part {
+                "None" => 0,
+                "Complete" => Self::eComplete.to_raw(),
+                _ => match Self::FLAGS.iter().find(|(_, name)| *name == part) {
+                    Some((flag, _)) => flag.to_raw(),
+                    None if is_digits(part, 10) => part.parse().map_err(|_| E_INVALIDARG)?,
+                    None => {
+                        let hex = part
+                            .strip_prefix("Unknown(0x")
+                            .and_then(|p| p.strip_suffix(')'));
+                        match hex {
+                            Some(hex) if is_digits(hex, 16) => {
+                                u32::from_str_radix(hex, 16).map_err(|_| E_INVALIDARG)?
+                            }
+                            _ => return Err(E_INVALIDARG),
+                        }
+                    }
+                },
+            };
+        }
+        Ok(Self::from_raw(raw))
+    }
+}
+
+/// Whether `s` is a non-empty string of digits in `radix`, without a sign.
+fn is_digits(s: &str, radix: u32) -> bool {
+    !s.is_empty() && s.chars().all(|c| c.is_digit(radix))
+}
+
 macro_rules! com_interface {
     ($(
         #[interface($iid:literal)]
@@ -68,13 +279,16 @@ macro_rules! com_interface {
             )*
         }
 
+        impl sealed::Sealed for $interface {}
+
         unsafe impl Interface for $interface {
             const IID: GUID = GUID::from_u128($iid);
             type Vtable = vtable::$interface;
         }
         )*
 
-        mod vtable {
+        /// The vtables of the interfaces above, with one function pointer per method.
+        pub mod vtable {
             use super::*;
             use crate::raw as interface;
             type IUnknown = IUnknown_Vtbl;
@@ -264,19 +478,44 @@ com_interface!(
 
 pub const SETUP_CONFIGURATION_CLSID: GUID = GUID::from_u128(0x177F0C4A_1CD3_4DE7_A32C_71DBBB9FA36D);
 
-pub(crate) unsafe trait Interface: Sized {
+mod sealed {
+    pub trait Sealed {}
+}
+
+impl sealed::Sealed for IUnknown {}
+
+unsafe impl Interface for IUnknown {
+    const IID: GUID = GUID::from_u128(0x00000000_0000_0000_C000_000000000046);
+    type Vtable = IUnknown_Vtbl;
+}
+
+/// A COM interface pointer.
+///
+/// # Safety
+///
+/// Implementors must be a non-null, owning pointer to a COM object implementing the interface
+/// identified by `IID`, whose vtable is `Vtable`.
+///
+/// This is sealed, so it's only implemented for the interfaces in this module.
+pub unsafe trait Interface: Sized + sealed::Sealed {
     const IID: GUID;
     type Vtable;
 
+    /// The interface pointer, without adding a reference.
+    #[inline(always)]
+    fn as_raw(&self) -> *mut c_void {
+        // SAFETY: implementors are a single COM pointer.
+        unsafe { *core::ptr::from_ref(self).cast::<*mut c_void>() }
+    }
+
+    /// The vtable and the pointer to the object, which is passed to its methods as `this`.
     #[inline(always)]
     unsafe fn vtable(&self) -> (*const *mut Self::Vtable, *mut c_void) {
-        unsafe {
-            let raw = *(core::ptr::from_ref(self).cast::<*mut c_void>());
-            let vtable = raw.cast::<*mut Self::Vtable>();
-            (vtable, raw)
-        }
+        let raw = self.as_raw();
+        (raw.cast::<*mut Self::Vtable>(), raw)
     }
 
+    /// Uses `QueryInterface` to get another interface on the same object.
     #[inline(always)]
     fn cast<I: Interface>(&self) -> Result<I, HRESULT> {
         unsafe {
@@ -289,7 +528,152 @@ pub(crate) unsafe trait Interface: Sized {
         }
     }
 
+    /// Takes over a reference to a COM pointer.
+    ///
+    /// # Safety
+    ///
+    /// `raw` must be a non-null pointer to an object implementing this interface.
     unsafe fn from_raw(raw: *mut c_void) -> Self {
         unsafe { core::mem::transmute_copy(&raw) }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    extern crate std;
+    use std::format;
+
+    #[test]
+    fn state_names() {
+        let table = [
+            (0, "None"),
+            (u32::MAX, "Complete"),
+            (1, "Local"),
+            (8, "NoErrors"),
+            (3, "Local | Registered"),
+            (15, "Local | Registered | NoRebootRequired | NoErrors"),
+            (12, "NoRebootRequired | NoErrors"),
+            (16, "Unknown(0x10)"),
+            (0x8000_0001, "Local | Unknown(0x80000000)"),
+            (
+                0xFFFF_FFFE,
+                "Registered | NoRebootRequired | NoErrors | Unknown(0xfffffff0)",
+            ),
+        ];
+        for (raw, name) in table {
+            let state = InstanceState::from_raw(raw);
+            assert_eq!(format!("{state}"), name);
+            assert_eq!(format!("{state:?}"), format!("InstanceState({name})"));
+            assert_eq!(name.parse(), Ok(state));
+        }
+        assert_eq!("4294967295".parse(), Ok(InstanceState::eComplete));
+        assert_eq!("Registered|Local".parse(), Ok(InstanceState::from_raw(3)));
+        // The form written before flags were separated by spaces.
+        assert_eq!(
+            "Local|2147483648".parse(),
+            Ok(InstanceState::from_raw(0x8000_0001))
+        );
+        assert_eq!(" Local |16".parse(), Ok(InstanceState::from_raw(17)));
+        for bad in [
+            "",
+            "|",
+            "Local|",
+            "local",
+            "4294967296",
+            "-1",
+            "+1",
+            " | ",
+            "Incomplete(1)",
+            "Unknown(16)",
+            "Unknown(0x)",
+            "Unknown(0x-1)",
+            "Unknown(0x100000000)",
+            "Local\t| Registered",
+        ] {
+            assert_eq!(bad.parse::<InstanceState>(), Err(E_INVALIDARG), "{bad}");
+        }
+    }
+
+    #[test]
+    fn state_flags() {
+        use InstanceState as S;
+        use std::vec::Vec;
+
+        let state = S::eLocal | S::eRegistered;
+        assert_eq!(state, S::from_raw(3));
+        assert!(state.contains(S::eLocal) && state.contains(S::eRegistered));
+        assert!(state.contains(state) && state.contains(S::eNone));
+        assert!(!state.contains(S::eNoErrors) && !state.contains(S::eComplete));
+        assert!(state.is_local() && state.is_registered());
+        assert!(!state.no_reboot_required() && !state.no_errors());
+        assert_eq!(state & S::eRegistered, S::eRegistered);
+        assert_eq!(state & S::eNoErrors, S::eNone);
+        assert_eq!(
+            !state & S::eComplete,
+            S::eNoRebootRequired | S::eNoErrors | S::from_raw(!15)
+        );
+        assert_eq!(
+            state.iter_flags().collect::<Vec<_>>(),
+            [S::eLocal, S::eRegistered]
+        );
+
+        let mut state = S::eNone;
+        state |= S::eNoErrors;
+        state |= S::eLocal;
+        assert_eq!(
+            state.iter_flags().collect::<Vec<_>>(),
+            [S::eLocal, S::eNoErrors]
+        );
+        state &= !S::eLocal;
+        assert_eq!(state, S::eNoErrors);
+
+        // Every bit is set when complete, including any setup may add later.
+        let complete = S::eComplete;
+        assert!(complete.is_local() && complete.is_registered());
+        assert!(complete.no_reboot_required() && complete.no_errors());
+        assert!(complete.contains(S::from_raw(0x8000_0000)));
+        assert_eq!(complete.iter_flags().count(), 4);
+        assert_eq!(!S::eNone, complete);
+        assert_eq!(!complete, S::eNone);
+        let named = S::eLocal | S::eRegistered | S::eNoRebootRequired | S::eNoErrors;
+        assert_ne!(named, complete);
+        assert!(!named.contains(complete));
+
+        assert_eq!(S::eNone.iter_flags().count(), 0);
+        assert_eq!(S::from_raw(16).iter_flags().count(), 0);
+    }
+
+    #[test]
+    fn state_round_trip() {
+        // A fixed xorshift sequence, so failures are reproducible.
+        let mut x: u32 = 0x2545_F491;
+        let random = core::iter::from_fn(|| {
+            x ^= x << 13;
+            x ^= x >> 17;
+            x ^= x << 5;
+            Some(x)
+        });
+        let edges = [
+            0,
+            1,
+            15,
+            16,
+            0x7FFF_FFFF,
+            0x8000_0000,
+            u32::MAX - 1,
+            u32::MAX,
+        ];
+        for raw in edges
+            .into_iter()
+            .chain((0..32).map(|bit| 1 << bit))
+            .chain((0..32).map(|bit| !(1 << bit)))
+            .chain(random.take(10_000))
+        {
+            let state = InstanceState::from_raw(raw);
+            assert_eq!(state.to_raw(), raw);
+            assert_eq!(format!("{}", state.to_raw()).parse(), Ok(state), "{raw}");
+            assert_eq!(format!("{state}").parse(), Ok(state), "{raw}");
+        }
+    }
+}
@@ -0,0 +1,340 @@
+//! `serde` support for the plain data types.
+//!
+//! The formats are chosen to be readable and stable:
+//!
+//! * [`FILETIME`] is its `u64` value.
+//! * [`InstanceState`] is its flags as written by `Display`, e.g. `"Local | Registered"`.
+//! * [`ProductId`] is its full id, e.g. `"Microsoft.VisualStudio.Product.Community"`.
+//! * [`InstanceId`] and [`Version`] are strings, as written by `Display`, e.g. `"17.9.34714.143"`.
+//! * [`Variant`] is tagged by its type, e.g. `{"Bool":true}`, or `"Empty"`.
+//!   Strings are converted lossily, as for [`strings::bstr_to_string_lossy`].
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use serde::de::{self, Deserializer, Visitor};
+use serde::ser::Serializer;
+use serde::{Deserialize, Serialize};
+
+use crate::{BSTR, FILETIME, InstanceId, InstanceState, ProductId, Variant, Version, strings};
+
+impl Serialize for FILETIME {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(self.as_u64())
+    }
+}
+
+impl<'de> Deserialize<'de> for FILETIME {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = u64::deserialize(deserializer)?;
+        Ok(Self {
+            dwLowDateTime: value as u32,
+            dwHighDateTime: (value >> 32) as u32,
+        })
+    }
+}
+
+impl Serialize for InstanceState {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Also accepts the decimal form printed by `vswhere`, as a string or a number.
+impl<'de> Deserialize<'de> for InstanceState {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct StateVisitor;
+
+        impl Visitor<'_> for StateVisitor {
+            type Value = InstanceState;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("instance state flags, e.g. `Local | Registered`, or a u32")
+            }
+
+            fn visit_str<E: de::Error>(self, s: &str) -> Result<Self::Value, E> {
+                s.parse()
+                    .map_err(|_| E::invalid_value(de::Unexpected::Str(s), &self))
+            }
+
+            fn visit_u64<E: de::Error>(self, raw: u64) -> Result<Self::Value, E> {
+                let raw = u32::try_from(raw)
+                    .map_err(|_| E::invalid_value(de::Unexpected::Unsigned(raw), &self))?;
+                Ok(InstanceState::from_raw(raw))
+            }
+        }
+
+        deserializer.deserialize_any(StateVisitor)
+    }
+}
+
+impl Serialize for ProductId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.full_id())
+    }
+}
+
+impl<'de> Deserialize<'de> for ProductId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let id = String::deserialize(deserializer)?;
+        Ok(Self::from(id.as_str()))
+    }
+}
+
+impl Serialize for InstanceId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for InstanceId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let id = String::deserialize(deserializer)?;
+        id.parse()
+            .map_err(|_| de::Error::invalid_value(de::Unexpected::Str(&id), &"an instance id"))
+    }
+}
+
+impl Serialize for Version {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Version {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let version = String::deserialize(deserializer)?;
+        version.parse().map_err(|_| {
+            de::Error::invalid_value(de::Unexpected::Str(&version), &"a dotted version")
+        })
+    }
+}
+
+/// The serialized form of a [`Variant`], with the string converted to UTF-8.
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "Variant")]
+enum Repr {
+    Bstr(String),
+    Bool(bool),
+    Signed(i64),
+    Unsigned(u64),
+    StringArray(Vec<String>),
+    FileTime(FILETIME),
+    Date(f64),
+    Empty,
+    Null,
+    Unknown,
+}
+
+impl Serialize for Variant {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let repr = match self {
+            Self::Bstr(bstr) => Repr::Bstr(strings::bstr_to_string_lossy(bstr)),
+            Self::Bool(bool) => Repr::Bool(*bool),
+            Self::Signed(i64) => Repr::Signed(*i64),
+            Self::Unsigned(u64) => Repr::Unsigned(*u64),
+            Self::StringArray(array) => Repr::StringArray(
+                array
+                    .iter()
+                    .map(|b| strings::bstr_to_string_lossy(b))
+                    .collect(),
+            ),
+            Self::FileTime(filetime) => Repr::FileTime(*filetime),
+            Self::Date(f64) => Repr::Date(*f64),
+            Self::Empty => Repr::Empty,
+            Self::Null => Repr::Null,
+            Self::Unknown => Repr::Unknown,
+        };
+        repr.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Variant {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(match Repr::deserialize(deserializer)? {
+            Repr::Bstr(string) => Self::Bstr(BSTR::from(string.as_str())),
+            Repr::Bool(bool) => Self::Bool(bool),
+            Repr::Signed(i64) => Self::Signed(i64),
+            Repr::Unsigned(u64) => Self::Unsigned(u64),
+            Repr::StringArray(array) => {
+                Self::StringArray(array.iter().map(|s| BSTR::from(s.as_str())).collect())
+            }
+            Repr::FileTime(filetime) => Self::FileTime(filetime),
+            Repr::Date(f64) => Self::Date(f64),
+            Repr::Empty => Self::Empty,
+            Repr::Null => Self::Null,
+            Repr::Unknown => Self::Unknown,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::{format, vec};
+
+    fn round_trip<T>(value: &T, json: &str) -> T
+    where
+        T: Serialize + for<'de> Deserialize<'de>,
+    {
+        assert_eq!(serde_json::to_string(value).unwrap(), json);
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn filetime() {
+        let time = FILETIME {
+            dwLowDateTime: 0x89ABCDEF,
+            dwHighDateTime: 0x01234567,
+        };
+        let back = round_trip(&time, "81985529216486895");
+        assert_eq!(back.as_u64(), time.as_u64());
+    }
+
+    #[test]
+    fn instance_state() {
+        let table = [
+            (InstanceState::eNone, r#""None""#),
+            (InstanceState::eComplete, r#""Complete""#),
+            (InstanceState::from_raw(1 | 2), r#""Local | Registered""#),
+            (
+                InstanceState::from_raw(8 | 32),
+                r#""NoErrors | Unknown(0x20)""#,
+            ),
+        ];
+        for (state, json) in table {
+            assert_eq!(round_trip(&state, json), state);
+        }
+        let parse = serde_json::from_str::<InstanceState>;
+        assert_eq!(parse("4294967295").unwrap(), InstanceState::eComplete);
+        assert_eq!(parse(r#""3""#).unwrap(), InstanceState::from_raw(3));
+        assert_eq!(
+            parse(r#""Local|Registered""#).unwrap(),
+            InstanceState::from_raw(3)
+        );
+        assert!(parse(r#""Remote""#).is_err());
+        assert!(parse("4294967296").is_err());
+        assert!(parse("-1").is_err());
+    }
+
+    #[test]
+    fn product_id() {
+        let table = [
+            (
+                ProductId::BuildTools,
+                r#""Microsoft.VisualStudio.Product.BuildTools""#,
+            ),
+            (ProductId::Other("Example".into()), r#""Example""#),
+        ];
+        for (product, json) in table {
+            assert_eq!(round_trip(&product, json), product);
+        }
+    }
+
+    #[test]
+    fn ids_and_versions() {
+        let id: InstanceId = "C0FFEE42".parse().unwrap();
+        assert_eq!(round_trip(&id, r#""c0ffee42""#), id);
+        assert!(serde_json::from_str::<InstanceId>(r#""c0ffee 42""#).is_err());
+
+        let version = Version::new(17, 9, 34714, 143);
+        assert_eq!(round_trip(&version, r#""17.9.34714.143""#), version);
+        assert_eq!(
+            serde_json::from_str::<Version>(r#""17.10""#).unwrap(),
+            Version::new(17, 10, 0, 0)
+        );
+        assert!(serde_json::from_str::<Version>(r#""seventeen""#).is_err());
+    }
+
+    #[test]
+    fn variant() {
+        let table = vec![
+            (
+                Variant::Bstr(BSTR::from("Überall 🦀")),
+                r#"{"Bstr":"Überall 🦀"}"#,
+            ),
+            (Variant::Bool(true), r#"{"Bool":true}"#),
+            (Variant::Signed(-42), r#"{"Signed":-42}"#),
+            (
+                Variant::Unsigned(u64::MAX),
+                r#"{"Unsigned":18446744073709551615}"#,
+            ),
+            (
+                Variant::StringArray(vec![BSTR::from("a"), BSTR::from("b")]),
+                r#"{"StringArray":["a","b"]}"#,
+            ),
+            (Variant::StringArray(Vec::new()), r#"{"StringArray":[]}"#),
+            (
+                Variant::FileTime(FILETIME {
+                    dwLowDateTime: 1,
+                    dwHighDateTime: 1,
+                }),
+                r#"{"FileTime":4294967297}"#,
+            ),
+            (Variant::Date(45308.5), r#"{"Date":45308.5}"#),
+            (Variant::Empty, r#""Empty""#),
+            (Variant::Null, r#""Null""#),
+            (Variant::Unknown, r#""Unknown""#),
+        ];
+        for (variant, json) in table {
+            let back = round_trip(&variant, json);
+            assert_eq!(format!("{back:?}"), format!("{variant:?}"));
+        }
+        assert!(serde_json::from_str::<Variant>(r#"{"Float":1.5}"#).is_err());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn instance_snapshot() {
+        use crate::info::{InstanceSnapshot, PackageInfo};
+
+        let snapshot = InstanceSnapshot {
+            instance_id: "c0ffee42".parse().unwrap(),
+            display_name: "Visual Studio Community".into(),
+            description: "Überall 🦀".into(),
+            installation_name: "VisualStudio/17.9.5+34714.143".into(),
+            installation_path: r"C:\VS".into(),
+            installation_version: Version::new(17, 9, 34714, 143),
+            install_date: 133_000_000_000_000_000,
+            state: InstanceState::eComplete,
+            is_launchable: true,
+            is_complete: true,
+            product_id: Some(ProductId::Community),
+            packages: Some(vec![PackageInfo {
+                id: "Microsoft.VisualStudio.Component.VC.Tools.x86.x64".into(),
+                version: "17.9.34511.75".into(),
+                chip: "x64".into(),
+                language: String::new(),
+                branch: String::new(),
+                kind: "Component".into(),
+                unique_id:
+                    "Microsoft.VisualStudio.Component.VC.Tools.x86.x64,version=17.9.34511.75".into(),
+                is_extension: false,
+            }]),
+        };
+        let json = serde_json::to_string(&snapshot).unwrap();
+        assert!(json.contains(r#""instance_id":"c0ffee42""#), "{json}");
+        assert!(
+            json.contains(r#""installation_version":"17.9.34714.143""#),
+            "{json}"
+        );
+        assert!(json.contains(r#""state":"Complete""#), "{json}");
+        assert!(
+            json.contains(r#""product_id":"Microsoft.VisualStudio.Product.Community""#),
+            "{json}"
+        );
+        let back: InstanceSnapshot = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, snapshot);
+
+        let snapshot = InstanceSnapshot {
+            packages: None,
+            ..snapshot
+        };
+        let json = serde_json::to_string(&snapshot).unwrap();
+        assert_eq!(
+            serde_json::from_str::<InstanceSnapshot>(&json).unwrap(),
+            snapshot
+        );
+    }
+}
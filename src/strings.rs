@@ -0,0 +1,272 @@
+//! Converting the strings returned by the setup API into Rust strings.
+//!
+//! These are the only places in this crate where UTF-16 is converted.
+//! Each function takes the full contents of a [`BSTR`](crate::BSTR),
+//! which can be passed directly as `&bstr`.
+//!
+//! A `BSTR` is length-prefixed so the whole string is always converted,
+//! including any interior nulls. Nothing is truncated at the first null.
+//!
+//! Strings passed *to* the setup API are encoded here too, see `with_wide`.
+
+use alloc::string::{FromUtf16Error, String};
+use alloc::vec::Vec;
+
+use crate::{E_INVALIDARG, HRESULT, WideStr};
+
+/// Converts to a `String`, replacing invalid UTF-16 (i.e. unpaired surrogates) with
+/// [`U+FFFD REPLACEMENT CHARACTER`](char::REPLACEMENT_CHARACTER).
+pub fn bstr_to_string_lossy(bstr: &[u16]) -> String {
+    String::from_utf16_lossy(bstr)
+}
+
+/// Converts to a `String`, failing if the string is not valid UTF-16 (i.e. contains unpaired surrogates).
+pub fn bstr_to_string_checked(bstr: &[u16]) -> Result<String, FromUtf16Error> {
+    String::from_utf16(bstr)
+}
+
+/// Converts to an `OsString` without any loss, even if the string is not valid UTF-16.
+#[cfg(feature = "std")]
+pub fn bstr_to_os_string(bstr: &[u16]) -> std::ffi::OsString {
+    use std::os::windows::ffi::OsStringExt;
+    std::ffi::OsString::from_wide(bstr)
+}
+
+/// Converts to a `PathBuf` without any loss, even if the path is not valid UTF-16.
+#[cfg(feature = "std")]
+pub fn bstr_to_pathbuf(bstr: &[u16]) -> std::path::PathBuf {
+    std::path::PathBuf::from(bstr_to_os_string(bstr))
+}
+
+/// The UTF-16 units of an `OsStr`, without any loss.
+#[cfg(feature = "std")]
+pub(crate) fn os_str_to_wide(s: &std::ffi::OsStr) -> impl Iterator<Item = u16> + '_ {
+    use std::os::windows::ffi::OsStrExt;
+    s.encode_wide()
+}
+
+/// Uppercases UTF-16 one character at a time, keeping unpaired surrogates as they are.
+///
+/// A character whose uppercase form is more than one character (e.g. `ß`) is left alone,
+/// so every character maps to exactly one other.
+#[cfg(feature = "std")]
+pub(crate) fn to_uppercase(units: &[u16]) -> Vec<u16> {
+    let mut upper = Vec::with_capacity(units.len());
+    for c in char::decode_utf16(units.iter().copied()) {
+        match c {
+            Ok(c) => {
+                let mut chars = c.to_uppercase();
+                let c = match (chars.next(), chars.next()) {
+                    (Some(u), None) => u,
+                    _ => c,
+                };
+                upper.extend_from_slice(c.encode_utf16(&mut [0; 2]));
+            }
+            Err(e) => upper.push(e.unpaired_surrogate()),
+        }
+    }
+    upper
+}
+
+/// Compares two UTF-16 strings ignoring ASCII case, as setup does for ids, without converting either.
+pub(crate) fn eq_ignore_ascii_case(a: &[u16], b: &[u16]) -> bool {
+    fn fold(unit: u16) -> u16 {
+        match u8::try_from(unit) {
+            Ok(byte) => u16::from(byte.to_ascii_lowercase()),
+            Err(_) => unit,
+        }
+    }
+    a.len() == b.len() && a.iter().zip(b).all(|(&a, &b)| fold(a) == fold(b))
+}
+
+/// A `BSTR` holding exactly these units, which may not be valid UTF-16.
+#[cfg(test)]
+pub(crate) fn bstr_from_units(units: &[u16]) -> crate::BSTR {
+    crate::BSTR::from_wide(units)
+}
+
+/// Strings shorter than this are encoded on the stack.
+pub(crate) const STACK_LEN: usize = 64;
+
+/// Encode UTF-16 units as a null-terminated [`WideStr`] and pass it to `f`.
+///
+/// Short strings are encoded on the stack, anything longer is copied to the heap.
+/// Returns `E_INVALIDARG` if there's an interior null, as the string would otherwise be truncated.
+pub(crate) fn with_wide<R>(
+    units: impl IntoIterator<Item = u16>,
+    f: impl FnOnce(WideStr<'_>) -> Result<R, HRESULT>,
+) -> Result<R, HRESULT> {
+    let mut stack = [0; STACK_LEN];
+    let mut heap = Vec::new();
+    let wide = encode(units, &mut stack, &mut heap)?;
+    // SAFETY: `encode` always null terminates.
+    f(unsafe { WideStr::from_slice_with_nul_unchecked(wide) })
+}
+
+/// Encode into `stack` if it fits (including the null), otherwise into `heap`.
+pub(crate) fn encode<'a>(
+    units: impl IntoIterator<Item = u16>,
+    stack: &'a mut [u16; STACK_LEN],
+    heap: &'a mut Vec<u16>,
+) -> Result<&'a [u16], HRESULT> {
+    let mut len = 0;
+    for unit in units {
+        if unit == 0 {
+            return Err(E_INVALIDARG);
+        }
+        if len < STACK_LEN - 1 {
+            stack[len] = unit;
+        } else {
+            if heap.is_empty() {
+                heap.extend_from_slice(&stack[..len]);
+            }
+            heap.push(unit);
+        }
+        len += 1;
+    }
+    if heap.is_empty() {
+        stack[len] = 0;
+        Ok(&stack[..=len])
+    } else {
+        heap.push(0);
+        Ok(heap)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    extern crate std;
+    use std::vec::Vec;
+
+    fn wide(s: &str) -> Vec<u16> {
+        s.encode_utf16().collect()
+    }
+
+    #[test]
+    fn valid() {
+        let s = "C:\\Program Files\\Microsoft Visual Studio\\2022\\Community 🦀";
+        assert_eq!(bstr_to_string_lossy(&wide(s)), s);
+        assert_eq!(bstr_to_string_checked(&wide(s)).unwrap(), s);
+        assert_eq!(bstr_to_string_lossy(&[]), "");
+    }
+
+    #[test]
+    fn interior_nulls() {
+        let s = "a\0b\0";
+        assert_eq!(bstr_to_string_lossy(&wide(s)), s);
+        assert_eq!(bstr_to_string_checked(&wide(s)).unwrap(), s);
+    }
+
+    #[test]
+    fn unpaired_surrogates() {
+        let lone_high = [u16::from(b'a'), 0xD800, u16::from(b'b')];
+        let lone_low = [0xDC00, u16::from(b'a')];
+        assert_eq!(bstr_to_string_lossy(&lone_high), "a\u{FFFD}b");
+        assert_eq!(bstr_to_string_lossy(&lone_low), "\u{FFFD}a");
+        assert!(bstr_to_string_checked(&lone_high).is_err());
+        assert!(bstr_to_string_checked(&lone_low).is_err());
+    }
+
+    #[test]
+    fn ignoring_ascii_case() {
+        let table = [
+            (
+                "Microsoft.VisualStudio.Workload.VCTools",
+                "microsoft.visualstudio.workload.vctools",
+                true,
+            ),
+            ("VCTools", "VCTOOLS", true),
+            ("", "", true),
+            ("VCTools", "VCTool", false),
+            ("VCTool", "VCTools", false),
+            ("a@", "A`", false),
+            // Only ASCII is folded.
+            ("é", "É", false),
+            ("产品", "产品", true),
+        ];
+        for (a, b, expected) in table {
+            assert_eq!(
+                eq_ignore_ascii_case(&wide(a), &wide(b)),
+                expected,
+                "{a} {b}"
+            );
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn os_string_is_lossless() {
+        use std::os::windows::ffi::OsStrExt;
+        let wide = [u16::from(b'C'), u16::from(b':'), 0xD800, 0, u16::from(b'x')];
+        let os = bstr_to_os_string(&wide);
+        assert_eq!(os.encode_wide().collect::<Vec<u16>>(), wide);
+        let path = bstr_to_pathbuf(&wide);
+        assert_eq!(path.as_os_str().encode_wide().collect::<Vec<u16>>(), wide);
+    }
+
+    fn encoded(s: &str) -> Result<Vec<u16>, HRESULT> {
+        let mut stack = [0; STACK_LEN];
+        let mut heap = Vec::new();
+        encode(s.encode_utf16(), &mut stack, &mut heap).map(<[u16]>::to_vec)
+    }
+
+    #[test]
+    fn encoding() {
+        let long = "n".repeat(300);
+        let cjk = "产品.显示名称";
+        let emoji = "🦀 name 🦀";
+        let mut names = std::vec![long.as_str(), cjk, emoji, ""];
+        // Either side of the point where the stack buffer (including the null) is full.
+        let sizes: Vec<String> = [STACK_LEN - 2, STACK_LEN - 1, STACK_LEN, STACK_LEN + 1]
+            .iter()
+            .map(|&len| "x".repeat(len))
+            .collect();
+        names.extend(sizes.iter().map(String::as_str));
+        // A surrogate pair straddling the end of the stack buffer.
+        let straddle = std::format!("{}🦀", "x".repeat(STACK_LEN - 2));
+        names.push(&straddle);
+
+        for name in names {
+            let mut expected = wide(name);
+            expected.push(0);
+            assert_eq!(encoded(name), Ok(expected), "{name}");
+        }
+    }
+
+    #[test]
+    fn encoding_interior_null() {
+        assert_eq!(encoded("a\0b"), Err(E_INVALIDARG));
+        assert_eq!(
+            encoded(&std::format!("{}\0", "x".repeat(300))),
+            Err(E_INVALIDARG)
+        );
+    }
+
+    /// All conversions should go through this module.
+    #[cfg(feature = "std")]
+    #[test]
+    fn no_conversions_elsewhere() {
+        let src = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+        for entry in std::fs::read_dir(src).unwrap() {
+            let path = entry.unwrap().path();
+            if path.file_name().is_some_and(|name| name == "strings.rs") {
+                continue;
+            }
+            let source = std::fs::read_to_string(&path).unwrap();
+            for pattern in [
+                ".to_string()",
+                "from_utf16",
+                "from_wide(",
+                ".encode_wide()",
+                "decode_utf16",
+            ] {
+                assert!(
+                    !source.contains(pattern),
+                    "{} uses `{pattern}`, use the `strings` module instead",
+                    path.display()
+                );
+            }
+        }
+    }
+}
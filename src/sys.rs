@@ -0,0 +1,79 @@
+//! Every function this crate imports from Windows, each declared exactly once.
+
+use core::ffi::c_void;
+
+use windows_result::HRESULT;
+
+use crate::defs::{GUID, SAFEARRAY};
+
+pub(crate) type HKEY = *mut c_void;
+
+/// Import a COM function from `combase.dll`.
+/// Windows 7 doesn't have `combase.dll` so these are imported from `ole32.dll` there instead.
+macro_rules! com_link {
+    (fn $($function:tt)*) => {
+        #[cfg(not(target_vendor = "win7"))]
+        windows_link::link!("combase.dll" "system" fn $($function)*);
+        #[cfg(target_vendor = "win7")]
+        windows_link::link!("ole32.dll" "system" fn $($function)*);
+    };
+}
+
+com_link!(fn CoInitializeEx(pvReserved: *const (), dwCoInit: u32) -> HRESULT);
+com_link!(fn CoUnInitialize());
+// Use CoIncrementMTA on win8+?
+com_link!(fn CoCreateInstance(
+    rclsid: *const GUID,
+    pUnkOuter: *mut c_void,
+    dwClsContext: u32,
+    riid: *const GUID,
+    ppv: *mut *mut c_void,
+) -> HRESULT);
+
+windows_link::link!("oleaut32.dll" "system" fn SafeArrayLock(psa: *const SAFEARRAY) -> HRESULT);
+windows_link::link!("oleaut32.dll" "system" fn SafeArrayUnlock(psa: *const SAFEARRAY) -> HRESULT);
+windows_link::link!("oleaut32.dll" "system" fn SafeArrayDestroy(psa: *const SAFEARRAY) -> HRESULT);
+// Only the tests create arrays.
+#[cfg(test)]
+windows_link::link!("oleaut32.dll" "system" fn SafeArrayCreateVector(vt: u16, lbound: i32, elements: u32) -> *mut SAFEARRAY);
+
+windows_link::link!("advapi32.dll" "system" fn RegOpenKeyExW(hkey: HKEY, lpsubkey: *const u16, uloptions: u32, samdesired: u32, phkresult: *mut HKEY) -> u32);
+windows_link::link!("advapi32.dll" "system" fn RegQueryValueExW(hkey: HKEY, lpvaluename: *const u16, lpreserved: *mut u32, lptype: *mut u32, lpdata: *mut u8, lpcbdata: *mut u32) -> u32);
+windows_link::link!("advapi32.dll" "system" fn RegCloseKey(hkey: HKEY) -> u32);
+
+windows_link::link!("kernel32.dll" "system" fn LocaleNameToLCID(lpname: *const u16, dwflags: u32) -> u32);
+windows_link::link!("kernel32.dll" "system" fn GetUserDefaultUILanguage() -> u16);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::ptr::{null, null_mut};
+
+    /// Makes sure every import resolves when the test binary loads,
+    /// rather than in a user's build.
+    #[test]
+    fn smoke() {
+        let imports = [
+            CoInitializeEx as *const (),
+            CoUnInitialize as *const (),
+            CoCreateInstance as *const (),
+            SafeArrayLock as *const (),
+            SafeArrayUnlock as *const (),
+            SafeArrayDestroy as *const (),
+            SafeArrayCreateVector as *const (),
+            RegOpenKeyExW as *const (),
+            RegQueryValueExW as *const (),
+            RegCloseKey as *const (),
+            LocaleNameToLCID as *const (),
+            GetUserDefaultUILanguage as *const (),
+        ];
+        assert!(imports.iter().all(|import| !import.is_null()));
+
+        // Calls that can only fail and have no side effects.
+        unsafe {
+            assert!(SafeArrayLock(null()).is_err());
+            assert!(SafeArrayUnlock(null()).is_err());
+            assert_ne!(RegCloseKey(null_mut()), 0);
+        }
+    }
+}
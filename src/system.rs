@@ -0,0 +1,215 @@
+//! Checks on the state of the machine, separate from any Visual Studio instance.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ptr::null_mut as null;
+
+use crate::sys::{HKEY, RegCloseKey, RegOpenKeyExW, RegQueryValueExW};
+use crate::{E_UNEXPECTED, HRESULT, strings};
+
+/// Whether Windows has a reboot pending, e.g. from a previous install or an update.
+///
+/// The Visual Studio installer fails late, and with a confusing error, if it is started while
+/// a reboot is pending. This checks the documented indicators:
+///
+/// - the Component Based Servicing `RebootPending` key
+/// - the Windows Update `RebootRequired` key
+/// - any `PendingFileRenameOperations` in the Session Manager
+pub fn reboot_pending() -> Result<bool, HRESULT> {
+    reboot_pending_in(&LocalMachine)
+}
+
+/// The machine's `MachineGuid`, set when Windows is installed, or `None` if it isn't set.
+///
+/// This identifies the machine to anyone who can also read it, so think before sharing it.
+/// [`key::unique_key`](crate::key::unique_key) can use it to scope keys to the machine.
+pub fn machine_guid() -> Result<Option<String>, HRESULT> {
+    let Some(key) = Key::open(CRYPTOGRAPHY)? else {
+        return Ok(None);
+    };
+    Ok(key
+        .query_string(MACHINE_GUID)?
+        .map(|guid| strings::bstr_to_string_lossy(&guid)))
+}
+
+const CRYPTOGRAPHY: &str = r"SOFTWARE\Microsoft\Cryptography";
+const MACHINE_GUID: &str = "MachineGuid";
+const CBS_REBOOT_PENDING: &str =
+    r"SOFTWARE\Microsoft\Windows\CurrentVersion\Component Based Servicing\RebootPending";
+const WU_REBOOT_REQUIRED: &str =
+    r"SOFTWARE\Microsoft\Windows\CurrentVersion\WindowsUpdate\Auto Update\RebootRequired";
+const SESSION_MANAGER: &str = r"SYSTEM\CurrentControlSet\Control\Session Manager";
+const PENDING_FILE_RENAMES: &str = "PendingFileRenameOperations";
+
+/// The two questions [`reboot_pending`] asks of `HKEY_LOCAL_MACHINE`.
+pub(crate) trait Registry {
+    fn key_exists(&self, key: &str) -> Result<bool, HRESULT>;
+    /// The size in bytes of a value's data, or `None` if either the key or value doesn't exist.
+    fn value_size(&self, key: &str, value: &str) -> Result<Option<u32>, HRESULT>;
+}
+
+pub(crate) fn reboot_pending_in(registry: &impl Registry) -> Result<bool, HRESULT> {
+    if registry.key_exists(CBS_REBOOT_PENDING)? || registry.key_exists(WU_REBOOT_REQUIRED)? {
+        return Ok(true);
+    }
+    // An empty `REG_MULTI_SZ` is one or two nulls.
+    let renames = registry.value_size(SESSION_MANAGER, PENDING_FILE_RENAMES)?;
+    Ok(renames.is_some_and(|size| size > 4))
+}
+
+struct LocalMachine;
+
+impl Registry for LocalMachine {
+    fn key_exists(&self, key: &str) -> Result<bool, HRESULT> {
+        Ok(Key::open(key)?.is_some())
+    }
+
+    fn value_size(&self, key: &str, value: &str) -> Result<Option<u32>, HRESULT> {
+        let Some(key) = Key::open(key)? else {
+            return Ok(None);
+        };
+        let mut size = 0;
+        let status = strings::with_wide(value.encode_utf16(), |value| unsafe {
+            Ok(RegQueryValueExW(
+                key.0,
+                value.as_ptr(),
+                null(),
+                null(),
+                null(),
+                &mut size,
+            ))
+        })?;
+        match status {
+            ERROR_SUCCESS => Ok(Some(size)),
+            ERROR_FILE_NOT_FOUND => Ok(None),
+            error => Err(HRESULT::from_win32(error)),
+        }
+    }
+}
+
+/// An open registry key, closed on drop.
+struct Key(HKEY);
+
+impl Key {
+    /// Open a key under `HKEY_LOCAL_MACHINE`, returning `None` if it doesn't exist.
+    fn open(key: &str) -> Result<Option<Self>, HRESULT> {
+        let mut handle = null();
+        let status = strings::with_wide(key.encode_utf16(), |key| unsafe {
+            Ok(RegOpenKeyExW(
+                HKEY_LOCAL_MACHINE,
+                key.as_ptr(),
+                0,
+                KEY_READ | KEY_WOW64_64KEY,
+                &mut handle,
+            ))
+        })?;
+        match status {
+            ERROR_SUCCESS => Ok(Some(Self(handle))),
+            ERROR_FILE_NOT_FOUND => Ok(None),
+            error => Err(HRESULT::from_win32(error)),
+        }
+    }
+
+    /// Read a `REG_SZ` value, without its null terminator.
+    fn query_string(&self, value: &str) -> Result<Option<Vec<u16>>, HRESULT> {
+        strings::with_wide(value.encode_utf16(), |value| {
+            let mut data = Vec::<u16>::new();
+            loop {
+                let mut kind = 0;
+                let mut size = (data.len() * 2) as u32;
+                let status = unsafe {
+                    RegQueryValueExW(
+                        self.0,
+                        value.as_ptr(),
+                        null(),
+                        &mut kind,
+                        if data.is_empty() {
+                            null()
+                        } else {
+                            data.as_mut_ptr().cast()
+                        },
+                        &mut size,
+                    )
+                };
+                match status {
+                    // Asking with an empty buffer reports success along with the size needed.
+                    ERROR_SUCCESS | ERROR_MORE_DATA if (size as usize).div_ceil(2) > data.len() => {
+                        data.resize((size as usize).div_ceil(2), 0);
+                    }
+                    ERROR_SUCCESS if kind == REG_SZ => {
+                        data.truncate(size as usize / 2);
+                        while data.last() == Some(&0) {
+                            data.pop();
+                        }
+                        return Ok(Some(data));
+                    }
+                    ERROR_SUCCESS => return Err(E_UNEXPECTED),
+                    ERROR_FILE_NOT_FOUND => return Ok(None),
+                    error => return Err(HRESULT::from_win32(error)),
+                }
+            }
+        })
+    }
+}
+
+impl Drop for Key {
+    fn drop(&mut self) {
+        unsafe {
+            RegCloseKey(self.0);
+        }
+    }
+}
+
+const HKEY_LOCAL_MACHINE: HKEY = 0x80000002_u32 as i32 as isize as HKEY;
+const KEY_READ: u32 = 0x20019;
+const KEY_WOW64_64KEY: u32 = 0x0100;
+const ERROR_SUCCESS: u32 = 0;
+const ERROR_FILE_NOT_FOUND: u32 = 2;
+const ERROR_MORE_DATA: u32 = 234;
+const REG_SZ: u32 = 1;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake_store::FakeStore;
+
+    /// Keys, or values of keys, with the size of the value's data.
+    type FakeRegistry<'a> = FakeStore<'a, (&'static str, Option<&'static str>), u32>;
+
+    impl Registry for FakeRegistry<'_> {
+        fn key_exists(&self, key: &str) -> Result<bool, HRESULT> {
+            Ok(self.find(|&(k, _)| k == key)?.is_some())
+        }
+
+        fn value_size(&self, key: &str, value: &str) -> Result<Option<u32>, HRESULT> {
+            assert_eq!((key, value), (SESSION_MANAGER, PENDING_FILE_RENAMES));
+            Ok(self.find(|&(k, v)| k == key && v == Some(value))?.copied())
+        }
+    }
+
+    #[test]
+    fn indicators() {
+        let renames = |size| [((SESSION_MANAGER, Some(PENDING_FILE_RENAMES)), Ok(size))];
+        let table: [(&[_], bool); 6] = [
+            (&[], false),
+            (&[((CBS_REBOOT_PENDING, None), Ok(0))], true),
+            (&[((WU_REBOOT_REQUIRED, None), Ok(0))], true),
+            (&renames(2), false),
+            (&renames(4), false),
+            (&renames(96), true),
+        ];
+        for (i, (entries, expected)) in table.into_iter().enumerate() {
+            assert_eq!(
+                reboot_pending_in(&FakeStore(entries)),
+                Ok(expected),
+                "case {i}"
+            );
+        }
+    }
+
+    #[test]
+    fn errors_are_reported() {
+        let registry = FakeStore(&[((CBS_REBOOT_PENDING, None), Err(E_UNEXPECTED))]);
+        assert_eq!(reboot_pending_in(&registry), Err(E_UNEXPECTED));
+    }
+}
@@ -0,0 +1,36 @@
+//! A scratch directory for tests that need real files.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// A directory under the system temp directory that's removed, with its contents, when dropped.
+///
+/// Each one gets a new name so tests running in parallel, or in other processes, don't collide.
+pub(crate) struct TempDir(PathBuf);
+
+impl TempDir {
+    pub(crate) fn new(name: &str) -> Self {
+        static NEXT: AtomicU32 = AtomicU32::new(0);
+        let unique = NEXT.fetch_add(1, Ordering::Relaxed);
+        let path = std::env::temp_dir().join(std::format!(
+            "vssetup-{name}-test-{}-{unique}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+}
+
+impl core::ops::Deref for TempDir {
+    type Target = Path;
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        // Don't panic while a failed test is already unwinding.
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
@@ -0,0 +1,107 @@
+//! Matching MSVC toolset versions, as used by `-vcvars_ver`.
+
+use std::path::Path;
+
+use crate::integrate::{COMPONENT_HOSTS, COMPONENT_PREFIX};
+
+/// Whether a dotted version starts with the components of `prefix`.
+///
+/// Whole components must match so `14.29` matches `14.29.30133` but `14.2` does not.
+pub(crate) fn version_matches(prefix: &str, version: &str) -> bool {
+    if prefix.is_empty() {
+        return false;
+    }
+    let mut version = version.split('.');
+    prefix
+        .split('.')
+        .all(|wanted| version.next().is_some_and(|part| part == wanted))
+}
+
+/// Whether a component id is a side-by-side toolset for the given version.
+///
+/// E.g. `Microsoft.VisualStudio.Component.VC.14.29.16.11.x86.x64` is a `14.29` toolset.
+pub(crate) fn is_toolset_component(id: &str, prefix: &str) -> bool {
+    let Some(rest) = id.strip_prefix(COMPONENT_PREFIX) else {
+        return false;
+    };
+    let Some(version) = COMPONENT_HOSTS
+        .iter()
+        .find_map(|host| rest.strip_suffix(host))
+    else {
+        return false;
+    };
+    version.starts_with(|c: char| c.is_ascii_digit()) && version_matches(prefix, version)
+}
+
+/// Whether the `VC\Tools\MSVC` directory contains a toolset for the given version.
+///
+/// Any errors reading the directory are treated as no match.
+pub(crate) fn dir_has_toolset(msvc_dir: &Path, prefix: &str) -> bool {
+    let Ok(entries) = std::fs::read_dir(msvc_dir) else {
+        return false;
+    };
+    entries.flatten().any(|entry| {
+        entry.file_type().is_ok_and(|kind| kind.is_dir())
+            && entry
+                .file_name()
+                .to_str()
+                .is_some_and(|name| version_matches(prefix, name))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp_dir::TempDir;
+
+    #[test]
+    fn prefixes() {
+        assert!(version_matches("14.29", "14.29.30133"));
+        assert!(version_matches("14.29.30133", "14.29.30133"));
+        assert!(version_matches("14", "14.29.30133"));
+        assert!(!version_matches("14.2", "14.29.30133"));
+        assert!(!version_matches("14.29.30133.1", "14.29.30133"));
+        assert!(!version_matches("14.30", "14.29.30133"));
+        assert!(!version_matches("", "14.29.30133"));
+    }
+
+    #[test]
+    fn components() {
+        let table = [
+            (
+                "Microsoft.VisualStudio.Component.VC.14.29.16.11.x86.x64",
+                true,
+            ),
+            (
+                "Microsoft.VisualStudio.Component.VC.14.29.16.11.ARM64",
+                true,
+            ),
+            ("Microsoft.VisualStudio.Component.VC.14.29.x86.x64", true),
+            ("Microsoft.VisualStudio.Component.VC.14.29.16.11.ATL", false),
+            (
+                "Microsoft.VisualStudio.Component.VC.14.30.17.0.x86.x64",
+                false,
+            ),
+            ("Microsoft.VisualStudio.Component.VC.Tools.x86.x64", false),
+            ("Microsoft.VisualStudio.Component.Windows10SDK.14.29", false),
+        ];
+        for (id, expected) in table {
+            assert_eq!(is_toolset_component(id, "14.29"), expected, "{id}");
+        }
+    }
+
+    #[test]
+    fn directories() {
+        let root = TempDir::new("toolset");
+        let msvc = root.join("VC").join("Tools").join("MSVC");
+        std::fs::create_dir_all(msvc.join("14.29.30133")).unwrap();
+        std::fs::create_dir_all(msvc.join("14.38.33130")).unwrap();
+        std::fs::write(msvc.join("14.31.31103"), b"not a directory").unwrap();
+
+        assert!(dir_has_toolset(&msvc, "14.29"));
+        assert!(dir_has_toolset(&msvc, "14.38.33130"));
+        assert!(!dir_has_toolset(&msvc, "14.31"));
+        assert!(!dir_has_toolset(&msvc, "14.3"));
+        assert!(!dir_has_toolset(&root.join("missing"), "14.29"));
+    }
+}
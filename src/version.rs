@@ -0,0 +1,350 @@
+//! Version numbers as used by Visual Studio setup.
+
+use core::fmt;
+use core::ops::RangeInclusive;
+use core::str::FromStr;
+
+use crate::{E_INVALIDARG, HRESULT};
+
+/// A version packed into a `u64` as four 16-bit components, as returned by
+/// [`SetupHelper::parse_version`](crate::SetupHelper::parse_version).
+///
+/// The major version is in the highest bits so packed versions compare in version order.
+///
+/// ```
+/// use vssetup::PackedVersion;
+///
+/// let version: PackedVersion = "17.9.34723.18".parse().unwrap();
+/// assert_eq!(version.major(), 17);
+/// assert_eq!(format!("{version}"), "17.9.34723.18");
+/// assert!(version < "17.10".parse().unwrap());
+/// ```
+#[repr(transparent)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct PackedVersion(u64);
+
+impl PackedVersion {
+    pub const MIN: Self = Self(0);
+    pub const MAX: Self = Self(u64::MAX);
+
+    pub const fn new(major: u16, minor: u16, build: u16, revision: u16) -> Self {
+        Self((major as u64) << 48 | (minor as u64) << 32 | (build as u64) << 16 | revision as u64)
+    }
+
+    /// Wrap a `u64` from the setup API.
+    pub const fn from_bits(bits: u64) -> Self {
+        Self(bits)
+    }
+
+    /// The `u64` as used by the setup API.
+    pub const fn to_bits(self) -> u64 {
+        self.0
+    }
+
+    pub const fn major(self) -> u16 {
+        (self.0 >> 48) as u16
+    }
+
+    pub const fn minor(self) -> u16 {
+        (self.0 >> 32) as u16
+    }
+
+    pub const fn build(self) -> u16 {
+        (self.0 >> 16) as u16
+    }
+
+    pub const fn revision(self) -> u16 {
+        self.0 as u16
+    }
+
+    /// Parse a version range the same way as
+    /// [`SetupHelper::parse_version_range`](crate::SetupHelper::parse_version_range).
+    ///
+    /// - A single version, e.g. `17.0`, is a minimum with no maximum.
+    /// - `[17.0]` is exactly that version.
+    /// - `[16.0,17.0)` has an inclusive (`[`, `]`) or exclusive (`(`, `)`) bound at each end.
+    ///   Either bound can be left empty, e.g. `[16.0,)`.
+    ///
+    /// Exclusive bounds are converted to the nearest inclusive version so the result is always inclusive.
+    ///
+    /// Returns `E_INVALIDARG` if the range is malformed or empty.
+    ///
+    /// ```
+    /// use vssetup::PackedVersion;
+    ///
+    /// let vs2019 = PackedVersion::parse_range("[16.0,17.0)").unwrap();
+    /// assert!(vs2019.contains(&"16.11.34601.136".parse().unwrap()));
+    /// assert!(!vs2019.contains(&"17.0".parse().unwrap()));
+    /// ```
+    pub fn parse_range(range: &str) -> Result<RangeInclusive<Self>, HRESULT> {
+        let range = range.trim();
+        let (Some(open), Some(close)) = (range.chars().next(), range.chars().next_back()) else {
+            return Err(E_INVALIDARG);
+        };
+        if !matches!(open, '[' | '(') {
+            return Ok(range.parse()?..=Self::MAX);
+        }
+        if !matches!(close, ']' | ')') || range.len() < 2 {
+            return Err(E_INVALIDARG);
+        }
+        let inner = &range[1..range.len() - 1];
+        let (min, max) = match inner.split_once(',') {
+            Some((min, max)) => (min.trim(), max.trim()),
+            // `[17.0]` is an exact version. `(17.0)` can't contain anything.
+            None if open == '[' && close == ']' && !inner.trim().is_empty() => {
+                (inner.trim(), inner.trim())
+            }
+            None => return Err(E_INVALIDARG),
+        };
+        let min = match (min, open) {
+            ("", _) => Self::MIN,
+            (min, '[') => min.parse()?,
+            (min, _) => Self::from_bits(min.parse::<Self>()?.0.checked_add(1).ok_or(E_INVALIDARG)?),
+        };
+        let max = match (max, close) {
+            ("", _) => Self::MAX,
+            (max, ']') => max.parse()?,
+            (max, _) => Self::from_bits(max.parse::<Self>()?.0.checked_sub(1).ok_or(E_INVALIDARG)?),
+        };
+        if min > max {
+            return Err(E_INVALIDARG);
+        }
+        Ok(min..=max)
+    }
+}
+
+impl fmt::Display for PackedVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}.{}.{}.{}",
+            self.major(),
+            self.minor(),
+            self.build(),
+            self.revision()
+        )
+    }
+}
+
+/// Parses one to four dot-separated components, the same as the setup API.
+/// Missing components are zero.
+///
+/// Returns `E_INVALIDARG` if a component is empty, isn't a number or doesn't fit in a `u16`.
+impl FromStr for PackedVersion {
+    type Err = HRESULT;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut components = [0; 4];
+        let mut parts = s.split('.');
+        for (component, part) in components.iter_mut().zip(&mut parts) {
+            if part.is_empty() || !part.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(E_INVALIDARG);
+            }
+            *component = part.parse().map_err(|_| E_INVALIDARG)?;
+        }
+        if parts.next().is_some() {
+            return Err(E_INVALIDARG);
+        }
+        let [major, minor, build, revision] = components;
+        Ok(Self::new(major, minor, build, revision))
+    }
+}
+
+/// A version with four numeric components, e.g. `17.9.34723.18`.
+///
+/// This is the unpacked form of [`PackedVersion`]. Both have the same range and order,
+/// so converting between them never fails.
+///
+/// ```
+/// use vssetup::Version;
+///
+/// let version: Version = "17.10".parse().unwrap();
+/// assert_eq!(version.major, 17);
+/// assert!(version > "17.9.34723.18".parse().unwrap());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Version {
+    pub major: u16,
+    pub minor: u16,
+    pub build: u16,
+    pub revision: u16,
+}
+
+impl Version {
+    pub const fn new(major: u16, minor: u16, build: u16, revision: u16) -> Self {
+        Self {
+            major,
+            minor,
+            build,
+            revision,
+        }
+    }
+}
+
+impl From<PackedVersion> for Version {
+    fn from(packed: PackedVersion) -> Self {
+        Self::new(
+            packed.major(),
+            packed.minor(),
+            packed.build(),
+            packed.revision(),
+        )
+    }
+}
+
+impl From<Version> for PackedVersion {
+    fn from(version: Version) -> Self {
+        Self::new(
+            version.major,
+            version.minor,
+            version.build,
+            version.revision,
+        )
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        PackedVersion::from(*self).fmt(f)
+    }
+}
+
+/// Parses the same as [`PackedVersion`].
+impl FromStr for Version {
+    type Err = HRESULT;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse::<PackedVersion>().map(Self::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    extern crate std;
+    use std::format;
+
+    #[test]
+    fn components() {
+        let version = PackedVersion::new(17, 9, 34723, 18);
+        assert_eq!(version.to_bits(), 0x0011_0009_87A3_0012);
+        assert_eq!(PackedVersion::from_bits(version.to_bits()), version);
+        assert_eq!(
+            (
+                version.major(),
+                version.minor(),
+                version.build(),
+                version.revision()
+            ),
+            (17, 9, 34723, 18)
+        );
+        assert_eq!(format!("{}", PackedVersion::MAX), "65535.65535.65535.65535");
+    }
+
+    #[test]
+    fn round_trip() {
+        let table = [
+            "15.9.28307.2094",
+            "16.11.34601.136",
+            "17.9.34723.18",
+            "17.10.35013.160",
+            "18.0.11010.2",
+            "0.0.0.0",
+            "65535.65535.65535.65535",
+        ];
+        for s in table {
+            let version: PackedVersion = s.parse().unwrap();
+            assert_eq!(format!("{version}"), s);
+        }
+    }
+
+    #[test]
+    fn parsing() {
+        assert_eq!("17".parse(), Ok(PackedVersion::new(17, 0, 0, 0)));
+        assert_eq!("17.9".parse(), Ok(PackedVersion::new(17, 9, 0, 0)));
+        assert_eq!("17.9.1".parse(), Ok(PackedVersion::new(17, 9, 1, 0)));
+        for bad in [
+            "",
+            ".",
+            "17.",
+            ".9",
+            "17..9",
+            "1.2.3.4.5",
+            "65536",
+            "17.x",
+            "+17",
+            "-1",
+            " 17",
+        ] {
+            assert_eq!(bad.parse::<PackedVersion>(), Err(E_INVALIDARG), "{bad}");
+        }
+    }
+
+    #[test]
+    fn ranges() {
+        let v = |s: &str| s.parse::<PackedVersion>().unwrap();
+        let table = [
+            ("17.0", v("17.0")..=PackedVersion::MAX),
+            ("[17.0]", v("17.0")..=v("17.0")),
+            ("[16.0,17.0]", v("16.0")..=v("17.0")),
+            ("[16.0,17.0)", v("16.0")..=v("16.65535.65535.65535")),
+            ("(16.0,17.0]", v("16.0.0.1")..=v("17.0")),
+            ("(16.0,17.0)", v("16.0.0.1")..=v("16.65535.65535.65535")),
+            ("[16.0,)", v("16.0")..=PackedVersion::MAX),
+            ("[,17.0)", PackedVersion::MIN..=v("16.65535.65535.65535")),
+            ("[,]", PackedVersion::MIN..=PackedVersion::MAX),
+            (" [ 16.0 , 17.0 ) ", v("16.0")..=v("16.65535.65535.65535")),
+        ];
+        for (s, expected) in table {
+            assert_eq!(PackedVersion::parse_range(s), Ok(expected), "{s}");
+        }
+        for bad in [
+            "",
+            "[",
+            "[]",
+            "(17.0)",
+            "[17.0",
+            "16.0,17.0",
+            "[16.0,17.0,18.0]",
+            "[17.0,16.0]",
+            "(17.0,17.0)",
+            "[17.x,18.0]",
+            "(65535.65535.65535.65535,]",
+            "[,0.0)",
+        ] {
+            assert_eq!(PackedVersion::parse_range(bad), Err(E_INVALIDARG), "{bad}");
+        }
+    }
+
+    #[test]
+    fn unpacked() {
+        let table = [
+            "15.9.28307.2094",
+            "17.9.34723.18",
+            "17.10",
+            "0.0.0.0",
+            "65535.65535.65535.65535",
+        ];
+        for s in table {
+            let packed: PackedVersion = s.parse().unwrap();
+            let version: Version = s.parse().unwrap();
+            assert_eq!(Version::from(packed), version);
+            assert_eq!(PackedVersion::from(version), packed);
+            assert_eq!(format!("{version}"), format!("{packed}"));
+        }
+        assert_eq!("1.2.3.4".parse(), Ok(Version::new(1, 2, 3, 4)));
+        assert_eq!("1.2.3.4.5".parse::<Version>(), Err(E_INVALIDARG));
+        // Field order matches the packed order.
+        let parse = |s: &str| s.parse::<Version>().unwrap();
+        assert!(parse("17.9") < parse("17.10"));
+        assert!(parse("16.11.34601.136") < parse("17.0"));
+    }
+
+    #[test]
+    fn ordering() {
+        let parse = |s: &str| s.parse::<PackedVersion>().unwrap();
+        assert!(parse("17.9") < parse("17.10"));
+        assert!(parse("16.11.34601.136") < parse("17.0"));
+        assert!(parse("17.0.0.1") > parse("17.0"));
+    }
+}
@@ -0,0 +1,340 @@
+//! Output in the same JSON format as `vswhere -format json`.
+//!
+//! Scripts that already parse `vswhere`'s output can use this crate as a drop-in data source:
+//!
+//! ```
+//! use vssetup::{SetupConfiguration, com, vswhere};
+//!
+//! fn main() -> Result<(), vssetup::HRESULT> {
+//!     com::initialize()?;
+//!     let setup = SetupConfiguration::new()?;
+//!     let instances: Vec<_> = setup.enum_all_instances()?.collect();
+//!     println!("{}", vswhere::to_json(&instances));
+//!     Ok(())
+//! }
+//! ```
+//!
+//! As with `vswhere`, a property is left out if setup fails to return it,
+//! e.g. `productPath` for an instance that has no product.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+use crate::strings::bstr_to_string_lossy;
+use crate::{BSTR, HRESULT, InstanceState, Lcid, SetupInstance, SetupPropertyStore, Variant};
+
+/// Renders `instances` as a JSON array, exactly as `vswhere -format json` would.
+///
+/// This includes the `catalog` and `properties` objects, and the extra properties newer
+/// versions of setup make available on the instance itself (e.g. `channelId`).
+/// Keys are in the same order as `vswhere`'s: the properties every instance has come first,
+/// then the others sorted by name.
+pub fn to_json<'a>(instances: impl IntoIterator<Item = &'a SetupInstance>) -> String {
+    let mut json = Json::default();
+    json.begin('[');
+    for instance in instances {
+        json.element();
+        write_instance(&mut json, instance);
+    }
+    json.end(']');
+    json.out.push('\n');
+    json.out
+}
+
+fn write_instance(json: &mut Json, instance: &SetupInstance) {
+    let string = |bstr: Result<BSTR, HRESULT>| bstr.map(|b| bstr_to_string_lossy(&b));
+    json.begin('{');
+    json.keys.clear();
+    json.member_string("instanceId", string(instance.GetInstanceId()));
+    if let Ok(date) = instance.GetInstallDate() {
+        json.member("installDate");
+        json.string(&format!("{date}"));
+    }
+    json.member_string("installationName", string(instance.GetInstallationName()));
+    json.member_string("installationPath", string(instance.GetInstallationPath()));
+    json.member_string(
+        "installationVersion",
+        string(instance.GetInstallationVersion()),
+    );
+    if let Ok(Some(product)) = instance.GetProduct() {
+        json.member_string("productId", string(product.GetId()));
+    }
+    json.member_string("productPath", string(instance.GetProductPath()));
+    let state = instance.GetState();
+    if let Ok(state) = state {
+        json.member("state");
+        json.number(state.to_raw());
+    }
+    json.member_bool("isComplete", instance.IsComplete());
+    json.member_bool("isLaunchable", instance.IsLaunchable());
+    let catalog = instance.to_catalog();
+    if let Ok(catalog) = &catalog {
+        json.member_bool("isPrerelease", catalog.IsPrerelease());
+    }
+    if let Ok(state) = state {
+        let no_reboot = state.to_raw() & InstanceState::eNoRebootRequired.to_raw();
+        json.member_bool("isRebootRequired", Ok(no_reboot == 0));
+    }
+    json.member_string(
+        "displayName",
+        string(instance.GetDisplayName(Lcid::USER_DEFAULT)),
+    );
+    json.member_string(
+        "description",
+        string(instance.GetDescription(Lcid::USER_DEFAULT)),
+    );
+
+    // The engine path is sorted in with the properties from the instance's own store.
+    let mut properties = Vec::new();
+    if let Ok(path) = instance.GetEnginePath() {
+        properties.push((String::from("enginePath"), Variant::Bstr(path)));
+    }
+    if let Ok(store) = instance.to_property_store() {
+        read_properties(&store, &mut properties);
+    }
+    properties.retain(|(name, _)| !json.keys.iter().any(|key| key.eq_ignore_ascii_case(name)));
+    write_properties(json, properties);
+
+    if let Ok(Some(store)) = catalog.and_then(|catalog| catalog.GetCatalogInfo()) {
+        json.member("catalog");
+        json.begin('{');
+        let mut properties = Vec::new();
+        read_properties(&store, &mut properties);
+        write_properties(json, properties);
+        json.end('}');
+    }
+    if let Ok(Some(store)) = instance.GetProperties() {
+        json.member("properties");
+        json.begin('{');
+        let mut properties = Vec::new();
+        read_properties(&store, &mut properties);
+        write_properties(json, properties);
+        json.end('}');
+    }
+    json.end('}');
+}
+
+/// Adds each property in `store` to `properties`, with its name in camel case.
+fn read_properties(store: &SetupPropertyStore, properties: &mut Vec<(String, Variant)>) {
+    let Ok(names) = store.GetNames() else {
+        return;
+    };
+    for name in &names {
+        if let Ok(value) = store.GetValue(name) {
+            properties.push((camel_case(&bstr_to_string_lossy(name)), value));
+        }
+    }
+}
+
+/// Writes `properties` sorted by name, ignoring ASCII case.
+///
+/// If there's more than one property with the same name, only the first is written.
+fn write_properties(json: &mut Json, mut properties: Vec<(String, Variant)>) {
+    // The sort is stable, so it's the first of any duplicates that's kept.
+    properties.sort_by_cached_key(|(name, _)| name.to_ascii_lowercase());
+    properties.dedup_by(|(later, _), (first, _)| later.eq_ignore_ascii_case(first));
+    for (name, value) in properties {
+        match value {
+            Variant::Bstr(bstr) => json.member_string(&name, Ok(bstr_to_string_lossy(&bstr))),
+            Variant::Bool(bool) => json.member_bool(&name, Ok(bool)),
+            Variant::Signed(i64) => {
+                json.member(&name);
+                json.number(i64);
+            }
+            Variant::Unsigned(u64) => {
+                json.member(&name);
+                json.number(u64);
+            }
+            value @ (Variant::FileTime(_) | Variant::Date(_)) => {
+                json.member_string(&name, Ok(format!("{value}")))
+            }
+            Variant::StringArray(strings) => {
+                json.member(&name);
+                json.begin('[');
+                for bstr in &strings {
+                    json.element();
+                    json.string(&bstr_to_string_lossy(bstr));
+                }
+                json.end(']');
+            }
+            Variant::Empty | Variant::Null | Variant::Unknown => {}
+        }
+    }
+}
+
+/// Lowercases the first letter, as `vswhere` does for property names.
+fn camel_case(name: &str) -> String {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => first.to_lowercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// A minimal writer for `vswhere`'s layout: two space indents and one member per line.
+#[derive(Default)]
+struct Json {
+    out: String,
+    depth: usize,
+    /// Whether the current object or array has nothing in it yet.
+    empty: bool,
+    /// The keys written since the current instance began, so its properties don't repeat them.
+    keys: Vec<String>,
+}
+
+impl Json {
+    fn begin(&mut self, open: char) {
+        self.out.push(open);
+        self.depth += 1;
+        self.empty = true;
+    }
+
+    fn end(&mut self, close: char) {
+        self.depth -= 1;
+        if !self.empty {
+            self.newline();
+        }
+        self.out.push(close);
+        self.empty = false;
+    }
+
+    fn newline(&mut self) {
+        self.out.push('\n');
+        for _ in 0..self.depth {
+            self.out.push_str("  ");
+        }
+    }
+
+    fn element(&mut self) {
+        if !self.empty {
+            self.out.push(',');
+        }
+        self.empty = false;
+        self.newline();
+    }
+
+    fn member(&mut self, key: &str) {
+        self.element();
+        self.string(key);
+        self.out.push_str(": ");
+        self.keys.push(String::from(key));
+    }
+
+    fn member_string(&mut self, key: &str, value: Result<String, HRESULT>) {
+        if let Ok(value) = value {
+            self.member(key);
+            self.string(&value);
+        }
+    }
+
+    fn member_bool(&mut self, key: &str, value: Result<bool, HRESULT>) {
+        if let Ok(value) = value {
+            self.member(key);
+            self.out.push_str(if value { "true" } else { "false" });
+        }
+    }
+
+    fn number(&mut self, value: impl core::fmt::Display) {
+        let _ = write!(self.out, "{value}");
+    }
+
+    fn string(&mut self, value: &str) {
+        self.out.push('"');
+        for c in value.chars() {
+            match c {
+                '"' => self.out.push_str("\\\""),
+                '\\' => self.out.push_str("\\\\"),
+                '\n' => self.out.push_str("\\n"),
+                '\r' => self.out.push_str("\\r"),
+                '\t' => self.out.push_str("\\t"),
+                c if c < ' ' => {
+                    let _ = write!(self.out, "\\u{:04x}", c as u32);
+                }
+                c => self.out.push(c),
+            }
+        }
+        self.out.push('"');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn camel_case_names() {
+        let table = [
+            ("CampaignId", "campaignId"),
+            ("nickname", "nickname"),
+            ("ÜberId", "überId"),
+            ("", ""),
+        ];
+        for (name, expected) in table {
+            assert_eq!(camel_case(name), expected);
+        }
+    }
+
+    /// The keys of each instance's object in the fixture, in order.
+    fn fixture_keys() -> Vec<&'static str> {
+        include_str!("../tests/fixtures/vswhere.json")
+            .lines()
+            .filter_map(|line| line.strip_prefix("    \"")?.split_once('"'))
+            .map(|(key, _)| key)
+            .collect()
+    }
+
+    #[test]
+    fn property_order() {
+        let keys = fixture_keys();
+        let start = keys.iter().position(|&key| key == "description").unwrap() + 1;
+        let end = keys.iter().position(|&key| key == "catalog").unwrap();
+        let expected = &keys[start..end];
+
+        // As they might come from setup, with the engine path first and a duplicate.
+        let properties = expected
+            .iter()
+            .rev()
+            .chain(["EnginePath"].iter())
+            .map(|&name| (String::from(name), Variant::Bool(true)))
+            .collect();
+        let mut json = Json::default();
+        json.begin('{');
+        write_properties(&mut json, properties);
+        json.end('}');
+        assert_eq!(json.keys, expected);
+    }
+
+    #[test]
+    fn layout() {
+        let mut json = Json::default();
+        json.begin('[');
+        json.end(']');
+        assert_eq!(json.out, "[]");
+
+        let mut json = Json::default();
+        json.begin('[');
+        json.element();
+        json.begin('{');
+        json.member_string("path", Ok(String::from("C:\\VS \"1\"\n\u{1}")));
+        json.member_string("skipped", Err(crate::E_NOTFOUND));
+        json.member_bool("isComplete", Ok(true));
+        json.member("empty");
+        json.begin('{');
+        json.end('}');
+        json.member("state");
+        json.number(u32::MAX);
+        json.end('}');
+        json.end(']');
+        let expected = r#"[
+  {
+    "path": "C:\\VS \"1\"\n\u0001",
+    "isComplete": true,
+    "empty": {},
+    "state": 4294967295
+  }
+]"#;
+        assert_eq!(json.out, expected);
+    }
+}
@@ -0,0 +1,60 @@
+//! Canaries for the parts of the API that `unsafe` code downstream may rely on.
+//!
+//! These only need to compile. If one of them stops compiling then a change
+//! is breaking for unsafe callers, even if it looks harmless.
+
+use core::ffi::c_void;
+use core::mem::{align_of, size_of};
+use vssetup::*;
+
+// Unsafe constructors, with the exact signatures downstream code is expected to use.
+const _: for<'a> unsafe fn(&'a [u16]) -> WideStr<'a> = WideStr::from_slice_with_nul_unchecked;
+const _: unsafe fn(*const u16) -> Option<WideStr<'static>> = WideStr::from_ptr;
+const _: unsafe fn() = com::uninitialize;
+const _: unsafe fn(*mut c_void) -> SetupConfiguration = SetupConfiguration::from_raw;
+const _: unsafe fn(*mut c_void) -> EnumSetupInstances = EnumSetupInstances::from_raw;
+const _: unsafe fn(*mut c_void) -> SetupInstance = SetupInstance::from_raw;
+type Callback = fn() -> u32;
+const _: unsafe fn(Callback) -> Result<u32, HRESULT> = com::with_com::<u32, Callback>;
+
+// `InstanceState` is a transparent wrapper around the COM enum, which is an `i32`.
+const _: () = assert!(size_of::<InstanceState>() == size_of::<i32>());
+const _: () = assert!(align_of::<InstanceState>() == align_of::<i32>());
+
+// Every wrapper is a transparent COM pointer, which is never null.
+macro_rules! com_pointer {
+    ($($wrapper:ty),+) => {$(
+        const _: () = assert!(size_of::<$wrapper>() == size_of::<*mut c_void>());
+        const _: () = assert!(align_of::<$wrapper>() == align_of::<*mut c_void>());
+        const _: () = assert!(size_of::<Option<$wrapper>>() == size_of::<*mut c_void>());
+    )+};
+}
+com_pointer!(
+    SetupConfiguration,
+    EnumSetupInstances,
+    SetupInstance,
+    SetupProductReference,
+    SetupErrorState,
+    SetupErrorInfo,
+    SetupFailedPackageReference,
+    SetupPropertyStore,
+    SetupLocalizedProperties,
+    SetupLocalizedPropertyStore,
+    SetupPackageReference,
+    SetupInstanceCatalog,
+    SetupHelper,
+    SetupPolicy
+);
+
+// `PackedVersion` is passed to and from the setup API as a `u64`.
+const _: () = assert!(size_of::<PackedVersion>() == size_of::<u64>());
+
+// `FILETIME` matches the Win32 layout.
+const _: () = assert!(size_of::<FILETIME>() == 8);
+const _: () = assert!(align_of::<FILETIME>() == 4);
+
+#[test]
+fn wide_str_from_ptr() {
+    // SAFETY: a null pointer is allowed.
+    assert!(unsafe { WideStr::from_ptr(core::ptr::null()) }.is_none());
+}
@@ -0,0 +1,67 @@
+//! `SetupHelper` should agree with `PackedVersion`'s own parser.
+//!
+//! Skipped when setup isn't installed (or COM isn't available).
+
+use vssetup::hresults::E_INVALIDARG;
+use vssetup::{PackedVersion, SetupConfiguration, SetupHelper, Version, com};
+
+fn helper() -> Option<SetupHelper> {
+    let _ = com::initialize();
+    SetupConfiguration::new().ok()?.to_helper().ok()
+}
+
+fn wide(s: &str) -> Vec<u16> {
+    s.encode_utf16().chain([0]).collect()
+}
+
+#[test]
+fn parse_version() {
+    let Some(helper) = helper() else {
+        return;
+    };
+    for version in ["17", "17.9", "17.9.34723", "17.9.34723.18", "0.0.0.0"] {
+        let expected: PackedVersion = version.parse().unwrap();
+        let packed = helper.parse_version(&wide(version)[..]);
+        assert_eq!(packed, Ok(expected), "{version}");
+    }
+    for garbage in ["", "garbage", "17.x", "1.2.3.4.5", "65536"] {
+        let packed = helper.parse_version(&wide(garbage)[..]);
+        assert!(packed.is_err(), "{garbage}: {packed:?}");
+        assert_eq!(
+            garbage.parse::<PackedVersion>(),
+            Err(E_INVALIDARG),
+            "{garbage}"
+        );
+    }
+}
+
+#[test]
+fn parse_version_range() {
+    let Some(helper) = helper() else {
+        return;
+    };
+    for range in ["17.0", "[16.0,17.0)", "(16.0,17.0]", "[16.0,)", "[,17.0)"] {
+        let expected = PackedVersion::parse_range(range).unwrap();
+        let parsed = helper.parse_version_range(&wide(range)[..]);
+        assert_eq!(parsed, Ok(expected), "{range}");
+    }
+    for garbage in ["", "[17.0,16.0]", "[16.0,17.0"] {
+        let parsed = helper.parse_version_range(&wide(garbage)[..]);
+        assert!(parsed.is_err(), "{garbage}: {parsed:?}");
+    }
+}
+
+#[test]
+fn installation_versions() {
+    let Some(helper) = helper() else {
+        return;
+    };
+    let setup = SetupConfiguration::new().unwrap();
+    for instance in setup.enum_all_instances().unwrap() {
+        let version = instance.installation_version().unwrap();
+        let packed = helper
+            .parse_version(&instance.get_installation_version().unwrap())
+            .unwrap();
+        assert_eq!(version, Version::from(packed));
+    }
+}
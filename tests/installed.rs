@@ -0,0 +1,16 @@
+//! `installed` must never panic, whatever the machine has installed (or not).
+
+use vssetup::integrate::Arch;
+use vssetup::{PackedVersion, Requirements, installed, is_installer_available};
+
+#[test]
+fn live() {
+    let any = installed(Requirements::default());
+    let x64 = installed(Requirements::default().vc_tools(Arch::X64));
+    let future = installed(Requirements::default().min_version(PackedVersion::MAX));
+    // Stricter requirements can only reduce the matches.
+    assert!(any || !x64);
+    assert!(!future);
+    // Instances can only be found through the installer.
+    assert!(is_installer_available() || !any);
+}
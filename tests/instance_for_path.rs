@@ -0,0 +1,57 @@
+//! Looking up the instance a path belongs to on the real setup, with long and verbatim paths.
+//!
+//! Skipped when setup isn't installed (or COM isn't available).
+
+use std::ffi::OsString;
+use std::path::PathBuf;
+use vssetup::{SetupConfiguration, com};
+
+fn setup() -> Option<SetupConfiguration> {
+    let _ = com::initialize();
+    SetupConfiguration::new().ok()
+}
+
+#[test]
+fn installation_paths() {
+    let Some(setup) = setup() else {
+        return;
+    };
+    for instance in setup.EnumAllInstances().unwrap() {
+        let path = instance.installation_path().unwrap();
+        let found = setup.instance_for_path(&path).unwrap().unwrap();
+        assert_eq!(found.instance_id(), instance.instance_id(), "{path:?}");
+    }
+}
+
+#[test]
+fn outside_any_instance() {
+    let Some(setup) = setup() else {
+        return;
+    };
+    let temp = std::env::temp_dir();
+    let mut long = temp.clone();
+    while long.as_os_str().len() <= 260 {
+        long.push("not-visual-studio");
+    }
+    // `push` would replace the prefix, as `long` is absolute.
+    let mut verbatim = OsString::from(r"\\?\");
+    verbatim.push(&long);
+    let verbatim = PathBuf::from(verbatim);
+    for path in [temp, long, verbatim] {
+        let found = setup.instance_for_path(&path);
+        assert!(matches!(found, Ok(None)), "{path:?}: {found:?}");
+    }
+}
+
+#[test]
+fn current_exe() {
+    let Some(setup) = setup() else {
+        return;
+    };
+    // Test binaries aren't part of any instance, but the path must still be accepted.
+    let exe = std::env::current_exe().unwrap();
+    let found = setup.instance_for_path(&exe);
+    assert!(matches!(found, Ok(None)), "{exe:?}: {found:?}");
+    let found = setup.instance_for_current_process();
+    assert!(matches!(found, Ok(None)), "{found:?}");
+}
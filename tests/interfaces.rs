@@ -0,0 +1,195 @@
+//! Checks the `com_interface!` declarations in `src/raw.rs` against `interfaces.txt`,
+//! which is transcribed from the official `Setup.Configuration.h`.
+//!
+//! A wrong IID or a method out of vtable order still compiles, so this catches
+//! drift that would otherwise only show up as a crash at runtime.
+
+const RAW: &str = include_str!("../src/raw.rs");
+const IDL: &str = include_str!("interfaces.txt");
+
+#[derive(Debug, PartialEq)]
+struct Method {
+    name: String,
+    params: Vec<String>,
+}
+
+#[derive(Debug, PartialEq)]
+struct Interface {
+    name: String,
+    iid: String,
+    parent: String,
+    methods: Vec<Method>,
+}
+
+/// Maps a parameter type from the header to its type in `raw.rs`.
+fn rust_type(idl: &str) -> String {
+    match idl {
+        "BSTR*" => "*mut BSTR".into(),
+        "LPFILETIME" => "*mut FILETIME".into(),
+        "LCID" => "LCID".into(),
+        "LPCOLESTR" => "LPCOLESTR".into(),
+        "LPCWSTR" => "*const u16".into(),
+        "ULONG" => "u32".into(),
+        "ULONG*" => "*mut u32".into(),
+        "ULONGLONG*" => "*mut u64".into(),
+        "HRESULT*" => "*mut HRESULT".into(),
+        "VARIANT_BOOL*" => "*mut VARIANT_BOOL".into(),
+        "LPVARIANT" => "*mut VARIANT".into(),
+        "LPSAFEARRAY*" => "*mut *mut SAFEARRAY".into(),
+        "InstanceState*" => "*mut InstanceState".into(),
+        _ => match idl.strip_suffix("**") {
+            Some(interface) => format!("*mut Option<interface::{interface}>"),
+            None => panic!("interfaces.txt: unknown parameter type `{idl}`"),
+        },
+    }
+}
+
+/// Normalizes a GUID to uppercase hex digits only.
+fn guid_digits(guid: &str) -> String {
+    let digits: String = guid
+        .trim_start_matches("0x")
+        .chars()
+        .filter(|c| !matches!(c, '_' | '-'))
+        .collect();
+    assert_eq!(digits.len(), 32, "malformed GUID `{guid}`");
+    digits.to_ascii_uppercase()
+}
+
+fn parse_idl() -> (String, Vec<Interface>) {
+    let mut clsid = None;
+    let mut interfaces: Vec<Interface> = Vec::new();
+    for line in IDL.lines() {
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(rest) = line.strip_prefix("coclass SetupConfiguration ") {
+            clsid = Some(guid_digits(rest.trim()));
+        } else if let Some(rest) = line.strip_prefix("interface ") {
+            let (head, parent) = rest.split_once(" : ").expect("interface without a parent");
+            let (name, iid) = head.split_once(' ').expect("interface without an IID");
+            interfaces.push(Interface {
+                name: name.into(),
+                iid: guid_digits(iid),
+                parent: parent.trim().into(),
+                methods: Vec::new(),
+            });
+        } else {
+            let (name, params) = line.trim().split_once('(').expect("malformed method");
+            let params = params.strip_suffix(')').expect("malformed method");
+            let params = params
+                .split(',')
+                .map(str::trim)
+                .filter(|p| !p.is_empty())
+                .map(rust_type)
+                .collect();
+            let interface = interfaces.last_mut().expect("method outside an interface");
+            interface.methods.push(Method {
+                name: name.into(),
+                params,
+            });
+        }
+    }
+    (clsid.expect("no coclass in interfaces.txt"), interfaces)
+}
+
+/// Collapses whitespace so multi-line declarations compare equal to single-line ones.
+fn normalize(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn parse_raw() -> (String, Vec<Interface>) {
+    let (_, clsid) = RAW
+        .split_once("SETUP_CONFIGURATION_CLSID: GUID = GUID::from_u128(")
+        .expect("no SETUP_CONFIGURATION_CLSID in raw.rs");
+    let clsid = guid_digits(clsid.split_once(')').unwrap().0);
+
+    let (_, decls) = RAW
+        .split_once("\ncom_interface!(")
+        .expect("no com_interface! invocation in raw.rs");
+    let (decls, _) = decls.split_once("\n);").unwrap();
+
+    let mut interfaces = Vec::new();
+    for decl in decls.split("#[interface(").skip(1) {
+        let (iid, rest) = decl.split_once(")]").unwrap();
+        let (_, rest) = rest.split_once("interface ").unwrap();
+        let (name, rest) = rest.split_once(':').unwrap();
+        let (parent, rest) = rest.split_once('{').unwrap();
+        let (body, _) = rest.split_once('}').unwrap();
+
+        let methods = body
+            .split(';')
+            .map(normalize)
+            .filter(|m| !m.is_empty())
+            .map(|m| {
+                let m = m.strip_prefix("pub fn ").expect("malformed method");
+                let (name, rest) = m.split_once('(').unwrap();
+                let (params, _) = rest.rsplit_once(") -> HRESULT").unwrap();
+                let params = params
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|p| !p.is_empty() && *p != "&self")
+                    .map(|p| p.split_once(':').unwrap().1.trim().into())
+                    .collect();
+                Method {
+                    name: name.into(),
+                    params,
+                }
+            })
+            .collect();
+        interfaces.push(Interface {
+            name: name.trim().into(),
+            iid: guid_digits(iid.trim()),
+            parent: parent.trim().into(),
+            methods,
+        });
+    }
+    (clsid, interfaces)
+}
+
+#[test]
+fn matches_header() {
+    let (expected_clsid, expected) = parse_idl();
+    let (actual_clsid, actual) = parse_raw();
+
+    let mut errors = Vec::new();
+    if expected_clsid != actual_clsid {
+        errors.push(format!(
+            "SETUP_CONFIGURATION_CLSID: expected {expected_clsid}, found {actual_clsid}"
+        ));
+    }
+    for want in &expected {
+        let Some(have) = actual.iter().find(|i| i.name == want.name) else {
+            errors.push(format!("{}: missing from raw.rs", want.name));
+            continue;
+        };
+        if want.iid != have.iid {
+            errors.push(format!(
+                "{}: expected IID {}, found {}",
+                want.name, want.iid, have.iid
+            ));
+        }
+        if want.parent != have.parent {
+            errors.push(format!(
+                "{}: expected parent {}, found {}",
+                want.name, want.parent, have.parent
+            ));
+        }
+        let slots = want.methods.len().max(have.methods.len());
+        for slot in 0..slots {
+            match (want.methods.get(slot), have.methods.get(slot)) {
+                (Some(w), Some(h)) if w == h => {}
+                (w, h) => errors.push(format!(
+                    "{}: method {slot}: expected {w:?}, found {h:?}",
+                    want.name
+                )),
+            }
+        }
+    }
+    for have in &actual {
+        if !expected.iter().any(|i| i.name == have.name) {
+            errors.push(format!("{}: not in interfaces.txt", have.name));
+        }
+    }
+
+    assert!(errors.is_empty(), "\n{}", errors.join("\n"));
+}
@@ -0,0 +1,34 @@
+//! Localized properties of a real instance.
+//!
+//! Skipped when setup isn't installed (or COM isn't available) or there are no instances.
+
+use vssetup::{SetupConfiguration, SetupInstance, com};
+
+const EN_US: u32 = 1033;
+
+fn first_instance() -> Option<SetupInstance> {
+    let _ = com::initialize();
+    SetupConfiguration::new()
+        .ok()?
+        .enum_instances()
+        .ok()?
+        .next()
+}
+
+#[test]
+fn names_for_en_us() {
+    let Some(instance) = first_instance() else {
+        return;
+    };
+    let localized = instance.to_localized_properties().unwrap();
+    let Some(store) = localized.get_localized_properties().unwrap() else {
+        return;
+    };
+    let names = store.get_names(EN_US).unwrap();
+    assert!(!names.is_empty());
+    for name in names.iter() {
+        assert!(!name.is_empty());
+        // Every listed name should have a value.
+        assert!(store.get_value(name, EN_US).is_ok(), "{name}");
+    }
+}
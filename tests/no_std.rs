@@ -0,0 +1,15 @@
+//! Uses only the parts of the API that are available without the `std` feature.
+//!
+//! CI runs this both with and without default features.
+
+use vssetup::{HRESULT, WideStr, wide_str};
+
+#[test]
+fn wide_str_without_std() -> Result<(), HRESULT> {
+    let literal = wide_str!("VC");
+    let slice = WideStr::from_slice_with_nul(&[b'V' as u16, b'C' as u16, 0])?;
+    assert!(literal == slice);
+    assert!(WideStr::from_slice_until_nul(&[b'V' as u16, 0, 0]).is_ok());
+    assert!(WideStr::from_slice_with_nul(&[b'V' as u16, 0, b'C' as u16, 0]).is_err());
+    Ok(())
+}
@@ -0,0 +1,62 @@
+//! `vswhere::to_json` should have the same keys, in the same places, as `vswhere` itself.
+//!
+//! Skipped when setup or `vswhere.exe` isn't installed.
+
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::process::Command;
+
+use serde_json::Value;
+use vssetup::{SetupConfiguration, com, vswhere};
+
+fn run_vswhere() -> Option<Value> {
+    let program_files = std::env::var_os("ProgramFiles(x86)")?;
+    let exe = PathBuf::from(program_files).join(r"Microsoft Visual Studio\Installer\vswhere.exe");
+    let output = Command::new(exe)
+        .args(["-all", "-prerelease", "-format", "json", "-utf8"])
+        .output()
+        .ok()?;
+    serde_json::from_slice(&output.stdout).ok()
+}
+
+/// The keys of an object and, recursively, of any objects within it, e.g. `catalog.id`.
+fn keys(value: &Value, prefix: &str, out: &mut BTreeSet<String>) {
+    if let Value::Object(object) = value {
+        for (key, value) in object {
+            let key = format!("{prefix}{key}");
+            keys(value, &format!("{key}."), out);
+            out.insert(key);
+        }
+    }
+}
+
+#[test]
+fn same_shape_as_vswhere() {
+    let _ = com::initialize();
+    let Ok(setup) = SetupConfiguration::new() else {
+        return;
+    };
+    let Some(Value::Array(expected)) = run_vswhere() else {
+        return;
+    };
+    let instances: Vec<_> = setup.enum_all_instances().unwrap().collect();
+    let Value::Array(actual) = serde_json::from_str(&vswhere::to_json(&instances)).unwrap() else {
+        panic!("not an array");
+    };
+    assert_eq!(actual.len(), expected.len());
+    for expected in &expected {
+        let id = &expected["instanceId"];
+        let actual = actual
+            .iter()
+            .find(|actual| actual["instanceId"] == *id)
+            .unwrap();
+        assert_eq!(
+            actual["installationPath"], expected["installationPath"],
+            "{id}"
+        );
+        let (mut actual_keys, mut expected_keys) = (BTreeSet::new(), BTreeSet::new());
+        keys(actual, "", &mut actual_keys);
+        keys(expected, "", &mut expected_keys);
+        assert_eq!(actual_keys, expected_keys, "{id}");
+    }
+}